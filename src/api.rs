@@ -20,7 +20,7 @@ fn bump_clients() {
 }
 
 fn breaks_to_periods(breaks: &[proto::Break]) -> Vec<BreakPeriod> {
-    breaks.iter().map(|b| BreakPeriod { start_ts: b.start_ts, end_ts: b.end_ts }).collect()
+    breaks.iter().map(|b| BreakPeriod { start_ts: b.start_ts, end_ts: b.end_ts, label: b.label.clone(), interruption: b.interruption }).collect()
 }
 
 fn timer_to_response(t: &state::ActiveTimer) -> TimerResponse {
@@ -54,19 +54,21 @@ fn entry_to_response(e: &state::TimeEntry) -> EntryResponse {
         break_secs,
         todo_id: e.todo_id,
         last_modified: e.last_modified,
+        tags: e.tags.clone(),
     }
 }
 
 fn todo_to_response(conn: &Connection, t: &state::TodoItem) -> TodoResponse {
-    let entry_secs = state::get_todo_total_secs(conn, t.id);
-    let active_secs = state::get_active_todo_secs(conn, t.id);
+    let (_, _, total_secs) = state::get_todo_combined_secs(conn, t.id);
     TodoResponse {
         id: t.id,
         text: t.text.clone(),
         done: t.done,
         created_at: t.created_at,
         last_modified: t.last_modified,
-        total_secs: entry_secs + active_secs,
+        total_secs,
+        estimate_secs: t.estimate_secs,
+        archived: t.archived,
     }
 }
 
@@ -87,8 +89,7 @@ pub async fn start_timer(
     if let Some(running) = state::get_running(&conn) {
         let now_ts = Local::now().timestamp();
         let mut paused = running;
-        paused.state = "paused".into();
-        paused.breaks.push(proto::Break { start_ts: now_ts, end_ts: 0 });
+        paused.pause(now_ts, "");
         state::update_active(&conn, &paused);
     }
 
@@ -103,7 +104,7 @@ pub async fn start_timer(
         todo_id: req.todo_id,
         last_modified: 0,
     };
-    let id = state::insert_active(&conn, &timer);
+    let id = state::insert_active_from(&conn, &timer, None);
     let inserted = state::get_active_by_id(&conn, id).unwrap();
     drop(conn);
     bump_clients();
@@ -120,24 +121,8 @@ pub async fn stop_timer(
         return Err(StatusCode::CONFLICT);
     }
 
-    let now_ts = Local::now().timestamp();
-    let elapsed = now_ts - timer.started_at;
-    let break_secs = state::total_break_secs(&timer.breaks, now_ts);
-    let active_secs = (elapsed - break_secs).max(0);
-
-    let entry = state::TimeEntry {
-        id: 0,
-        name: timer.name.clone(),
-        category: timer.category.clone(),
-        started_at: timer.started_at,
-        ended_at: now_ts,
-        active_secs,
-        breaks: timer.breaks.clone(),
-        todo_id: timer.todo_id,
-        last_modified: 0,
-    };
-    state::insert_entry(&conn, &entry);
-    state::clear_active(&conn, id);
+    let entry = timer.to_entry(Local::now().timestamp(), vec![]);
+    state::finish_timer(&conn, id, &entry);
 
     let last = state::get_last_entry(&conn).unwrap();
     drop(conn);
@@ -156,8 +141,7 @@ pub async fn pause_timer(
     }
 
     let now_ts = Local::now().timestamp();
-    timer.state = "paused".into();
-    timer.breaks.push(proto::Break { start_ts: now_ts, end_ts: 0 });
+    timer.pause(now_ts, "");
     state::update_active(&conn, &timer);
 
     let updated = state::get_active_by_id(&conn, id).unwrap();
@@ -182,12 +166,7 @@ pub async fn resume_timer(
     }
 
     let now_ts = Local::now().timestamp();
-    timer.state = "running".into();
-    if let Some(last) = timer.breaks.last_mut() {
-        if last.end_ts == 0 {
-            last.end_ts = now_ts;
-        }
-    }
+    timer.resume(now_ts);
     state::update_active(&conn, &timer);
 
     let updated = state::get_active_by_id(&conn, id).unwrap();
@@ -204,17 +183,9 @@ pub async fn get_entries(
 ) -> Json<Vec<EntryResponse>> {
     let conn = db.lock().unwrap();
     let since_ts = if q.today.unwrap_or(false) {
-        Some(
-            Local::now()
-                .date_naive()
-                .and_hms_opt(0, 0, 0)
-                .unwrap()
-                .and_local_timezone(Local)
-                .unwrap()
-                .timestamp(),
-        )
+        Some(state::today_start_ts(&conn))
     } else if q.week.unwrap_or(false) {
-        Some((Local::now() - chrono::Duration::days(7)).timestamp())
+        Some(state::day_start_ts(state::this_week_start(&conn), state::day_start_hour(&conn)))
     } else {
         None
     };
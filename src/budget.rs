@@ -0,0 +1,107 @@
+use chrono::Local;
+use rusqlite::Connection;
+
+use crate::color;
+use crate::duration::format_duration;
+use crate::state::*;
+use crate::timer::truncate;
+
+pub fn set(conn: &Connection, category: &str, duration: &str) {
+    let secs = match parse_duration(duration) {
+        Some(s) if s > 0 => s,
+        _ => {
+            eprintln!("Invalid duration \"{duration}\" (try 5h, 2h30m or 90m).");
+            std::process::exit(1);
+        }
+    };
+    let category = canonical_category(conn, category);
+    if category.is_empty() {
+        eprintln!("Category must not be empty.");
+        std::process::exit(1);
+    }
+    set_budget(conn, &category, secs);
+    println!("Budget for \"{category}\" set to {} per week.", format_duration(secs));
+}
+
+pub fn clear(conn: &Connection, category: &str) {
+    if clear_budget(conn, category) {
+        println!("Budget for \"{}\" removed.", category.trim());
+    } else {
+        println!("No budget set for \"{}\".", category.trim());
+    }
+}
+
+pub fn list(conn: &Connection) {
+    if list_budgets(conn).is_empty() {
+        println!("No budgets set. Cap a category with `tl budget set email 5h`.");
+        return;
+    }
+    for line in status_lines(conn) {
+        println!("{line}");
+    }
+}
+
+/// Active seconds per category this week (from the configured week start),
+/// running and paused timers counted up to now.
+fn week_usage(conn: &Connection) -> Vec<(String, i64)> {
+    let since = day_start_ts(this_week_start(conn), day_start_hour(conn));
+    usage(conn, since, i64::MAX)
+}
+
+/// Active seconds per category from `since_ts` until `until_ts`, with running
+/// and paused timers counted up to now if that falls inside. Both `tl budget`
+/// and the budget column of weekly reports use this, so they agree.
+pub fn usage(conn: &Connection, since_ts: i64, until_ts: i64) -> Vec<(String, i64)> {
+    let now_ts = Local::now().timestamp();
+    let mut used: Vec<(String, i64)> = Vec::new();
+    let mut add = |cat: &str, secs: i64| match used.iter_mut().find(|(c, _)| c.eq_ignore_ascii_case(cat)) {
+        Some((_, s)) => *s += secs,
+        None => used.push((cat.to_string(), secs)),
+    };
+    for e in query_entries(conn, Some(since_ts)).into_iter().filter(|e| e.started_at < until_ts) {
+        add(&e.category, e.active_secs);
+    }
+    if now_ts < until_ts {
+        for t in get_all_active(conn) {
+            add(&t.category, active_secs_since(&t, since_ts, now_ts));
+        }
+    }
+    used
+}
+
+/// Seconds `category` used according to `usage`.
+pub fn used_by(used: &[(String, i64)], category: &str) -> i64 {
+    used.iter().find(|(c, _)| c.eq_ignore_ascii_case(category)).map_or(0, |(_, s)| *s)
+}
+
+/// One line per budget with this week's usage, e.g.
+/// `  email                4h 10m 00s / 5h 00m 00s  50m 00s left`.
+/// Empty when no budgets are configured.
+pub fn status_lines(conn: &Connection) -> Vec<String> {
+    let used = week_usage(conn);
+    let palette = color::Palette::load(conn);
+    list_budgets(conn)
+        .into_iter()
+        .map(|(cat, budget)| {
+            let secs = used_by(&used, &cat);
+            format!(
+                "  {} {} / {}  {}",
+                palette.paint(&cat, &format!("{:<20}", truncate(&cat, 19))),
+                format_duration(secs),
+                format_duration(budget),
+                remaining(budget, secs),
+            )
+        })
+        .collect()
+}
+
+/// How much of `budget` is left after `used`, or how far over it went (in
+/// red when printing to a terminal).
+pub fn remaining(budget: i64, used: i64) -> String {
+    if used > budget {
+        let over = format!("over by {}", format_duration(used - budget));
+        color::paint(31, &over)
+    } else {
+        format!("{} left", format_duration(budget - used))
+    }
+}
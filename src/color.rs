@@ -0,0 +1,88 @@
+//! Per-category colors for terminal output. A category shows in the color
+//! set with `tl config set category-color`, or else one picked from a hash
+//! of its name, so it looks the same in every command and on every run.
+
+use std::collections::HashMap;
+use std::io::IsTerminal;
+
+use rusqlite::Connection;
+
+use crate::state::*;
+
+/// Color names accepted by `tl config set category-color`, with their ANSI
+/// SGR codes. Categories without a color of their own get one of these too.
+pub const COLORS: &[(&str, u8)] = &[
+    ("red", 31),
+    ("green", 32),
+    ("yellow", 33),
+    ("blue", 34),
+    ("magenta", 35),
+    ("cyan", 36),
+    ("bright-red", 91),
+    ("bright-green", 92),
+    ("bright-yellow", 93),
+    ("bright-blue", 94),
+    ("bright-magenta", 95),
+    ("bright-cyan", 96),
+];
+
+/// Whether to emit colors: stdout is a terminal and `NO_COLOR` is unset.
+pub fn enabled() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none()
+}
+
+/// `text` wrapped in the ANSI color `code`, or as-is when colors are off.
+pub fn paint(code: u8, text: &str) -> String {
+    if enabled() {
+        format!("\x1b[{code}m{text}\x1b[0m")
+    } else {
+        text.to_string()
+    }
+}
+
+pub fn code_for(name: &str) -> Option<u8> {
+    COLORS.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, code)| *code)
+}
+
+/// Color for a category without one of its own, from an FNV-1a hash of its
+/// lowercased name (stable across builds, unlike `DefaultHasher`).
+fn hashed(category: &str) -> u8 {
+    let hash = category
+        .trim()
+        .to_lowercase()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |h, b| (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3));
+    COLORS[(hash % COLORS.len() as u64) as usize].1
+}
+
+/// The configured category colors, loaded once for commands that print many
+/// rows.
+pub struct Palette {
+    explicit: HashMap<String, u8>,
+}
+
+impl Palette {
+    pub fn load(conn: &Connection) -> Palette {
+        let explicit = list_category_colors(conn)
+            .into_iter()
+            .filter_map(|(cat, color)| Some((cat.to_lowercase(), code_for(&color)?)))
+            .collect();
+        Palette { explicit }
+    }
+
+    /// `text` (e.g. a padded table cell) in `category`'s color.
+    pub fn paint(&self, category: &str, text: &str) -> String {
+        let code = self.explicit.get(&category.trim().to_lowercase()).copied().unwrap_or_else(|| hashed(category));
+        paint(code, text)
+    }
+
+    /// The category name in its own color.
+    pub fn category(&self, category: &str) -> String {
+        self.paint(category, category)
+    }
+}
+
+/// `category` in its color, for a one-off line.
+pub fn category(conn: &Connection, category: &str) -> String {
+    Palette::load(conn).category(category)
+}
@@ -0,0 +1,205 @@
+use rusqlite::Connection;
+
+use crate::color;
+use crate::state::*;
+
+/// Settings exposed through `tl config`, with a short description each.
+const KEYS: &[(&str, &str)] = &[
+    ("category-color", "Color for a category wherever it's shown: <category> <color>; others get one from their name"),
+    ("day-start", "Hour a tracking day begins, for --today and daily reports: 0-23 (default 0, midnight)"),
+    ("duration-format", "How durations are shown: hms (1h 23m 45s, default), clock (1:23:45) or decimal hours (1.40)"),
+    ("lowercase-categories", "Store categories in lowercase, so \"Email\" and \"email\" can't become two: on or off (default)"),
+    ("long-break", "Pause length after which `tl resume` offers to correct the break (default 12h)"),
+    ("max-duration", "Longest a single timer may run (active time) before tl offers to cap it, e.g. 10h"),
+    ("notifications", "Desktop notifications when a timer stops or the daily goal is reached: on or off"),
+    ("sleep-gap", "Offer to turn a gap this long since tl last ran, with a timer running, into a break (default 4h, 0 = off)"),
+    ("time-format", "How times of day are shown: 24h (default) or 12h with am/pm"),
+    ("week-start", "First day of the week for --week and reports: monday (default) or sunday"),
+];
+
+pub fn list(conn: &Connection) {
+    for (key, help) in KEYS {
+        let value = current(conn, key).unwrap_or_else(|| "(not set)".into());
+        println!("{key:<16} {value}");
+        println!("{:<16} {help}", "");
+    }
+}
+
+/// Change a setting. Every key takes one value except `category-color`,
+/// which takes a category (possibly several words) followed by a color.
+pub fn set(conn: &Connection, key: &str, values: &[String]) {
+    if key == "category-color" {
+        return set_color(conn, values);
+    }
+    let [value] = values else {
+        eprintln!("{key} takes a single value.");
+        std::process::exit(1);
+    };
+    let value = value.as_str();
+    match key {
+        "day-start" => {
+            let hour = value.strip_suffix(":00").unwrap_or(value);
+            match hour.parse::<u32>() {
+                Ok(h) if h < 24 => set_setting(conn, "day_start_hour", &h.to_string()),
+                _ => {
+                    eprintln!("Invalid hour \"{value}\" (use 0-23, e.g. 4 for 04:00).");
+                    std::process::exit(1);
+                }
+            }
+        }
+        "duration-format" => {
+            if DurationFormat::parse(value).is_none() {
+                eprintln!("Invalid duration format \"{value}\" (use hms, clock or decimal).");
+                std::process::exit(1);
+            }
+            set_setting(conn, "duration_format", value);
+        }
+        "long-break" => {
+            let secs = match parse_duration(value) {
+                Some(s) if s > 0 => s,
+                _ => {
+                    eprintln!("Invalid duration \"{value}\" (try 12h or 8h).");
+                    std::process::exit(1);
+                }
+            };
+            set_setting(conn, "long_break_secs", &secs.to_string());
+        }
+        "max-duration" => {
+            let secs = match parse_duration(value) {
+                Some(s) if s > 0 => s,
+                _ => {
+                    eprintln!("Invalid duration \"{value}\" (try 10h or 8h30m).");
+                    std::process::exit(1);
+                }
+            };
+            set_setting(conn, "max_duration_secs", &secs.to_string());
+        }
+        "sleep-gap" => {
+            let secs = match value {
+                "0" | "off" => 0,
+                _ => match parse_duration(value) {
+                    Some(s) if s > 0 => s,
+                    _ => {
+                        eprintln!("Invalid duration \"{value}\" (try 4h, or 0 to turn it off).");
+                        std::process::exit(1);
+                    }
+                },
+            };
+            set_setting(conn, "sleep_gap_secs", &secs.to_string());
+        }
+        "lowercase-categories" => {
+            if value != "on" && value != "off" {
+                eprintln!("Invalid value \"{value}\" (use on or off).");
+                std::process::exit(1);
+            }
+            set_setting(conn, "lowercase_categories", value);
+        }
+        "notifications" => {
+            if value != "on" && value != "off" {
+                eprintln!("Invalid value \"{value}\" (use on or off).");
+                std::process::exit(1);
+            }
+            set_setting(conn, "notifications", value);
+        }
+        "time-format" => {
+            if value != "12h" && value != "24h" {
+                eprintln!("Invalid time format \"{value}\" (use 12h or 24h).");
+                std::process::exit(1);
+            }
+            set_setting(conn, "time_format", value);
+        }
+        "week-start" => {
+            let value = value.to_lowercase();
+            if value != "monday" && value != "sunday" {
+                eprintln!("Invalid week start \"{value}\" (use monday or sunday).");
+                std::process::exit(1);
+            }
+            set_setting(conn, "week_start", &value);
+        }
+        _ => unknown(key),
+    }
+    println!("{key} = {}", current(conn, key).unwrap_or_default());
+}
+
+fn set_color(conn: &Connection, values: &[String]) {
+    let Some((color, category)) = values.split_last().filter(|(_, cat)| !cat.is_empty()) else {
+        eprintln!("Usage: tl config set category-color <category> <color>");
+        std::process::exit(1);
+    };
+    if color::code_for(color).is_none() {
+        let names: Vec<&str> = color::COLORS.iter().map(|(n, _)| *n).collect();
+        eprintln!("Unknown color \"{color}\" (use {}).", names.join(", "));
+        std::process::exit(1);
+    }
+    let category = canonical_category(conn, &category.join(" "));
+    set_category_color(conn, &category, &color.to_lowercase());
+    println!("category-color {} = {}", color::category(conn, &category), color.to_lowercase());
+}
+
+pub fn unset(conn: &Connection, key: &str, category: &[String]) {
+    if key == "category-color" {
+        if category.is_empty() {
+            eprintln!("Usage: tl config unset category-color <category>");
+            std::process::exit(1);
+        }
+        let category = category.join(" ");
+        if clear_category_color(conn, &category) {
+            println!("category-color for \"{}\" unset; it gets a color from its name again.", category.trim());
+        } else {
+            println!("No color set for \"{}\".", category.trim());
+        }
+        return;
+    }
+    let setting = match key {
+        "day-start" => "day_start_hour",
+        "duration-format" => "duration_format",
+        "long-break" => "long_break_secs",
+        "lowercase-categories" => "lowercase_categories",
+        "max-duration" => "max_duration_secs",
+        "notifications" => "notifications",
+        "sleep-gap" => "sleep_gap_secs",
+        "time-format" => "time_format",
+        "week-start" => "week_start",
+        _ => unknown(key),
+    };
+    if !category.is_empty() {
+        eprintln!("{key} takes no value to unset.");
+        std::process::exit(1);
+    }
+    if clear_setting(conn, setting) {
+        println!("{key} unset.");
+    } else {
+        println!("{key} was not set.");
+    }
+}
+
+fn current(conn: &Connection, key: &str) -> Option<String> {
+    match key {
+        "category-color" => {
+            let colors = list_category_colors(conn);
+            let palette = color::Palette::load(conn);
+            let shown: Vec<String> = colors.iter().map(|(cat, c)| format!("{}={c}", palette.category(cat))).collect();
+            (!shown.is_empty()).then(|| shown.join(", "))
+        }
+        "day-start" => Some(format!("{:02}:00", day_start_hour(conn))),
+        "duration-format" => get_setting(conn, "duration_format"),
+        // Always as 12h 00m 00s: that's how these are set.
+        "long-break" => Some(format_duration_as(long_break_secs(conn), DurationFormat::Hms)),
+        "max-duration" => max_duration_secs(conn).map(|secs| format_duration_as(secs, DurationFormat::Hms)),
+        "sleep-gap" => Some(match sleep_gap_secs(conn) {
+            0 => "off".to_string(),
+            secs => format_duration_as(secs, DurationFormat::Hms),
+        }),
+        "lowercase-categories" => get_setting(conn, "lowercase_categories"),
+        "notifications" => get_setting(conn, "notifications"),
+        "time-format" => get_setting(conn, "time_format"),
+        "week-start" => get_setting(conn, "week_start"),
+        _ => None,
+    }
+}
+
+fn unknown(key: &str) -> ! {
+    let known: Vec<&str> = KEYS.iter().map(|(k, _)| *k).collect();
+    eprintln!("Unknown setting \"{key}\". Known settings: {}.", known.join(", "));
+    std::process::exit(1);
+}
@@ -0,0 +1,158 @@
+//! `tl doctor`: consistency checks over the whole database, with `--fix` for
+//! the problems that can be repaired without guessing.
+
+use std::collections::HashSet;
+
+use rusqlite::Connection;
+
+use crate::duration::format_duration;
+use crate::state::*;
+
+struct Checkup {
+    fix: bool,
+    found: u32,
+    fixable: u32,
+}
+
+impl Checkup {
+    /// A problem that needs a human decision.
+    fn manual(&mut self, msg: String) {
+        self.found += 1;
+        println!("✗ {msg}");
+    }
+
+    /// A problem `--fix` knows how to repair. Returns whether to repair it now.
+    fn repairable(&mut self, msg: String) -> bool {
+        self.found += 1;
+        self.fixable += 1;
+        if self.fix {
+            println!("✓ {msg} — fixed");
+        } else {
+            println!("✗ {msg}");
+        }
+        self.fix
+    }
+}
+
+fn id_list(ids: &[u32]) -> String {
+    ids.iter().map(|id| format!("#{id}")).collect::<Vec<_>>().join(", ")
+}
+
+/// Split an owner's todo links (plus its primary `todo_id`) into those that
+/// still exist and those pointing at deleted todos.
+fn split_links(links: &[u32], primary: Option<u32>, todo_ids: &HashSet<u32>) -> (Vec<u32>, Vec<u32>) {
+    let (valid, mut missing): (Vec<u32>, Vec<u32>) = links.iter().partition(|id| todo_ids.contains(id));
+    if let Some(id) = primary.filter(|id| !todo_ids.contains(id) && !missing.contains(id)) {
+        missing.push(id);
+    }
+    (valid, missing)
+}
+
+pub fn run(conn: &Connection, fix: bool) {
+    let tx = conn.unchecked_transaction().expect("failed to begin transaction");
+    let mut c = Checkup { fix, found: 0, fixable: 0 };
+    let todo_ids: HashSet<u32> = list_todos(&tx).iter().map(|t| t.id).collect();
+    let entries = query_entries(&tx, None);
+    let timers = get_all_active(&tx);
+    let (entry_count, timer_count) = (entries.len(), timers.len());
+
+    for mut e in entries {
+        if e.ended_at < e.started_at {
+            c.manual(format!(
+                "Entry #{} \"{}\" ends before it starts ({} → {})",
+                e.id,
+                e.name,
+                format_datetime(&tx, e.started_at),
+                format_datetime(&tx, e.ended_at),
+            ));
+        } else {
+            // Zero-length entries are fine: a stop in the same second as the
+            // start, or a clock that jumped back, logs one on purpose.
+            // Less active time than the span allows is normal (`tl log edit
+            // --sub` trims it); more is impossible.
+            let max = (e.ended_at - e.started_at - total_break_secs(&e.breaks, e.ended_at)).max(0);
+            if (e.active_secs < 0 || e.active_secs > max)
+                && c.repairable(format!(
+                    "Entry #{} \"{}\" has {} of active time but start, end and breaks allow only {}",
+                    e.id,
+                    e.name,
+                    format_duration(e.active_secs),
+                    format_duration(max),
+                ))
+            {
+                e.active_secs = max;
+                update_entry(&tx, &e);
+            }
+        }
+
+        let links = entry_todos(&tx, &e);
+        let (valid, missing) = split_links(&links, e.todo_id, &todo_ids);
+        if !missing.is_empty()
+            && c.repairable(format!("Entry #{} \"{}\" links to deleted todo {}", e.id, e.name, id_list(&missing)))
+        {
+            e.todo_id = valid.first().copied();
+            update_entry(&tx, &e);
+            set_entry_todos(&tx, e.id, &valid);
+        }
+    }
+
+    let running: Vec<u32> = timers.iter().filter(|t| t.state == "running").filter_map(|t| t.id).collect();
+    if running.len() > 1 {
+        c.manual(format!(
+            "{} timers are running at once ({}); pause all but one",
+            running.len(),
+            id_list(&running),
+        ));
+    }
+
+    for mut t in timers {
+        let id = t.id.unwrap();
+        let open = t.breaks.iter().filter(|b| b.end_ts == 0).count();
+        if open > 1
+            && c.repairable(format!("Timer #{id} \"{}\" has {open} open breaks", t.name))
+        {
+            // Later open breaks lie inside the first one, as in `resume`.
+            let first = t.breaks.iter().position(|b| b.end_ts == 0).unwrap();
+            t.breaks.truncate(first + 1);
+            update_active(&tx, &t);
+        }
+
+        let links = timer_todos(&tx, &t);
+        let (valid, missing) = split_links(&links, t.todo_id, &todo_ids);
+        if !missing.is_empty()
+            && c.repairable(format!("Timer #{id} \"{}\" links to deleted todo {}", t.name, id_list(&missing)))
+        {
+            t.todo_id = valid.first().copied();
+            update_active(&tx, &t);
+            set_timer_todos(&tx, id, &valid);
+        }
+    }
+
+    for (table, owners, orphans) in orphaned_todo_links(&tx) {
+        if c.repairable(format!("{orphans} todo link(s) in {table} belong to deleted {owners}")) {
+            remove_orphaned_todo_links(&tx, table);
+        }
+    }
+
+    tx.commit().expect("failed to commit repairs");
+
+    println!(
+        "{}Checked {} entries, {} active timers and {} todos.",
+        if c.found > 0 { "\n" } else { "" },
+        entry_count,
+        timer_count,
+        todo_ids.len(),
+    );
+    if c.found == 0 {
+        println!("No problems found.");
+    } else if fix {
+        println!("{} problem(s) found, {} fixed.", c.found, c.fixable);
+    } else if c.fixable > 0 {
+        println!(
+            "{} problem(s) found; run `tl doctor --fix` to repair {} of them.",
+            c.found, c.fixable,
+        );
+    } else {
+        println!("{} problem(s) found.", c.found);
+    }
+}
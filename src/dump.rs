@@ -0,0 +1,496 @@
+//! `tl export --json` / `tl import`: the whole database as one readable JSON
+//! document, with breaks decoded from protobuf.
+
+use std::collections::HashMap;
+use std::io::{IsTerminal, Read};
+
+use chrono::Local;
+use dialoguer::Confirm;
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+
+use crate::prompt::Choice;
+use crate::state::*;
+
+/// Bumped if the document layout ever changes incompatibly.
+const FORMAT_VERSION: u32 = 1;
+
+/// What `tl import` does with data already in the database.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ImportMode {
+    /// Delete every entry, todo and timer first, then load the dump
+    Replace,
+    /// Add every record, even ones that look like duplicates
+    Merge,
+    /// Add only records not already present (the default)
+    #[default]
+    Skip,
+}
+
+#[derive(Serialize, Deserialize)]
+struct Dump {
+    version: u32,
+    exported_at: i64,
+    todos: Vec<TodoDump>,
+    time_entries: Vec<EntryDump>,
+    /// Entries moved out of the log by `tl archive`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    archived_entries: Vec<EntryDump>,
+    active_timers: Vec<TimerDump>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BreakDump {
+    start_ts: i64,
+    /// 0 while the break is still open.
+    end_ts: i64,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    label: String,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    interruption: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct NoteDump {
+    ts: i64,
+    text: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TodoDump {
+    id: u32,
+    text: String,
+    done: bool,
+    created_at: i64,
+    last_modified: i64,
+    #[serde(default)]
+    estimate_secs: Option<i64>,
+    #[serde(default)]
+    archived: bool,
+    #[serde(default)]
+    completed_at: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct EntryDump {
+    id: u32,
+    name: String,
+    category: String,
+    started_at: i64,
+    ended_at: i64,
+    active_secs: i64,
+    breaks: Vec<BreakDump>,
+    /// Linked todo ids from this dump, first one primary.
+    #[serde(default)]
+    todo_ids: Vec<u32>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Seconds east of UTC where the entry was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    utc_offset: Option<i32>,
+    /// Machine the timer ran on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<NoteDump>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TimerDump {
+    id: u32,
+    name: String,
+    category: String,
+    started_at: i64,
+    state: String,
+    breaks: Vec<BreakDump>,
+    #[serde(default)]
+    todo_ids: Vec<u32>,
+    /// `(work_secs, break_secs)` for Pomodoro timers.
+    #[serde(default)]
+    pomodoro: Option<(i64, i64)>,
+    /// Machine the timer was started on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    host: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    notes: Vec<NoteDump>,
+}
+
+fn dump_breaks(breaks: &[proto::Break]) -> Vec<BreakDump> {
+    breaks
+        .iter()
+        .map(|b| BreakDump { start_ts: b.start_ts, end_ts: b.end_ts, label: b.label.clone(), interruption: b.interruption })
+        .collect()
+}
+
+fn load_breaks(breaks: &[BreakDump]) -> Vec<proto::Break> {
+    breaks
+        .iter()
+        .map(|b| proto::Break { start_ts: b.start_ts, end_ts: b.end_ts, label: b.label.clone(), interruption: b.interruption })
+        .collect()
+}
+
+fn dump_notes(notes: Vec<Note>) -> Vec<NoteDump> {
+    notes.into_iter().map(|n| NoteDump { ts: n.ts, text: n.text }).collect()
+}
+
+fn dump_entry(conn: &Connection, e: TimeEntry) -> EntryDump {
+    EntryDump {
+        id: e.id,
+        todo_ids: entry_todos(conn, &e),
+        notes: dump_notes(entry_notes(conn, e.id)),
+        breaks: dump_breaks(&e.breaks),
+        name: e.name,
+        category: e.category,
+        started_at: e.started_at,
+        ended_at: e.ended_at,
+        active_secs: e.active_secs,
+        tags: e.tags,
+        utc_offset: e.utc_offset,
+        host: e.host,
+    }
+}
+
+pub fn export(conn: &Connection, file: Option<&str>) {
+    let dump = snapshot(conn);
+    let json = serde_json::to_string_pretty(&dump).unwrap();
+    match file {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, json + "\n") {
+                eprintln!("Couldn't write {path}: {e}");
+                std::process::exit(1);
+            }
+            println!(
+                "Exported {} entries{}, {} todos and {} active timers to {path}.",
+                dump.time_entries.len() + dump.archived_entries.len(),
+                archived_note(dump.archived_entries.len()),
+                dump.todos.len(),
+                dump.active_timers.len(),
+            );
+        }
+        None => println!("{json}"),
+    }
+}
+
+/// " (N archived)" when there are archived entries to mention.
+fn archived_note(archived: usize) -> String {
+    if archived == 0 { String::new() } else { format!(" ({archived} archived)") }
+}
+
+/// The whole database as a `Dump`.
+fn snapshot(conn: &Connection) -> Dump {
+    Dump {
+        version: FORMAT_VERSION,
+        exported_at: Local::now().timestamp(),
+        todos: list_todos(conn)
+            .into_iter()
+            .map(|t| TodoDump {
+                id: t.id,
+                text: t.text,
+                done: t.done,
+                created_at: t.created_at,
+                last_modified: t.last_modified,
+                estimate_secs: t.estimate_secs,
+                archived: t.archived,
+                completed_at: t.completed_at,
+            })
+            .collect(),
+        time_entries: query_entries(conn, None).into_iter().map(|e| dump_entry(conn, e)).collect(),
+        archived_entries: archived_entries(conn).into_iter().map(|e| dump_entry(conn, e)).collect(),
+        active_timers: get_all_active(conn)
+            .into_iter()
+            .map(|t| {
+                let id = t.id.unwrap();
+                TimerDump {
+                    id,
+                    todo_ids: timer_todos(conn, &t),
+                    pomodoro: get_pomodoro(conn, id).map(|p| (p.work_secs, p.break_secs)),
+                    notes: dump_notes(timer_notes(conn, id)),
+                    host: timer_host(conn, id),
+                    breaks: dump_breaks(&t.breaks),
+                    name: t.name,
+                    category: t.category,
+                    started_at: t.started_at,
+                    state: t.state,
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Load a dump into the database. Everything gets fresh ids, with todo links
+/// remapped to match. With `ImportMode::Skip`, records that are already
+/// present (same todo text and creation time, same entry name, start and
+/// end, same timer name and start) are left out, so importing the same file
+/// twice is harmless. It all happens in one transaction: a malformed record
+/// leaves the database as it was.
+pub fn import(conn: &Connection, file: &str, mode: ImportMode, yes: bool) {
+    let mut text = String::new();
+    let read = if file == "-" {
+        std::io::stdin().read_to_string(&mut text).map(|_| ())
+    } else {
+        std::fs::read_to_string(file).map(|s| text = s)
+    };
+    if let Err(e) = read {
+        eprintln!("Couldn't read {file}: {e}");
+        std::process::exit(1);
+    }
+    let dump: Dump = match serde_json::from_str(&text) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("{file} is not a tl JSON export: {e}");
+            std::process::exit(1);
+        }
+    };
+    if dump.version > FORMAT_VERSION {
+        eprintln!("{file} was written by a newer tl (format {}); please upgrade.", dump.version);
+        std::process::exit(1);
+    }
+    if let Err(problem) = check(&dump) {
+        eprintln!("{file} has a malformed record: {problem}. Nothing was imported.");
+        std::process::exit(1);
+    }
+    if mode == ImportMode::Replace && !yes && !confirm_replace(conn, file) {
+        return;
+    }
+
+    let (added, skipped) = load(conn, &dump, mode);
+    let entries = format!("{} entries{}", added[1] + added[3], archived_note(added[3]));
+    match mode {
+        ImportMode::Skip => println!(
+            "Imported {entries}, {} todos and {} active timers ({skipped} already present).",
+            added[0], added[2],
+        ),
+        ImportMode::Merge => println!("Imported {entries}, {} todos and {} active timers.", added[0], added[2]),
+        ImportMode::Replace => println!(
+            "Replaced everything with {entries}, {} todos and {} active timers from {file}.",
+            added[0], added[2],
+        ),
+    }
+}
+
+/// Write `dump` into the database in one transaction. Returns how many todos,
+/// entries, active timers and archived entries were added, and how many
+/// records were skipped as already present.
+fn load(conn: &Connection, dump: &Dump, mode: ImportMode) -> ([usize; 4], usize) {
+    let tx = conn.unchecked_transaction().expect("failed to begin transaction");
+    if mode == ImportMode::Replace {
+        wipe_tracking_data(&tx);
+    }
+    let skip = mode == ImportMode::Skip;
+    let (mut added, mut skipped) = ([0; 4], 0);
+
+    let mut todo_ids: HashMap<u32, u32> = HashMap::new();
+    for t in &dump.todos {
+        let existing: Option<u32> = skip
+            .then(|| {
+                tx.query_row(
+                    "SELECT id FROM todos WHERE text = ?1 AND created_at = ?2",
+                    params![t.text, t.created_at],
+                    |row| row.get(0),
+                )
+                .ok()
+            })
+            .flatten();
+        let id = existing.unwrap_or_else(|| {
+            added[0] += 1;
+            insert_todo(
+                &tx,
+                &TodoItem {
+                    id: 0,
+                    text: t.text.clone(),
+                    done: t.done,
+                    created_at: t.created_at,
+                    last_modified: t.last_modified,
+                    estimate_secs: t.estimate_secs,
+                    archived: t.archived,
+                    completed_at: t.completed_at.or(t.done.then_some(t.last_modified)),
+                },
+            )
+        });
+        if existing.is_some() {
+            skipped += 1;
+        }
+        todo_ids.insert(t.id, id);
+    }
+    let remap = |ids: &[u32]| -> Vec<u32> { ids.iter().filter_map(|id| todo_ids.get(id).copied()).collect() };
+
+    let entries = dump.time_entries.iter().map(|e| (e, false));
+    for (e, archived) in entries.chain(dump.archived_entries.iter().map(|e| (e, true))) {
+        let exists: bool = skip
+            && tx
+                .query_row(
+                    "SELECT COUNT(*) FROM (SELECT name, started_at, ended_at FROM time_entries
+                                           UNION ALL SELECT name, started_at, ended_at FROM archived_entries)
+                     WHERE name = ?1 AND started_at = ?2 AND ended_at = ?3",
+                    params![e.name, e.started_at, e.ended_at],
+                    |row| row.get::<_, i64>(0),
+                )
+                .unwrap_or(0)
+                > 0;
+        if exists {
+            skipped += 1;
+            continue;
+        }
+        let todos = remap(&e.todo_ids);
+        let entry = TimeEntry {
+            id: 0,
+            name: e.name.clone(),
+            category: e.category.clone(),
+            started_at: e.started_at,
+            ended_at: e.ended_at,
+            active_secs: e.active_secs,
+            breaks: load_breaks(&e.breaks),
+            todo_id: todos.first().copied(),
+            last_modified: 0,
+            tags: normalize_tags(&e.tags),
+            utc_offset: e.utc_offset,
+            host: e.host.clone(),
+        };
+        let id = if archived { insert_archived_entry(&tx, &entry) } else { insert_entry(&tx, &entry) };
+        set_entry_todos(&tx, id, &todos);
+        load_notes(&e.notes, |note| add_entry_note(&tx, id, note));
+        added[if archived { 3 } else { 1 }] += 1;
+    }
+
+    let now_ts = Local::now().timestamp();
+    for t in &dump.active_timers {
+        let exists = skip
+            && get_all_active(&tx)
+                .iter()
+                .any(|a| a.name == t.name && a.started_at == t.started_at);
+        if exists {
+            skipped += 1;
+            continue;
+        }
+        let todos = remap(&t.todo_ids);
+        let mut timer = ActiveTimer {
+            id: None,
+            name: t.name.clone(),
+            category: t.category.clone(),
+            started_at: t.started_at,
+            state: t.state.clone(),
+            breaks: load_breaks(&t.breaks),
+            todo_id: todos.first().copied(),
+            last_modified: 0,
+        };
+        // Only one timer may run at a time.
+        if timer.state == "running" && get_running(&tx).is_some() {
+            timer.pause(now_ts, "");
+        }
+        let id = insert_active_from(&tx, &timer, t.host.as_deref());
+        set_timer_todos(&tx, id, &todos);
+        if let Some((work, brk)) = t.pomodoro {
+            set_pomodoro(&tx, id, work, brk);
+        }
+        load_notes(&t.notes, |note| add_timer_note(&tx, id, note));
+        added[2] += 1;
+    }
+    tx.commit().expect("failed to commit import");
+    (added, skipped)
+}
+
+fn load_notes(notes: &[NoteDump], mut add: impl FnMut(&Note)) {
+    for n in notes {
+        add(&Note { ts: n.ts, text: n.text.clone() });
+    }
+}
+
+/// The first record that can't be imported as it is, described.
+fn check(dump: &Dump) -> Result<(), String> {
+    for t in &dump.todos {
+        if t.text.trim().is_empty() {
+            return Err(format!("todo #{} has no text", t.id));
+        }
+    }
+    for e in dump.time_entries.iter().chain(&dump.archived_entries) {
+        if e.name.trim().is_empty() || e.category.trim().is_empty() {
+            return Err(format!("entry #{} has no name or category", e.id));
+        }
+        if e.ended_at < e.started_at {
+            return Err(format!("entry #{} ends before it starts", e.id));
+        }
+        if e.active_secs < 0 {
+            return Err(format!("entry #{} has negative active time", e.id));
+        }
+    }
+    for t in &dump.active_timers {
+        if t.name.trim().is_empty() || t.category.trim().is_empty() {
+            return Err(format!("timer #{} has no name or category", t.id));
+        }
+        if t.state != "running" && t.state != "paused" {
+            return Err(format!("timer #{} has unknown state \"{}\"", t.id, t.state));
+        }
+    }
+    Ok(())
+}
+
+/// Ask before `--mode replace` deletes what's there. Needs a terminal to
+/// ask on; scripts pass `--yes`.
+fn confirm_replace(conn: &Connection, file: &str) -> bool {
+    let archived = archived_entries(conn).len();
+    let (entries, todos, timers) = (query_entries(conn, None).len(), list_todos(conn).len(), get_all_active(conn).len());
+    if entries + archived + todos + timers == 0 {
+        return true;
+    }
+    // Notes, links and pomodoros go with their entries and timers.
+    let what = format!(
+        "{} entries{}, {todos} todos and {timers} active timers",
+        entries + archived,
+        archived_note(archived),
+    );
+    if !std::io::stdin().is_terminal() {
+        eprintln!("--mode replace would delete {what}; add --yes to go ahead.");
+        std::process::exit(1);
+    }
+    Confirm::new()
+        .with_prompt(format!("Delete all {what}, then import {file}?"))
+        .default(false)
+        .interact_opt()
+        .chosen()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn replace_round_trips_archived_entries_and_notes() {
+        let conn = open_db_at(Path::new(":memory:"), Verbosity::Normal).unwrap();
+        let todo = add_todo(&conn, "Ship it", 0);
+        for (name, started_at) in [("Old", 1_000), ("New", 5_000)] {
+            let id = insert_entry(
+                &conn,
+                &TimeEntry {
+                    id: 0,
+                    name: name.into(),
+                    category: "work".into(),
+                    started_at,
+                    ended_at: started_at + 600,
+                    active_secs: 600,
+                    breaks: vec![],
+                    todo_id: Some(todo),
+                    last_modified: 0,
+                    tags: vec![],
+                    utc_offset: None,
+                    host: None,
+                },
+            );
+            add_entry_note(&conn, id, &Note { ts: started_at + 60, text: format!("{name} note") });
+        }
+        archive_entries_before(&conn, 5_000, false);
+
+        let json = serde_json::to_string(&snapshot(&conn)).unwrap();
+        let dump: Dump = serde_json::from_str(&json).unwrap();
+        let (added, _) = load(&conn, &dump, ImportMode::Replace);
+        assert_eq!(added, [1, 1, 0, 1]);
+
+        let old = &archived_entries(&conn)[0];
+        assert_eq!(old.name, "Old");
+        assert_eq!(entry_notes(&conn, old.id)[0].text, "Old note");
+        assert_eq!(entry_todos(&conn, old).len(), 1);
+        let new = &query_entries(&conn, None)[0];
+        assert_eq!(entry_notes(&conn, new.id)[0].text, "New note");
+        assert_ne!(old.id, new.id);
+    }
+}
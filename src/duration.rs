@@ -0,0 +1,34 @@
+//! Durations as the CLI prints them: in the format picked for this run
+//! (`--duration-format`, else the `duration-format` setting). The library's
+//! own `format_duration` always uses the default; modules that print import
+//! these instead.
+
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use crate::state::{self, DurationFormat, Precision};
+
+/// The format picked for this run, set once by `use_format`.
+static FORMAT: AtomicU8 = AtomicU8::new(0);
+
+/// Make every `format_duration` in this process use `format`.
+pub fn use_format(format: DurationFormat) {
+    FORMAT.store(format as u8, Ordering::Relaxed);
+}
+
+fn format() -> DurationFormat {
+    match FORMAT.load(Ordering::Relaxed) {
+        1 => DurationFormat::Clock,
+        2 => DurationFormat::Decimal,
+        _ => DurationFormat::Hms,
+    }
+}
+
+/// A duration in the format chosen for this run (see `use_format`).
+pub fn format_duration(secs: i64) -> String {
+    state::format_duration_as(secs, format())
+}
+
+/// `format_duration` cut down to `precision`, e.g. `2h 05m` for minutes.
+pub fn format_duration_prec(secs: i64, precision: Precision) -> String {
+    state::format_duration_prec(secs, precision, format())
+}
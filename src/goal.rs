@@ -0,0 +1,50 @@
+use rusqlite::Connection;
+
+use crate::duration::format_duration;
+use crate::state::*;
+
+pub fn set(conn: &Connection, duration: &str) {
+    let secs = match parse_duration(duration) {
+        Some(s) if s > 0 => s,
+        _ => {
+            eprintln!("Invalid duration \"{duration}\" (try 6h, 7h30m or 90m).");
+            std::process::exit(1);
+        }
+    };
+    set_setting(conn, "daily_goal_secs", &secs.to_string());
+    println!("Daily goal set to {}.", format_duration(secs));
+}
+
+pub fn clear(conn: &Connection) {
+    if clear_setting(conn, "daily_goal_secs") {
+        println!("Daily goal cleared.");
+    } else {
+        println!("No daily goal set.");
+    }
+}
+
+pub fn show(conn: &Connection) {
+    match progress_line(conn) {
+        Some(line) => println!("{line}"),
+        None => println!("No daily goal set. Set one with `tl goal set 6h`."),
+    }
+}
+
+/// Today's tracked time against the daily goal, e.g.
+/// `Goal: 4h 12m 00s / 6h 00m 00s [██████████████░░░░░░] 70%`.
+/// Returns `None` when no goal is configured.
+pub fn progress_line(conn: &Connection) -> Option<String> {
+    let goal = daily_goal_secs(conn)?;
+    let done = today_active_secs(conn);
+    let pct = done * 100 / goal.max(1);
+    let mut line = format!(
+        "Goal: {} / {} {} {pct}%",
+        format_duration(done),
+        format_duration(goal),
+        progress_bar(done, goal, 20),
+    );
+    if done >= goal {
+        line.push_str(" — reached!");
+    }
+    Some(line)
+}
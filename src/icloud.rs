@@ -33,7 +33,7 @@ pub fn push_changes(conn: &Connection) {
                     .breaks
                     .iter()
                     .map(|b| {
-                        serde_json::json!({"start_ts": b.start_ts, "end_ts": b.end_ts})
+                        serde_json::json!({"start_ts": b.start_ts, "end_ts": b.end_ts, "label": b.label, "interruption": b.interruption})
                     })
                     .collect();
                 serde_json::json!({
@@ -67,7 +67,7 @@ pub fn push_changes(conn: &Connection) {
                     .breaks
                     .iter()
                     .map(|b| {
-                        serde_json::json!({"start_ts": b.start_ts, "end_ts": b.end_ts})
+                        serde_json::json!({"start_ts": b.start_ts, "end_ts": b.end_ts, "label": b.label, "interruption": b.interruption})
                     })
                     .collect();
                 serde_json::json!({
@@ -150,6 +150,8 @@ pub fn fetch_and_apply(conn: &Connection) {
                         .map(|b| state::proto::Break {
                             start_ts: b["start_ts"].as_i64().unwrap_or(0),
                             end_ts: b["end_ts"].as_i64().unwrap_or(0),
+                            label: b["label"].as_str().unwrap_or("").to_string(),
+                            interruption: b["interruption"].as_bool().unwrap_or(false),
                         })
                         .collect();
                 let todo_id = t["todoId"].as_i64().map(|v| if v == 0 { None } else { Some(v as u32) }).flatten();
@@ -196,6 +198,8 @@ pub fn fetch_and_apply(conn: &Connection) {
                         .map(|b| state::proto::Break {
                             start_ts: b["start_ts"].as_i64().unwrap_or(0),
                             end_ts: b["end_ts"].as_i64().unwrap_or(0),
+                            label: b["label"].as_str().unwrap_or("").to_string(),
+                            interruption: b["interruption"].as_bool().unwrap_or(false),
                         })
                         .collect();
                 let todo_id = e["todoId"].as_i64().map(|v| if v == 0 { None } else { Some(v as u32) }).flatten();
@@ -210,6 +214,9 @@ pub fn fetch_and_apply(conn: &Connection) {
                     breaks,
                     todo_id,
                     last_modified: lm,
+                    tags: vec![],
+                    utc_offset: None,
+                    host: None,
                 };
 
                 if let Some(existing) = state::get_entry_by_id(conn, sid as u32) {
@@ -239,6 +246,9 @@ pub fn fetch_and_apply(conn: &Connection) {
                     done: done != 0,
                     created_at,
                     last_modified: lm,
+                    estimate_secs: None,
+                    archived: false,
+                    completed_at: None,
                 };
 
                 if let Some(existing) = state::get_todo_by_id(conn, sid as u32) {
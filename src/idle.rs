@@ -0,0 +1,191 @@
+use std::io::IsTerminal;
+use std::process::Command;
+
+use chrono::Local;
+use dialoguer::Confirm;
+use rusqlite::Connection;
+
+use crate::duration::format_duration;
+use crate::prompt::Choice;
+use crate::state::*;
+
+pub fn set(conn: &Connection, duration: &str) {
+    let secs = match parse_duration(duration) {
+        Some(s) if s > 0 => s,
+        _ => {
+            eprintln!("Invalid duration \"{duration}\" (try 10m or 1h).");
+            std::process::exit(1);
+        }
+    };
+    set_setting(conn, "idle_threshold_secs", &secs.to_string());
+    println!("Idle detection on: breaks are suggested after {} idle.", format_duration(secs));
+    if system_idle_secs().is_none() {
+        println!("Warning: can't read idle time on this system (needs ioreg, xprintidle or GNOME).");
+    }
+}
+
+pub fn clear(conn: &Connection) {
+    if clear_setting(conn, "idle_threshold_secs") {
+        println!("Idle detection off.");
+    } else {
+        println!("Idle detection is not enabled.");
+    }
+}
+
+pub fn show(conn: &Connection) {
+    match idle_threshold_secs(conn) {
+        Some(t) => println!("Idle detection on, threshold {}.", format_duration(t)),
+        None => println!("Idle detection off. Enable it with `tl idle set 10m`."),
+    }
+    match system_idle_secs() {
+        Some(s) => println!("Idle for {}.", format_duration(s)),
+        None => println!("Can't read idle time on this system."),
+    }
+}
+
+fn idle_threshold_secs(conn: &Connection) -> Option<i64> {
+    get_setting(conn, "idle_threshold_secs").and_then(|v| v.parse().ok())
+}
+
+/// If idle detection is enabled and the user has been idle past the
+/// threshold while a timer runs, offer to record the idle stretch as a break.
+/// Outside a terminal (e.g. a prompt hook) it only prints a warning.
+pub fn check(conn: &Connection) {
+    let Some(threshold) = idle_threshold_secs(conn) else {
+        return;
+    };
+    let Some(mut timer) = get_running(conn) else {
+        return;
+    };
+    let Some(idle) = system_idle_secs() else {
+        return;
+    };
+    if idle < threshold {
+        return;
+    }
+
+    let now_ts = Local::now().timestamp();
+    let last_activity = timer
+        .breaks
+        .iter()
+        .map(|b| b.end_ts)
+        .fold(timer.started_at, i64::max);
+    let idle_from = (now_ts - idle).max(last_activity);
+    if now_ts - idle_from < threshold {
+        return;
+    }
+
+    println!(
+        "You've been idle {} (since {}) while \"{}\" was running.",
+        format_duration(now_ts - idle_from),
+        format_hm(conn, idle_from),
+        timer.name,
+    );
+    if !std::io::stdin().is_terminal() {
+        return;
+    }
+    let confirm = Confirm::new()
+        .with_prompt("Record that as a break?")
+        .default(true)
+        .interact_opt()
+        .chosen();
+    if confirm {
+        timer.breaks.push(proto::Break {
+            start_ts: idle_from,
+            end_ts: now_ts,
+            label: "idle".into(),
+            interruption: false,
+        });
+        update_active(conn, &timer);
+        println!("Added a {} idle break.", format_duration(now_ts - idle_from));
+    }
+}
+
+/// Offer to turn a long gap since `tl` last ran into a break: with a timer
+/// running, that usually means the laptop slept through it. Then record this
+/// run as the latest heartbeat. Outside a terminal nothing is printed and the
+/// gap is kept for the next interactive command to offer.
+pub fn check_sleep(conn: &Connection) {
+    let now_ts = Local::now().timestamp();
+    let gap = match (get_running(conn), last_seen(conn)) {
+        (Some(timer), Some(seen)) => timer.sleep_start(seen, now_ts, sleep_gap_secs(conn)).map(|from| (timer, from)),
+        _ => None,
+    };
+    if let Some((mut timer, from)) = gap {
+        if !std::io::stdin().is_terminal() {
+            return;
+        }
+        println!(
+            "tl hasn't run for {} (since {} {}) while \"{}\" was running. Was the machine asleep?",
+            format_duration(now_ts - from),
+            format_date(from),
+            format_hm(conn, from),
+            timer.name,
+        );
+        let confirm = Confirm::new()
+            .with_prompt("Record that as a break?")
+            .default(true)
+            .interact_opt()
+            .chosen();
+        if confirm {
+            timer.breaks.push(proto::Break {
+                start_ts: from,
+                end_ts: now_ts,
+                label: "sleep".into(),
+                interruption: false,
+            });
+            update_active(conn, &timer);
+            println!("Added a {} sleep break.", format_duration(now_ts - from));
+        }
+    }
+    touch_heartbeat(conn, now_ts);
+}
+
+/// Seconds since the last keyboard/mouse input, if the platform exposes it.
+fn system_idle_secs() -> Option<i64> {
+    #[cfg(target_os = "macos")]
+    {
+        // `HIDIdleTime` is reported in nanoseconds.
+        let out = Command::new("ioreg").args(["-c", "IOHIDSystem", "-d", "4"]).output().ok()?;
+        let text = String::from_utf8_lossy(&out.stdout);
+        let line = text.lines().find(|l| l.contains("\"HIDIdleTime\""))?;
+        let ns: i64 = line.rsplit('=').next()?.trim().parse().ok()?;
+        Some(ns / 1_000_000_000)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    {
+        // X11 via xprintidle (milliseconds), then GNOME's Mutter idle monitor.
+        if let Ok(out) = Command::new("xprintidle").output()
+            && out.status.success()
+            && let Ok(ms) = String::from_utf8_lossy(&out.stdout).trim().parse::<i64>()
+        {
+            return Some(ms / 1000);
+        }
+        let out = Command::new("gdbus")
+            .args([
+                "call",
+                "--session",
+                "--dest",
+                "org.gnome.Mutter.IdleMonitor",
+                "--object-path",
+                "/org/gnome/Mutter/IdleMonitor/Core",
+                "--method",
+                "org.gnome.Mutter.IdleMonitor.GetIdletime",
+            ])
+            .output()
+            .ok()?;
+        if !out.status.success() {
+            return None;
+        }
+        // Output looks like `(uint64 12345,)`.
+        let text = String::from_utf8_lossy(&out.stdout);
+        let ms: i64 = text
+            .trim()
+            .trim_start_matches("(uint64 ")
+            .trim_end_matches(",)")
+            .parse()
+            .ok()?;
+        Some(ms / 1000)
+    }
+}
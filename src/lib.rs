@@ -0,0 +1,22 @@
+//! The data model behind the `tl` CLI, for building other front ends on the
+//! same database: timers, log entries, todos and their breaks, plus the
+//! queries and mutations over them. Everything takes a [`Connection`] from
+//! [`state::open_db`] (the CLI's own database) or [`state::open_db_at`].
+//!
+//! ```no_run
+//! use tl::state::{self, Verbosity};
+//!
+//! # fn main() -> Result<(), String> {
+//! let conn = state::open_db(Verbosity::Normal)?;
+//! if let Some(timer) = state::get_running(&conn) {
+//!     let now = chrono::Local::now().timestamp();
+//!     println!("{} — {}", timer.name, state::format_duration(timer.active_secs(now)));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod state;
+
+pub use rusqlite::Connection;
+pub use state::{ActiveTimer, TimeEntry, TodoItem};
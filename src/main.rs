@@ -1,4 +1,16 @@
-mod state;
+mod budget;
+mod color;
+mod config;
+mod doctor;
+mod dump;
+mod duration;
+mod goal;
+mod idle;
+mod notify;
+mod prompt;
+mod query;
+mod report;
+mod template;
 mod timer;
 mod todo;
 
@@ -21,7 +33,8 @@ mod ble;
 mod icloud;
 
 use clap::{Parser, Subcommand};
-use state::open_db;
+use tl::state;
+use state::{DurationFormat, EntryFilter, EntrySort, Precision, Verbosity, open_db};
 
 #[derive(Parser)]
 #[command(
@@ -30,15 +43,22 @@ use state::open_db;
     after_help = "\
 EXAMPLES:
   tl start              Start a new timer (prompts for name, category & todo link)
+  tl start --pomodoro   Start a timer tracked in 25m work / 5m break intervals
   tl stop               Stop the running timer and save to log
   tl pause              Pause the running timer
   tl resume             Resume a paused timer
-  tl switch             Switch to a different paused timer
+  tl interrupt          Log a 5m interruption on the running timer
+  tl switch             Switch to another active timer
   tl status             Show all active timers with linked todos
+  tl today              Quick summary of today: totals, categories, active timers
+  tl goal set 6h        Set a daily goal of 6 hours
+  tl budget set email 5h  Cap \"email\" at 5 hours per week
   tl log                Show all logged time entries
   tl log --today        Show today's entries only
-  tl log --week         Show entries from the last 7 days
+  tl log --week         Show entries from this week
+  tl log search bug     Find entries whose name contains \"bug\"
   tl log rm 5           Delete log entry #5
+  tl report --daily     Time per day this week
   tl todo add Fix bug   Add a todo item
   tl todo list          List all todos with tracked time
   tl todo done 3        Mark todo #3 as done
@@ -51,9 +71,24 @@ WORKFLOW:
   tl resume                         Continue working
   tl stop                           Stop timer — offers to mark todo as done
   tl todo list                      See todos with total time tracked
-  tl log --today                    See what you did today"
+  tl log --today                    See what you did today
+
+GLOBAL FLAGS:
+  -q, --quiet    Print only ids (e.g. `tl stop -q` prints the new entry id)
+  -v, --verbose  Log the database path, migrations and SQL timing to stderr
+      --dry-run  Print what tl archive, tl log rm/merge and tl todo rm/merge/
+                 archive/done would change, without changing anything"
 )]
 struct Cli {
+    /// Print only essential output (e.g. the new entry id), for scripts
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    quiet: bool,
+    /// Log the database path, migrations and SQL timing to stderr
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    /// Show what a bulk or destructive command would change, and change nothing
+    #[arg(long, global = true)]
+    dry_run: bool,
     #[command(subcommand)]
     command: Commands,
 }
@@ -63,29 +98,157 @@ enum Commands {
     /// Start a new timer (can link to a todo; pauses current if running)
     #[command(after_help = "\
 EXAMPLES:
-  tl start       Prompts for name, category, and optional todo link
-                 If a timer is already running, asks to pause it first")]
-    Start,
+  tl start                       Prompts for name, category, and optional todo links
+                                 (type to filter; pick several todos one at a time,
+                                 or \"+ Create new todo\" to add one on the spot)
+                                 If a timer is already running, asks to pause it first
+  tl start --pomodoro            Track the timer in 25m work / 5m break intervals
+  tl start --pomodoro --work 50 --break 10
+  tl start --ago 10m             Started working 10 minutes ago
+  tl start --at 09:15            Started working at 09:15 today
+  tl start --template standup    Start a saved template without any prompts
+  tl start --next                Start on the oldest open todo, linked and named after it;
+                                 asks only for a category it hasn't been logged under yet")]
+    Start {
+        /// Track this timer in Pomodoro work/break intervals
+        #[arg(long)]
+        pomodoro: bool,
+        /// Pomodoro work interval in minutes
+        #[arg(long, default_value_t = 25, requires = "pomodoro", value_parser = clap::value_parser!(u32).range(1..))]
+        work: u32,
+        /// Pomodoro break length in minutes
+        #[arg(long = "break", default_value_t = 5, requires = "pomodoro")]
+        break_mins: u32,
+        /// Backdate the start to this clock time (e.g. 09:15, today)
+        #[arg(long, conflicts_with = "ago")]
+        at: Option<String>,
+        /// Backdate the start by this much (e.g. 10m, 1h30m)
+        #[arg(long)]
+        ago: Option<String>,
+        /// Start a saved template (see `tl template`) without prompting
+        #[arg(long, value_name = "KEY")]
+        template: Option<String>,
+        /// Start on the oldest open todo that no timer is tracking
+        #[arg(long, conflicts_with = "template")]
+        next: bool,
+    },
+
+    /// Start a saved template: shorthand for `tl start --template <key>`
+    #[command(name = "t", after_help = "\
+EXAMPLES:
+  tl t standup       Start the \"standup\" template right away")]
+    T {
+        /// Template key
+        key: String,
+    },
+
+    /// Save timer presets that start with no prompts
+    #[command(after_help = "\
+EXAMPLES:
+  tl template add standup --name Standup --category meetings
+                                   Save a template
+  tl template add bug --name \"Fix login\" --category dev --todo 3
+                                   Link todo #3 whenever it starts
+  tl template list                 Show saved templates
+  tl template rm standup           Remove a template
+
+Start one with `tl t standup` or `tl start --template standup`.")]
+    Template {
+        #[command(subcommand)]
+        action: TemplateAction,
+    },
 
     /// Stop the running timer, save to log, and optionally complete linked todo
     #[command(after_help = "\
 EXAMPLES:
-  tl stop        Stops the running timer and records the time entry
-                 If linked to a todo, offers to mark it as done")]
-    Stop,
+  tl stop                     Stops the running timer and records the time entry
+                              If linked to todos, offers to mark each as done
+  tl stop --tag billable      Attach tags to the logged entry (repeatable)
+  tl stop --at 17:30          Record the entry as ending at 17:30 today
+  tl stop --ago 2h            Record the entry as ending 2 hours ago
+  tl stop --force             Save without asking, even if it overlaps logged time
+  tl stop --cap               Cap a timer over max-duration without asking (otherwise
+                              -q, --force and scripts log it uncapped with a warning)
+  tl stop --done              Mark the linked todos done without asking (--keep leaves
+                              them open; with neither, -q and scripts leave them open)")]
+    Stop {
+        /// Tag to attach to the logged entry (repeatable, or comma-separated)
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// End the entry at this clock time instead of now (e.g. 17:30)
+        #[arg(long, conflicts_with = "ago")]
+        at: Option<String>,
+        /// End the entry this long ago instead of now (e.g. 2h)
+        #[arg(long)]
+        ago: Option<String>,
+        /// Save even if the entry overlaps already logged time
+        #[arg(long)]
+        force: bool,
+        /// Cap a timer over the max-duration setting without asking
+        #[arg(long, conflicts_with_all = ["at", "ago"])]
+        cap: bool,
+        /// Mark the linked todos as done without asking
+        #[arg(long, conflicts_with = "keep")]
+        done: bool,
+        /// Leave the linked todos open without asking
+        #[arg(long)]
+        keep: bool,
+    },
 
     /// Pause the running timer (take a break)
     #[command(after_help = "\
 EXAMPLES:
-  tl pause       Pauses the running timer — break time starts counting")]
-    Pause,
+  tl pause                Pauses the running timer — break time starts counting
+                          Prompts for an optional reason (Enter skips it; -q
+                          and scripts skip the prompt)
+  tl pause --label lunch  Pause and label the break without prompting
+                          (--reason works too); `tl log show` lists each
+                          break with its times and reason
+  tl pause --remind 5m    Pause, then stay open and ring once the break passes 5m
+                          (resuming from elsewhere ends the wait)")]
+    Pause {
+        /// Reason for this break, e.g. lunch or meeting
+        #[arg(long, visible_alias = "reason")]
+        label: Option<String>,
+        /// Wait and ring once the break has lasted this long, e.g. 5m
+        #[arg(long, value_name = "DURATION")]
+        remind: Option<String>,
+    },
+
+    /// Log an interruption on the running timer without pausing it
+    #[command(after_help = "\
+EXAMPLES:
+  tl interrupt                      Records the last 5m as an interruption
+  tl interrupt --duration 15m       Records the last 15m instead
+  tl interrupt --reason \"phone call\"
+                                    Interruptions count as break time, and the
+                                    stop summary and `tl log show` tally them
+                                    separately")]
+    Interrupt {
+        /// How long the interruption lasted, ending now, e.g. 5m
+        #[arg(long, value_name = "DURATION", default_value = "5m")]
+        duration: String,
+        /// What interrupted you, e.g. phone call
+        #[arg(long)]
+        reason: Option<String>,
+    },
 
     /// Resume a paused timer
     #[command(after_help = "\
 EXAMPLES:
-  tl resume      If one paused timer, resumes it
-                 If multiple, lets you pick which one")]
-    Resume,
+  tl resume         If one paused timer, resumes it
+                    If multiple, lets you pick (starting on the one paused last)
+                    If none, offers to start a new timer
+  tl resume --last  Resume the most recently paused timer without asking
+  tl resume --force Don't offer to correct a break longer than `long-break` (12h)")]
+    Resume {
+        /// Resume the most recently paused timer without prompting
+        #[arg(long)]
+        last: bool,
+        /// Skip the check for suspiciously long breaks
+        #[arg(long)]
+        force: bool,
+    },
 
     /// Start a new timer using the details of the most recently stopped timer
     #[command(after_help = "\
@@ -94,29 +257,121 @@ EXAMPLES:
                  link of your last completed activity")]
     Restart,
 
-    /// Start a blocking Pomodoro timer
+    /// Watch the Pomodoro timer and get reminders to take and end breaks
     #[command(after_help = "\
 EXAMPLES:
-  tl pomodoro 25     Start a 25-minute timer that blocks the terminal,
-                     shows remaining time, auto-stops, and sends a notification.")]
+  tl pomodoro            Watch the active Pomodoro timer (starts one if none),
+                         showing the remaining work/break time and ringing
+                         when it's time to pause or resume
+  tl pomodoro 50 --break 10
+                         Same, but a new timer uses 50m work / 10m breaks")]
     Pomodoro {
-        /// Duration in minutes (default 25)
-        #[arg(default_value_t = 25)]
+        /// Work interval in minutes for a newly started timer (default 25)
+        #[arg(default_value_t = 25, value_parser = clap::value_parser!(u32).range(1..))]
         minutes: u32,
+        /// Break length in minutes for a newly started timer
+        #[arg(long = "break", default_value_t = 5)]
+        break_mins: u32,
+    },
+
+    /// Add a timestamped note to the running timer without stopping it
+    #[command(after_help = "\
+EXAMPLES:
+  tl note tried the cache fix, no luck
+                 Appends the note at the current time; `tl status` counts the
+                 running timer's notes and `tl log show` lists them once stopped")]
+    Note {
+        /// The note text
+        #[arg(required = true)]
+        text: Vec<String>,
     },
 
     /// Show all active timers (running and paused)
     #[command(after_help = "\
 EXAMPLES:
   tl status      Shows each active timer with state, active time, breaks,
-                 and linked todo")]
-    Status,
+                 and linked todo
+  tl status --precision minutes
+                 Durations without the ticking seconds, e.g. 2h 05m")]
+    Status {
+        /// How finely to show durations
+        #[arg(long, value_enum, default_value_t = PrecisionArg::Seconds)]
+        precision: PrecisionArg,
+    },
+
+    /// Show a compact summary of today's tracked time
+    #[command(after_help = "\
+EXAMPLES:
+  tl today       Shows today's active and break totals, entry count,
+                 per-category breakdown, active timers, goal progress
+                 and category budgets for this week")]
+    Today,
 
-    /// Switch to a different paused timer (pauses the current one)
+    /// Set, show, or clear the daily tracked-time goal
     #[command(after_help = "\
 EXAMPLES:
-  tl switch      Lists paused timers and lets you pick one to resume
-                 The currently running timer gets paused automatically")]
+  tl goal              Show today's progress toward the goal
+  tl goal set 6h       Set a daily goal of 6 hours
+  tl goal set 7h30m    Durations accept h/m/s units or plain minutes
+  tl goal clear        Remove the daily goal")]
+    Goal {
+        #[command(subcommand)]
+        action: Option<GoalAction>,
+    },
+
+    /// Cap how much time a category may take per week
+    #[command(after_help = "\
+EXAMPLES:
+  tl budget                  This week's usage against each budget
+  tl budget set email 5h     No more than 5 hours of \"email\" per week
+  tl budget rm email         Remove the budget
+
+Budgets also show up in `tl today` and next to each category in
+`tl report --week`, in red when a category is over.")]
+    Budget {
+        #[command(subcommand)]
+        action: Option<BudgetAction>,
+    },
+
+    /// Show or change settings
+    #[command(after_help = "\
+EXAMPLES:
+  tl config                        List settings and their current values
+  tl config set max-duration 10h   Warn about (and offer to cap) timers over 10h
+  tl config unset max-duration     Back to the default
+  tl config set time-format 12h    Show times as 3:05:12 PM instead of 15:05:12
+  tl config set notifications on   Notify when a timer stops or the daily goal is reached
+  tl config set week-start sunday  Start weeks (and --week) on Sunday
+  tl config set duration-format clock
+                                   Show durations as 1:23:45 (or decimal: 1.40 hours)
+  tl config set category-color meetings blue
+                                   Show \"meetings\" in blue in status, log and reports")]
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+    },
+
+    /// Opt-in idle detection: suggest a break after time away from the keyboard
+    #[command(after_help = "\
+EXAMPLES:
+  tl idle              Show the threshold and how long you've been idle
+  tl idle set 10m      Suggest a break after 10 minutes without input
+  tl idle check        Check now (tl status also checks when enabled)
+  tl idle clear        Turn idle detection off
+
+Reads idle time from ioreg on macOS, and xprintidle (X11) or GNOME's idle
+monitor on Linux.")]
+    Idle {
+        #[command(subcommand)]
+        action: Option<IdleAction>,
+    },
+
+    /// Switch to another active timer (pauses the current one)
+    #[command(after_help = "\
+EXAMPLES:
+  tl switch      Lists every active timer, the running one marked and pre-selected
+                 Picking a paused timer pauses the running one and resumes it;
+                 picking the running one leaves everything as it is")]
     Switch,
 
     /// Show or manage time log entries
@@ -124,7 +379,32 @@ EXAMPLES:
 EXAMPLES:
   tl log             Show all log entries
   tl log --today     Show only today's entries
-  tl log --week      Show entries from the last 7 days
+  tl log --week      Show entries from this week
+  tl log --tag billable
+                     Show only entries tagged #billable
+  tl log --category clientA-dev --category clientA-meetings
+                     Show entries in either category, with a combined total
+  tl log --limit 20  Show the 20 most recent entries
+  tl log --host laptop
+                     Show only entries logged on the machine named \"laptop\"
+  tl log --width 70  Fit the table to 70 columns (default: the terminal's width)
+  tl log --duration-format clock
+                     Durations as 1:23:45 (also: decimal for 1.40 hours, hms)
+  tl log --relative --week
+                     Date entries as \"2h ago\", \"3 days ago\" instead of 2024-05-01
+  tl log --sort active --reverse
+                     Longest entries first (also: --sort date, category)
+  tl log -i --week   Browse this week in a table; / filters, e edits, d deletes
+  tl log search bug  Find entries whose name contains \"bug\"
+  tl log show 5      Full details of entry #5, breaks included
+  tl log show 5 --breaks-gantt
+                     Same, with a timeline of active spans and breaks
+  tl log split 5 14:30
+                     Split entry #5 into two at 14:30
+  tl log merge 5 6   Merge entries #5 and #6 into one (they mustn't overlap)
+  tl log breaks 5    Fix or delete the breaks recorded on entry #5
+  tl log link 5 3    Count entry #5 toward todo #3 instead (several ids split it)
+  tl log unlink 5    Detach entry #5 from its todos
   tl log rm 5        Delete log entry #5")]
     Log {
         #[command(subcommand)]
@@ -132,9 +412,242 @@ EXAMPLES:
         /// Show only today's entries
         #[arg(long)]
         today: bool,
-        /// Show entries from the last 7 days
+        /// Show entries from this week (see `week-start`)
+        #[arg(long)]
+        week: bool,
+        /// Show only entries carrying this tag
         #[arg(long)]
+        tag: Option<String>,
+        /// Show only entries in this category (repeat to combine several)
+        #[arg(long)]
+        category: Vec<String>,
+        /// Show only entries logged on this machine (see `tl log show`)
+        #[arg(long)]
+        host: Option<String>,
+        /// Show only the N most recent entries
+        #[arg(long, value_name = "N")]
+        limit: Option<u32>,
+        /// Order entries by this field
+        #[arg(long, value_enum, default_value_t = SortArg::Date)]
+        sort: SortArg,
+        /// Reverse the order (e.g. newest or longest first)
+        #[arg(long)]
+        reverse: bool,
+        /// Fit the table to this many columns (default: the terminal's width)
+        #[arg(long, value_name = "COLS")]
+        width: Option<usize>,
+        /// Date entries by how long ago they started (\"3 days ago\")
+        #[arg(long)]
+        relative: bool,
+        /// Show durations as hms (1h 23m 45s), clock (1:23:45) or decimal
+        /// hours (1.40), overriding the duration-format setting
+        #[arg(long, value_name = "FORMAT")]
+        duration_format: Option<DurationFormatArg>,
+        /// Browse entries in a scrollable table: filter with /, edit, delete
+        /// (only --today / --week narrow it down)
+        #[cfg(feature = "tui")]
+        #[arg(
+            short,
+            long,
+            conflicts_with_all = ["tag", "category", "host", "limit", "sort", "reverse", "width", "relative"]
+        )]
+        interactive: bool,
+    },
+
+    /// Summarize tracked time over a period
+    #[command(after_help = "\
+EXAMPLES:
+  tl report                  Time per category this week
+  tl report --month          ...since the 1st of this month
+  tl report --from 2024-05-01 --to 2024-05-31
+  tl report --month --daily  One line per day, including days with nothing tracked
+  tl report --grid --week    Categories × weekdays grid with row/column totals
+  tl report --markdown       Markdown table, ready to paste into a standup
+  tl report --compare        This week's categories next to last week's, with +/- and %
+  tl report --month --compare
+                             This month so far against the same days of last month
+  tl report --month --category clientA-dev --category clientA-meetings
+                             Just those categories, with one combined total
+  tl report --month --match bug
+                             Only entries whose name matches the regex \"bug\"
+  tl report --match '(?i)^fix ' --daily
+                             Case-insensitive; works with --daily and --grid too
+  tl report --from 2023-01-01 --to 2023-12-31 --include-archived
+                             Count entries moved away by tl archive too
+  tl report --month --duration-format decimal
+                             Hours as 1.40, ready for a timesheet
+  tl report --week --html --file week.html
+                             A styled page with the category and daily tables, to email")]
+    Report {
+        /// Cover this week so far (the default; see `week-start`)
+        #[arg(long, conflicts_with_all = ["month", "from", "to"])]
+        week: bool,
+        /// Cover the current calendar month
+        #[arg(long, conflicts_with_all = ["from", "to"])]
+        month: bool,
+        /// First day to include (YYYY-MM-DD)
+        #[arg(long)]
+        from: Option<String>,
+        /// Last day to include (YYYY-MM-DD, defaults to today)
+        #[arg(long)]
+        to: Option<String>,
+        /// Show total time per calendar day instead of per category
+        #[arg(long, conflicts_with_all = ["grid", "categories_as_tree", "compare"])]
+        daily: bool,
+        /// Show a category × weekday grid with row and column totals
+        #[arg(long, conflicts_with_all = ["categories_as_tree", "compare"])]
+        grid: bool,
+        /// Roll up slash-delimited categories (work/clientA/frontend) into an
+        /// indented tree with subtotals
+        #[arg(long, conflicts_with = "compare")]
+        categories_as_tree: bool,
+        /// Put each category next to the previous period (the same days of
+        /// last week or last month) with the change
+        #[arg(long)]
+        compare: bool,
+        /// Emit a Markdown table (for pasting into standup notes)
+        #[arg(long)]
+        markdown: bool,
+        /// Write a self-contained HTML page with the category and daily
+        /// breakdowns, to share as-is
+        #[arg(long, conflicts_with_all = ["daily", "grid", "categories_as_tree", "compare", "markdown"])]
+        html: bool,
+        /// With --html, write the page to this file instead of printing it
+        #[arg(long, requires = "html", value_name = "PATH")]
+        file: Option<String>,
+        /// Only count entries whose name matches this regex
+        #[arg(long = "match", value_name = "REGEX")]
+        name_match: Option<String>,
+        /// Only count entries in this category (repeat to combine several)
+        #[arg(long)]
+        category: Vec<String>,
+        /// Also count entries moved away by `tl archive`
+        #[arg(long)]
+        include_archived: bool,
+        /// Show durations as hms (1h 23m 45s), clock (1:23:45) or decimal
+        /// hours (1.40), overriding the duration-format setting
+        #[arg(long, value_name = "FORMAT")]
+        duration_format: Option<DurationFormatArg>,
+    },
+
+    /// A short prose summary of the period, ready to paste into chat
+    #[command(after_help = "\
+EXAMPLES:
+  tl digest          \"This week you tracked 28h across 5 categories over 4 days.
+                     Top: development (12h), meetings (6h 10m), email (3h).
+                     You completed 7 todos.\" followed by the completed todos
+  tl digest --month  The same for the current calendar month
+  tl digest --from 2024-05-01 --to 2024-05-07")]
+    Digest {
+        /// Cover this week so far (the default; see `week-start`)
+        #[arg(long, conflicts_with_all = ["month", "from", "to"])]
         week: bool,
+        /// Cover the current calendar month
+        #[arg(long, conflicts_with_all = ["from", "to"])]
+        month: bool,
+        /// First day to include (YYYY-MM-DD)
+        #[arg(long)]
+        from: Option<String>,
+        /// Last day to include (YYYY-MM-DD, defaults to today)
+        #[arg(long)]
+        to: Option<String>,
+    },
+
+    /// Print a bare number (seconds or a count) for scripts and dashboards
+    #[command(after_help = "\
+EXAMPLES:
+  tl query total --today           Active seconds logged today, e.g. 15300
+  tl query total --week --category email
+  tl query total --week --category clientA-dev --category clientA-meetings
+                                   Seconds across both categories combined
+  tl query total --todo 3          Seconds logged against todo #3
+  tl query count --today           Number of entries logged today
+  echo \"$(( $(tl query total --today) / 60 )) minutes\"")]
+    Query {
+        #[command(subcommand)]
+        action: QueryAction,
+    },
+
+    /// All-time totals, tracking streaks and averages
+    #[command(after_help = "\
+EXAMPLES:
+  tl stats       Total tracked time, days tracked, current and longest streak,
+                 average per tracked day, top category and busiest weekday")]
+    Stats,
+
+    /// Dump the whole database (timers, entries, todos) as JSON
+    #[command(after_help = "\
+EXAMPLES:
+  tl export --json                     Print the JSON document to stdout
+  tl export --json --file backup.json  Write it to a file
+  (For a CSV of log entries, see `tl log export`.)")]
+    Export {
+        /// Export as JSON (currently the only format)
+        #[arg(long, required = true)]
+        json: bool,
+        /// Write to this file instead of stdout
+        #[arg(long)]
+        file: Option<String>,
+    },
+
+    /// Load a `tl export --json` dump into this database
+    #[command(after_help = "\
+EXAMPLES:
+  tl import backup.json   Add everything not already present; todo links are
+                          remapped to the new todo ids
+  tl import -             Read the dump from stdin
+  tl import backup.json --mode merge
+                          Add every record, even ones that look like duplicates
+  tl import backup.json --mode replace
+                          Delete all entries, todos and timers first (asks; --yes skips)
+
+The import runs in one transaction: if any record is malformed, nothing is imported.")]
+    Import {
+        /// JSON file to import, or - for stdin
+        file: String,
+        /// What to do with data already here: replace, merge or skip (add
+        /// only records not already present)
+        #[arg(long, value_enum, default_value_t = dump::ImportMode::Skip)]
+        mode: dump::ImportMode,
+        /// With --mode replace, don't ask before deleting
+        #[arg(short, long)]
+        yes: bool,
+    },
+
+    /// Check the database for inconsistencies
+    #[command(after_help = "\
+EXAMPLES:
+  tl doctor        List problems, each with the offending entry, timer or todo id
+  tl doctor --fix  Also repair the safe ones
+
+CHECKS:
+  Entries that end before they start
+  Entries with more active time than their start, end and breaks allow (fixable)
+  Links to deleted todos (fixable)
+  Timers with more than one open break (fixable)
+  More than one running timer
+  Todo links left behind by deleted entries or timers (fixable)")]
+    Doctor {
+        /// Repair what can be repaired safely
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// Move old entries out of the main table to keep everyday commands fast
+    #[command(after_help = "\
+EXAMPLES:
+  tl archive --before 2024-01-01   Archive everything started before 2024
+  tl archive --before 2024-01-01 --dry-run
+                                   List the entries that would be archived
+  tl report --from 2023-01-01 --to 2023-12-31 --include-archived
+                                   Report on archived time
+
+Archived entries no longer show in tl log, tl status, todo totals or the
+other reports unless --include-archived is given. Their ids aren't reused.")]
+    Archive {
+        /// Archive entries started before this day (YYYY-MM-DD)
+        #[arg(long, value_name = "DATE")]
+        before: String,
     },
 
     /// Start the REST API server for Watch app sync
@@ -165,8 +678,17 @@ EXAMPLES:
     #[command(after_help = "\
 EXAMPLES:
   tl todo add Fix the login bug    Add a new todo
-  tl todo list                     List all todos with tracked time
+  tl todo add Ship it --estimate 2h
+                                   Add a todo with a time estimate
+  tl todo estimate 3 1h30m         Set todo #3's estimate
+  tl todo list                     List all todos with time tracked (all time and today)
   tl todo done 3                   Mark todo #3 as done
+  tl todo done 2 4 5               Mark several at once (missing ids are reported)
+  tl todo done --from-entry 12     Mark the todos log entry #12 counts toward as done
+  tl todo archive --done           Archive all completed todos
+  tl todo list --archived          Include archived todos
+  tl todo list --done --week       What you completed this week
+  tl todo merge 7 3                Fold duplicate todo #7 (and its time) into #3
   tl todo rm 3                     Remove todo #3")]
     Todo {
         #[command(subcommand)]
@@ -174,6 +696,90 @@ EXAMPLES:
     },
 }
 
+#[derive(Subcommand)]
+enum GoalAction {
+    /// Set the daily goal
+    Set {
+        /// Duration, e.g. 6h, 7h30m, 90m
+        duration: String,
+    },
+    /// Remove the daily goal
+    Clear,
+}
+
+#[derive(Subcommand)]
+enum BudgetAction {
+    /// Set a category's weekly budget
+    Set {
+        /// Category name (matched case-insensitively)
+        category: String,
+        /// Duration, e.g. 5h, 2h30m, 90m
+        duration: String,
+    },
+    /// Remove a category's budget
+    Rm {
+        /// Category name
+        category: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum TemplateAction {
+    /// Save a template (replaces one with the same key)
+    Add {
+        /// Short key to start it by, e.g. standup
+        key: String,
+        /// Timer name
+        #[arg(long)]
+        name: String,
+        /// Timer category
+        #[arg(long)]
+        category: String,
+        /// Todo ID to link (repeatable)
+        #[arg(long = "todo", value_name = "ID")]
+        todo_ids: Vec<u32>,
+    },
+    /// List saved templates
+    List,
+    /// Remove a template
+    Rm {
+        /// Template key
+        key: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Change a setting
+    Set {
+        /// Setting name, e.g. max-duration
+        key: String,
+        /// New value (category-color takes a category, then a color)
+        #[arg(required = true)]
+        value: Vec<String>,
+    },
+    /// Reset a setting to its default
+    Unset {
+        /// Setting name
+        key: String,
+        /// The category, for category-color
+        category: Vec<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum IdleAction {
+    /// Enable idle detection with this threshold
+    Set {
+        /// Duration, e.g. 10m, 1h
+        duration: String,
+    },
+    /// Offer to record a break if idle past the threshold
+    Check,
+    /// Disable idle detection
+    Clear,
+}
+
 #[derive(Subcommand)]
 enum LogAction {
     /// Edit a log entry
@@ -198,10 +804,62 @@ enum LogAction {
         /// Show only today's entries
         #[arg(long)]
         today: bool,
-        /// Show entries from the last 7 days
+        /// Show entries from this week (see `week-start`)
         #[arg(long)]
         week: bool,
     },
+    /// Search log entries by name (case-insensitive substring)
+    Search {
+        /// Text to look for in entry names
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Show only today's entries
+        #[arg(long)]
+        today: bool,
+        /// Show entries from this week (see `week-start`)
+        #[arg(long)]
+        week: bool,
+    },
+    /// Show every detail of a log entry, including individual breaks
+    Show {
+        /// Log entry ID
+        id: u32,
+        /// Draw a timeline of active time (█) and breaks (─) across the entry
+        #[arg(long)]
+        breaks_gantt: bool,
+    },
+    /// Split a log entry into two at a point in time
+    Split {
+        /// Log entry ID
+        id: u32,
+        /// Split point, e.g. `14:30` or `2024-05-01 14:30`
+        at: String,
+    },
+    /// List and edit (or delete) the breaks of a log entry
+    Breaks {
+        /// Log entry ID
+        id: u32,
+    },
+    /// Merge two log entries into one
+    Merge {
+        /// First log entry ID (its name and category are kept)
+        id1: u32,
+        /// Second log entry ID
+        id2: u32,
+    },
+    /// Link a log entry to different todos, replacing its current links
+    Link {
+        /// Log entry ID
+        id: u32,
+        /// Todo ID(s); time is split evenly between several
+        #[arg(required = true)]
+        todo_ids: Vec<u32>,
+    },
+    /// Remove a log entry's todo links
+    Unlink {
+        /// Log entry ID
+        id: u32,
+    },
     /// Remove a log entry
     Rm {
         /// Log entry ID
@@ -209,20 +867,95 @@ enum LogAction {
     },
 }
 
+#[derive(Subcommand)]
+enum QueryAction {
+    /// Total active seconds of the matching entries
+    Total {
+        /// Only today's entries
+        #[arg(long)]
+        today: bool,
+        /// Only entries from this week (see `week-start`)
+        #[arg(long)]
+        week: bool,
+        /// Only entries in this category (case-insensitive; repeat to add several)
+        #[arg(long)]
+        category: Vec<String>,
+        /// Only entries linked to this todo ID
+        #[arg(long, value_name = "N")]
+        todo: Option<u32>,
+    },
+    /// Number of matching entries
+    Count {
+        /// Only today's entries
+        #[arg(long)]
+        today: bool,
+        /// Only entries from this week (see `week-start`)
+        #[arg(long)]
+        week: bool,
+        /// Only entries in this category (case-insensitive; repeat to add several)
+        #[arg(long)]
+        category: Vec<String>,
+        /// Only entries linked to this todo ID
+        #[arg(long, value_name = "N")]
+        todo: Option<u32>,
+    },
+}
+
 #[derive(Subcommand)]
 enum TodoAction {
     /// Add a new todo item
     Add {
         /// The todo text
         text: Vec<String>,
+        /// Expected time, e.g. 2h or 45m
+        #[arg(long)]
+        estimate: Option<String>,
     },
-    /// List all todo items with tracked time
-    List,
-    /// Mark a todo as done
-    Done {
+    /// Set how long a todo is expected to take (0 clears it)
+    Estimate {
+        /// Todo ID
+        id: u32,
+        /// Duration, e.g. 2h, 1h30m, 45m
+        duration: String,
+    },
+    /// List todo items with tracked time
+    List {
+        /// Include archived todos
+        #[arg(long)]
+        archived: bool,
+        /// Show only completed todos
+        #[arg(long)]
+        done: bool,
+        /// With --done, only todos completed today
+        #[arg(long, requires = "done", conflicts_with = "week")]
+        today: bool,
+        /// With --done, only todos completed this week
+        #[arg(long, requires = "done")]
+        week: bool,
+    },
+    /// Archive a todo (hidden from the list, time links kept)
+    Archive {
+        /// Todo ID
+        #[arg(required_unless_present = "done", conflicts_with = "done")]
+        id: Option<u32>,
+        /// Archive every completed todo
+        #[arg(long)]
+        done: bool,
+    },
+    /// Bring an archived todo back into the list
+    Unarchive {
         /// Todo ID
         id: u32,
     },
+    /// Mark one or more todos as done
+    Done {
+        /// Todo ID(s)
+        #[arg(required_unless_present = "from_entry")]
+        ids: Vec<u32>,
+        /// Also mark the todos linked to this log entry
+        #[arg(long, value_name = "ENTRY_ID")]
+        from_entry: Option<u32>,
+    },
     Edit {
         /// Todo ID
         id: u32,
@@ -234,6 +967,13 @@ enum TodoAction {
         /// Todo ID
         id: u32,
     },
+    /// Merge a duplicate todo into another, keeping the time tracked on both
+    Merge {
+        /// Todo ID to merge away (deleted afterwards)
+        from_id: u32,
+        /// Todo ID to keep
+        into_id: u32,
+    },
     /// Remove a todo item
     Rm {
         /// Todo ID
@@ -241,9 +981,63 @@ enum TodoAction {
     },
 }
 
+// Command-line spellings of the library's display options; the library
+// itself doesn't depend on clap.
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum DurationFormatArg {
+    Hms,
+    Clock,
+    Decimal,
+}
+
+impl From<DurationFormatArg> for DurationFormat {
+    fn from(arg: DurationFormatArg) -> Self {
+        match arg {
+            DurationFormatArg::Hms => DurationFormat::Hms,
+            DurationFormatArg::Clock => DurationFormat::Clock,
+            DurationFormatArg::Decimal => DurationFormat::Decimal,
+        }
+    }
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum PrecisionArg {
+    Seconds,
+    Minutes,
+    Hours,
+}
+
+impl From<PrecisionArg> for Precision {
+    fn from(arg: PrecisionArg) -> Self {
+        match arg {
+            PrecisionArg::Seconds => Precision::Seconds,
+            PrecisionArg::Minutes => Precision::Minutes,
+            PrecisionArg::Hours => Precision::Hours,
+        }
+    }
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum SortArg {
+    Date,
+    Active,
+    Category,
+}
+
+impl From<SortArg> for EntrySort {
+    fn from(arg: SortArg) -> Self {
+        match arg {
+            SortArg::Date => EntrySort::Date,
+            SortArg::Active => EntrySort::Active,
+            SortArg::Category => EntrySort::Category,
+        }
+    }
+}
+
 #[cfg(feature = "serve")]
-fn run_server(port: u16, #[allow(unused)] enable_ble: bool, #[allow(unused)] enable_icloud: bool) {
-    let conn = open_db();
+fn run_server(port: u16, #[allow(unused)] enable_ble: bool, #[allow(unused)] enable_icloud: bool, verbosity: Verbosity) {
+    let conn = open_db_or_exit(verbosity);
     mdns::advertise(port);
 
     #[cfg(feature = "ble")]
@@ -253,7 +1047,7 @@ fn run_server(port: u16, #[allow(unused)] enable_ble: bool, #[allow(unused)] ena
 
     #[cfg(feature = "icloud")]
     if enable_icloud {
-        let db = std::sync::Arc::new(std::sync::Mutex::new(open_db()));
+        let db = std::sync::Arc::new(std::sync::Mutex::new(open_db_or_exit(verbosity)));
         icloud::start_background_sync(db);
     }
 
@@ -261,9 +1055,76 @@ fn run_server(port: u16, #[allow(unused)] enable_ble: bool, #[allow(unused)] ena
     rt.block_on(server::run(conn, port));
 }
 
+/// `open_db`, exiting with its message if the database can't be used.
+fn open_db_or_exit(verbosity: Verbosity) -> rusqlite::Connection {
+    open_db(verbosity).unwrap_or_else(|e| {
+        eprintln!("{e}");
+        std::process::exit(1);
+    })
+}
+
+/// Commands that honour `--dry-run`. `tl doctor` only reports with it, even
+/// with `--fix`.
+fn supports_dry_run(command: &Commands) -> bool {
+    matches!(
+        command,
+        Commands::Archive { .. }
+            | Commands::Doctor { .. }
+            | Commands::Log { action: Some(LogAction::Rm { .. } | LogAction::Merge { .. }), .. }
+            | Commands::Todo {
+                action: TodoAction::Rm { .. } | TodoAction::Merge { .. } | TodoAction::Archive { .. } | TodoAction::Done { .. }
+            }
+    )
+}
+
 fn main() {
     let cli = Cli::parse();
-    let conn = open_db();
+    let verbosity = if cli.quiet {
+        Verbosity::Quiet
+    } else if cli.verbose {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
+    };
+    if cli.dry_run && !supports_dry_run(&cli.command) {
+        eprintln!(
+            "--dry-run only works with tl archive, tl log rm/merge, tl todo rm/merge/archive/done \
+             and tl doctor; run this command without it."
+        );
+        std::process::exit(1);
+    }
+    // The server stops on Ctrl-C as before; everywhere else it cancels.
+    #[cfg(feature = "serve")]
+    let serving = matches!(cli.command, Commands::Serve { .. });
+    #[cfg(not(feature = "serve"))]
+    let serving = false;
+    if !serving {
+        prompt::handle_ctrl_c();
+    }
+    let conn = open_db_or_exit(verbosity);
+    let running = state::running_timers(&conn);
+    if running.len() > 1 {
+        let ids: Vec<String> = running.iter().map(|t| format!("#{}", t.id.unwrap())).collect();
+        eprintln!(
+            "Warning: {} timers are marked running ({}); using {}. Run `tl doctor` to check the database.",
+            running.len(),
+            ids.join(", "),
+            ids[0],
+        );
+    }
+    // Both may write: the first-run marker, a break for time spent asleep.
+    if !cli.dry_run {
+        if !verbosity.quiet() && state::first_run(&conn) {
+            timer::welcome();
+        }
+        idle::check_sleep(&conn);
+    }
+    let dry_run = cli.dry_run;
+    let duration_format = match &cli.command {
+        Commands::Log { duration_format, .. } | Commands::Report { duration_format, .. } => *duration_format,
+        _ => None,
+    };
+    duration::use_format(duration_format.map_or_else(|| state::duration_format(&conn), DurationFormat::from));
 
     match cli.command {
         #[cfg(feature = "serve")]
@@ -288,35 +1149,130 @@ fn main() {
             #[cfg(not(feature = "icloud"))]
             { icloud_flag = false; }
 
-            run_server(port, ble_flag, icloud_flag);
-            return;
+            run_server(port, ble_flag, icloud_flag, verbosity);
         }
         #[cfg(feature = "tui")]
         Commands::Ui => {
             tui::run(&conn);
-            return;
         }
-        Commands::Start => timer::start(&conn),
-        Commands::Stop => timer::stop(&conn),
-        Commands::Pause => timer::pause(&conn),
-        Commands::Resume => timer::resume(&conn),
-        Commands::Restart => timer::restart(&conn),
-        Commands::Pomodoro { minutes } => timer::pomodoro(&conn, minutes),
-        Commands::Status => timer::status(&conn),
-        Commands::Switch => timer::switch(&conn),
-        Commands::Log { action, today, week } => match action {
-            None => timer::log(&conn, today, week),
+        Commands::Start { pomodoro, work, break_mins, at, ago, template, next } => {
+            let started_at = timer::past_ts(at.as_deref(), ago.as_deref());
+            timer::start(&conn, pomodoro.then_some((work, break_mins)), started_at, template.as_deref(), next, verbosity)
+        }
+        Commands::T { key } => timer::start(&conn, None, None, Some(&key), false, verbosity),
+        Commands::Template { action } => match action {
+            TemplateAction::Add { key, name, category, todo_ids } => {
+                template::add(&conn, &key, &name, &category, &todo_ids)
+            }
+            TemplateAction::List => template::list(&conn),
+            TemplateAction::Rm { key } => template::rm(&conn, &key),
+        },
+        Commands::Stop { tags, at, ago, force, cap, done, keep } => {
+            let complete_todos = (done || keep).then_some(done);
+            let ended_at = timer::past_ts(at.as_deref(), ago.as_deref());
+            timer::stop(&conn, &tags, ended_at, force, cap, complete_todos, verbosity)
+        }
+        Commands::Note { text } => timer::note(&conn, &text.join(" "), verbosity),
+        Commands::Pause { label, remind } => timer::pause(&conn, label, remind.as_deref(), verbosity),
+        Commands::Interrupt { duration, reason } => timer::interrupt(&conn, &duration, reason, verbosity),
+        Commands::Resume { last, force } => timer::resume(&conn, last, force, verbosity),
+        Commands::Restart => timer::restart(&conn, verbosity),
+        Commands::Pomodoro { minutes, break_mins } => timer::pomodoro(&conn, minutes, break_mins),
+        Commands::Status { precision } => timer::status(&conn, precision.into()),
+        Commands::Today => timer::today(&conn),
+        Commands::Goal { action } => match action {
+            None => goal::show(&conn),
+            Some(GoalAction::Set { duration }) => goal::set(&conn, &duration),
+            Some(GoalAction::Clear) => goal::clear(&conn),
+        },
+        Commands::Budget { action } => match action {
+            None => budget::list(&conn),
+            Some(BudgetAction::Set { category, duration }) => budget::set(&conn, &category, &duration),
+            Some(BudgetAction::Rm { category }) => budget::clear(&conn, &category),
+        },
+        Commands::Config { action } => match action {
+            None => config::list(&conn),
+            Some(ConfigAction::Set { key, value }) => config::set(&conn, &key, &value),
+            Some(ConfigAction::Unset { key, category }) => config::unset(&conn, &key, &category),
+        },
+        Commands::Idle { action } => match action {
+            None => idle::show(&conn),
+            Some(IdleAction::Set { duration }) => idle::set(&conn, &duration),
+            Some(IdleAction::Check) => idle::check(&conn),
+            Some(IdleAction::Clear) => idle::clear(&conn),
+        },
+        Commands::Switch => timer::switch(&conn, verbosity),
+        #[cfg(feature = "tui")]
+        Commands::Log { action: None, today, week, interactive: true, .. } => {
+            tui::run_log(&conn, timer::since_ts(&conn, today, week));
+        }
+        Commands::Log { action, today, week, tag, category, host, limit, sort, reverse, width, relative, .. } => match action {
+            None => {
+                let filter = EntryFilter {
+                    tag: tag.as_deref(),
+                    categories: &category,
+                    host: host.as_deref(),
+                    limit,
+                    sort: sort.into(),
+                    reverse,
+                    ..Default::default()
+                };
+                timer::log(&conn, today, week, filter, width, relative)
+            }
             Some(LogAction::Edit { id, name, category, add, sub }) => timer::edit_log(&conn, id, name, category, add, sub),
             Some(LogAction::Export { today, week }) => timer::export_log(&conn, today, week),
-            Some(LogAction::Rm { id }) => timer::rm(&conn, id),
+            Some(LogAction::Search { query, today, week }) => timer::search(&conn, &query.join(" "), today, week),
+            Some(LogAction::Show { id, breaks_gantt }) => timer::show(&conn, id, breaks_gantt),
+            Some(LogAction::Split { id, at }) => timer::split(&conn, id, &at),
+            Some(LogAction::Merge { id1, id2 }) => timer::merge(&conn, id1, id2, dry_run),
+            Some(LogAction::Breaks { id }) => timer::edit_breaks(&conn, id),
+            Some(LogAction::Link { id, todo_ids }) => timer::link(&conn, id, &todo_ids),
+            Some(LogAction::Unlink { id }) => timer::unlink(&conn, id),
+            Some(LogAction::Rm { id }) => timer::rm(&conn, id, dry_run),
+        },
+        Commands::Report { week: _, month, from, to, daily, grid, categories_as_tree, compare, markdown, html, file, name_match, category, include_archived, .. } => {
+            let period = report::Period::resolve(&conn, month, from.as_deref(), to.as_deref())
+                .matching(name_match.as_deref())
+                .in_categories(category)
+                .with_archived(include_archived);
+            if html {
+                report::html(&conn, &period, file.as_deref());
+            } else if compare {
+                report::compare(&conn, &period, markdown);
+            } else if categories_as_tree {
+                report::tree(&conn, &period, markdown);
+            } else if grid {
+                report::grid(&conn, &period, markdown);
+            } else if daily {
+                report::daily(&conn, &period, markdown);
+            } else {
+                report::summary(&conn, &period, markdown);
+            }
+        }
+        Commands::Digest { week: _, month, from, to } => {
+            let period = report::Period::resolve(&conn, month, from.as_deref(), to.as_deref());
+            report::digest(&conn, &period, month);
+        }
+        Commands::Query { action } => match action {
+            QueryAction::Total { today, week, category, todo } => query::total(&conn, today, week, &category, todo),
+            QueryAction::Count { today, week, category, todo } => query::count(&conn, today, week, &category, todo),
         },
+        Commands::Stats => report::stats(&conn),
+        Commands::Export { json: _, file } => dump::export(&conn, file.as_deref()),
+        Commands::Import { file, mode, yes } => dump::import(&conn, &file, mode, yes),
+        Commands::Doctor { fix } => doctor::run(&conn, fix && !dry_run),
+        Commands::Archive { before } => timer::archive(&conn, &before, dry_run),
         Commands::Todo { action } => match action {
-            TodoAction::Add { text } => todo::add(&conn, &text.join(" ")),
-            TodoAction::List => todo::list(&conn),
+            TodoAction::Add { text, estimate } => todo::add(&conn, &text.join(" "), estimate.as_deref(), verbosity),
+            TodoAction::Estimate { id, duration } => todo::estimate(&conn, id, &duration),
+            TodoAction::List { archived, done, today, week } => todo::list(&conn, archived, done, today, week),
+            TodoAction::Archive { id, done } => todo::archive(&conn, id, done, dry_run),
+            TodoAction::Unarchive { id } => todo::unarchive(&conn, id),
             TodoAction::Edit { id, text } => todo::edit(&conn, id, &text.join(" ")),
-            TodoAction::Done { id } => todo::done(&conn, id),
+            TodoAction::Done { ids, from_entry } => todo::done(&conn, &ids, from_entry, dry_run),
             TodoAction::Undo { id } => todo::undo(&conn, id),
-            TodoAction::Rm { id } => todo::rm(&conn, id),
+            TodoAction::Merge { from_id, into_id } => todo::merge(&conn, from_id, into_id, dry_run),
+            TodoAction::Rm { id } => todo::rm(&conn, id, dry_run),
         },
     }
 }
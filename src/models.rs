@@ -4,6 +4,10 @@ use serde::{Deserialize, Serialize};
 pub struct BreakPeriod {
     pub start_ts: i64,
     pub end_ts: i64,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub interruption: bool,
 }
 
 #[derive(Debug, Serialize)]
@@ -38,6 +42,7 @@ pub struct EntryResponse {
     pub break_secs: i64,
     pub todo_id: Option<u32>,
     pub last_modified: i64,
+    pub tags: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -56,6 +61,8 @@ pub struct TodoResponse {
     pub created_at: i64,
     pub last_modified: i64,
     pub total_secs: i64,
+    pub estimate_secs: Option<i64>,
+    pub archived: bool,
 }
 
 #[derive(Debug, Deserialize)]
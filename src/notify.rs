@@ -0,0 +1,46 @@
+//! Opt-in desktop notifications (`tl config set notifications on`), shown
+//! through `notify-rust` when built with the `notifications` feature.
+
+use rusqlite::Connection;
+
+use crate::duration::format_duration;
+use crate::state::*;
+
+pub fn enabled(conn: &Connection) -> bool {
+    get_setting(conn, "notifications").as_deref() == Some("on")
+}
+
+/// Show a desktop notification if they're turned on. Without the feature, or
+/// when no notification daemon is running, this quietly does nothing.
+pub fn send(conn: &Connection, summary: &str, body: &str) {
+    if !enabled(conn) {
+        return;
+    }
+    #[cfg(feature = "notifications")]
+    let _ = notify_rust::Notification::new()
+        .appname("tl")
+        .summary(summary)
+        .body(body)
+        .show();
+    #[cfg(not(feature = "notifications"))]
+    let _ = (summary, body);
+}
+
+/// Notify once per day, the first time today's total reaches the daily goal.
+pub fn check_goal(conn: &Connection) {
+    let Some(goal) = daily_goal_secs(conn) else {
+        return;
+    };
+    if !enabled(conn) {
+        return;
+    }
+    let today = today_date(conn).to_string();
+    if get_setting(conn, "goal_notified_on").as_deref() == Some(today.as_str()) {
+        return;
+    }
+    let done = today_active_secs(conn);
+    if done >= goal {
+        set_setting(conn, "goal_notified_on", &today);
+        send(conn, "Daily goal reached", &format!("{} tracked today", format_duration(done)));
+    }
+}
@@ -0,0 +1,67 @@
+//! Backing out of an interactive prompt. Ctrl-C at any prompt, or Esc at a
+//! yes/no or pick-one question, ends the command with "Cancelled." instead
+//! of a panic. Commands only write once their prompts are answered, so there
+//! is nothing to undo.
+
+use std::io::{ErrorKind, IsTerminal};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Exit status after a cancelled prompt, the same as a shell reports for a
+/// process stopped by Ctrl-C.
+pub const CANCELLED_EXIT_CODE: i32 = 130;
+
+/// Route Ctrl-C through `cancel`. Prompts raise SIGINT themselves when
+/// Ctrl-C is pressed, which would otherwise kill the process with the cursor
+/// still hidden; with a handler installed they return an error instead.
+pub fn handle_ctrl_c() {
+    let _ = ctrlc::set_handler(|| cancel());
+}
+
+/// End the command after the user backed out of a prompt.
+pub fn cancel() -> ! {
+    // Ctrl-C at a prompt reaches here twice: from the signal handler's thread
+    // and from the prompt's error. Let only the first one report and exit.
+    static CANCELLED: AtomicBool = AtomicBool::new(false);
+    if CANCELLED.swap(true, Ordering::SeqCst) {
+        loop {
+            std::thread::park();
+        }
+    }
+    // Select prompts hide the cursor and don't get the chance to restore it.
+    if std::io::stderr().is_terminal() {
+        eprint!("\x1b[?25h");
+    }
+    eprintln!("Cancelled.");
+    std::process::exit(CANCELLED_EXIT_CODE);
+}
+
+/// The result of `interact_text()` or `interact_opt()`.
+pub trait Answer<T> {
+    /// The answer, or cancel the command on Ctrl-C.
+    fn answer(self) -> T;
+}
+
+impl<T> Answer<T> for dialoguer::Result<T> {
+    fn answer(self) -> T {
+        match self {
+            Ok(value) => value,
+            Err(dialoguer::Error::IO(e)) if e.kind() == ErrorKind::Interrupted => cancel(),
+            Err(e) => {
+                eprintln!("Prompt failed: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+/// The result of `interact_opt()` where Esc has no meaning of its own.
+pub trait Choice<T> {
+    /// The choice, or cancel the command on Esc or Ctrl-C.
+    fn chosen(self) -> T;
+}
+
+impl<T> Choice<T> for dialoguer::Result<Option<T>> {
+    fn chosen(self) -> T {
+        self.answer().unwrap_or_else(|| cancel())
+    }
+}
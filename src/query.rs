@@ -0,0 +1,39 @@
+use rusqlite::Connection;
+
+use crate::state::*;
+use crate::timer::since_ts;
+
+/// Active seconds of each entry matching the `tl query` flags. Under
+/// `--todo`, an entry linked to several todos counts an even share and
+/// archived entries count too, the same as `tl todo list`.
+fn matching_secs(conn: &Connection, today: bool, week: bool, categories: &[String], todo: Option<u32>) -> Vec<i64> {
+    let entries = filter_entries(
+        conn,
+        &EntryFilter {
+            since_ts: since_ts(conn, today, week),
+            categories,
+            include_archived: todo.is_some(),
+            ..Default::default()
+        },
+    );
+    entries
+        .iter()
+        .filter_map(|e| match todo {
+            None => Some(e.active_secs),
+            Some(id) => {
+                let todos = entry_todos(conn, e);
+                todos.contains(&id).then(|| e.active_secs / todos.len() as i64)
+            }
+        })
+        .collect()
+}
+
+/// Print the total active seconds of the matching entries as a bare integer.
+pub fn total(conn: &Connection, today: bool, week: bool, categories: &[String], todo: Option<u32>) {
+    println!("{}", matching_secs(conn, today, week, categories, todo).iter().sum::<i64>());
+}
+
+/// Print the number of matching entries as a bare integer.
+pub fn count(conn: &Connection, today: bool, week: bool, categories: &[String], todo: Option<u32>) {
+    println!("{}", matching_secs(conn, today, week, categories, todo).len());
+}
@@ -0,0 +1,699 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use chrono::{Datelike, Duration, NaiveDate};
+use regex::Regex;
+use rusqlite::Connection;
+
+use crate::budget;
+use crate::color;
+use crate::duration::format_duration;
+use crate::state::*;
+use crate::timer::truncate;
+
+/// An inclusive range of tracking days, optionally narrowed to entries whose
+/// name matches a pattern. Days start at the configured `day-start` hour.
+#[derive(Clone)]
+pub struct Period {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub name_match: Option<Regex>,
+    /// Only these categories (`--category`, repeatable); empty means all.
+    pub categories: Vec<String>,
+    /// Also count entries moved away by `tl archive` (`--include-archived`).
+    pub include_archived: bool,
+    pub day_start_hour: u32,
+}
+
+impl Period {
+    /// Work out the report range from the CLI flags. `--from`/`--to` win over
+    /// `--month`; otherwise it's this week so far (`--week`, the default).
+    pub fn resolve(conn: &Connection, month: bool, from: Option<&str>, to: Option<&str>) -> Period {
+        let day_start_hour = day_start_hour(conn);
+        let today = today_date(conn);
+        let parse = |s: &str| match NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            Ok(d) => d,
+            Err(_) => {
+                eprintln!("Invalid date \"{s}\" (expected YYYY-MM-DD).");
+                std::process::exit(1);
+            }
+        };
+
+        let period = if from.is_some() || to.is_some() {
+            let to = to.map_or(today, parse);
+            let from = from.map_or(to - Duration::days(6), parse);
+            Period { from, to, name_match: None, categories: Vec::new(), include_archived: false, day_start_hour }
+        } else if month {
+            Period { from: today.with_day0(0).unwrap(), to: today, name_match: None, categories: Vec::new(), include_archived: false, day_start_hour }
+        } else {
+            Period { from: this_week_start(conn), to: today, name_match: None, categories: Vec::new(), include_archived: false, day_start_hour }
+        };
+
+        if period.from > period.to {
+            eprintln!("--from must not be after --to.");
+            std::process::exit(1);
+        }
+        period
+    }
+
+    /// Only count entries whose name matches `pattern` (`--match`). Exits if
+    /// it isn't a valid regex.
+    pub fn matching(mut self, pattern: Option<&str>) -> Period {
+        self.name_match = pattern.map(|p| match Regex::new(p) {
+            Ok(re) => re,
+            Err(e) => {
+                eprintln!("Invalid --match pattern: {e}");
+                std::process::exit(1);
+            }
+        });
+        self
+    }
+
+    /// Only count entries in one of `categories` (`--category`).
+    pub fn in_categories(mut self, categories: Vec<String>) -> Period {
+        self.categories = categories;
+        self
+    }
+
+    /// Count archived entries too (`--include-archived`).
+    pub fn with_archived(mut self, include_archived: bool) -> Period {
+        self.include_archived = include_archived;
+        self
+    }
+
+    /// Report heading, e.g. `Report 2024-05-01 → 2024-05-07`.
+    fn title(&self) -> String {
+        let mut title = format!("Report {} → {}", self.from, self.to);
+        if !self.categories.is_empty() {
+            title.push_str(&format!(", categories {}", self.categories.join(" + ")));
+        }
+        if let Some(re) = &self.name_match {
+            title.push_str(&format!(", names matching /{re}/"));
+        }
+        title
+    }
+
+    /// The period to compare against (`--compare`): the same days of the
+    /// month before for a month-to-date period, the same days of the week
+    /// before for a shorter one, otherwise the same number of days right
+    /// before it.
+    pub fn previous(&self) -> Period {
+        let (from, to) = if self.from.day() == 1 && self.from.month() == self.to.month() && self.from.year() == self.to.year() {
+            let from = self.from - chrono::Months::new(1);
+            let last = (self.from - Duration::days(1)).day();
+            (from, from.with_day(self.to.day().min(last)).unwrap())
+        } else if self.days() < 7 {
+            // A week so far: the same days of the week before.
+            (self.from - Duration::days(7), self.to - Duration::days(7))
+        } else {
+            (self.from - Duration::days(self.days()), self.from - Duration::days(1))
+        };
+        Period { from, to, ..self.clone() }
+    }
+
+    pub fn days(&self) -> i64 {
+        (self.to - self.from).num_days() + 1
+    }
+
+    /// Unix timestamps bounding the period, from the start of its first day
+    /// to the start of the day after its last.
+    fn bounds(&self) -> (i64, i64) {
+        (
+            day_start_ts(self.from, self.day_start_hour),
+            day_start_ts(self.to + Duration::days(1), self.day_start_hour),
+        )
+    }
+
+    /// The tracking day an entry counts towards.
+    fn date_of(&self, e: &TimeEntry) -> NaiveDate {
+        day_of(e.started_at, self.day_start_hour)
+    }
+
+    fn entries(&self, conn: &Connection) -> Vec<TimeEntry> {
+        let (since_ts, until_ts) = self.bounds();
+        let mut entries = filter_entries(
+            conn,
+            &EntryFilter {
+                since_ts: Some(since_ts),
+                until_ts: Some(until_ts),
+                categories: &self.categories,
+                include_archived: self.include_archived,
+                ..Default::default()
+            },
+        );
+        if let Some(re) = &self.name_match {
+            entries.retain(|e| re.is_match(&e.name));
+        }
+        entries
+    }
+}
+
+/// Time per category over the period, as a plain-text or Markdown table.
+pub fn summary(conn: &Connection, period: &Period, markdown: bool) {
+    let entries = period.entries(conn);
+    if markdown {
+        println!("## {}\n", period.title());
+    } else {
+        println!("{}\n", period.title());
+    }
+    if entries.is_empty() {
+        println!("No entries in this period.");
+        return;
+    }
+
+    let mut rows: Vec<_> = by_category(&entries).into_iter().collect();
+    rows.sort_by_key(|(_, (secs, _))| std::cmp::Reverse(*secs));
+    let total: i64 = entries.iter().map(|e| e.active_secs).sum();
+
+    if markdown {
+        println!("| Category | Time | Entries |");
+        println!("|---|---:|---:|");
+        for (cat, (secs, count)) in &rows {
+            println!("| {} | {} | {count} |", md_escape(cat), format_duration(*secs));
+        }
+        println!("| **Total** | **{}** | **{}** |", format_duration(total), entries.len());
+        return;
+    }
+
+    // Weekly budgets only line up with reports on a single week from its
+    // start. Usage comes from the same place as `tl budget`, live timers
+    // included.
+    let one_week = period.days() <= 7 && period.from == week_start_for(period.from, week_start(conn));
+    let budgets = if one_week { list_budgets(conn) } else { Vec::new() };
+    let used = if budgets.is_empty() {
+        Vec::new()
+    } else {
+        let (since_ts, until_ts) = period.bounds();
+        budget::usage(conn, since_ts, until_ts)
+    };
+    let budget_note = |cat: &str| {
+        budgets
+            .iter()
+            .find(|(c, _)| c.eq_ignore_ascii_case(cat))
+            .map_or(String::new(), |(_, budget)| {
+                format!("  {}", budget::remaining(*budget, budget::used_by(&used, cat)))
+            })
+    };
+
+    let palette = color::Palette::load(conn);
+    println!("{:<20} {:<12} Entries", "Category", "Time");
+    println!("{}", "-".repeat(42));
+    for (cat, (secs, count)) in &rows {
+        let label = palette.paint(cat, &format!("{cat:<20}"));
+        println!("{label} {:<12} {count:<7}{}", format_duration(*secs), budget_note(cat));
+    }
+    println!("{}", "-".repeat(42));
+    println!("{:<20} {:<12} {}", "TOTAL", format_duration(total), entries.len());
+}
+
+/// Active seconds and entry count per category.
+fn by_category(entries: &[TimeEntry]) -> BTreeMap<&str, (i64, u32)> {
+    let mut by_cat: BTreeMap<&str, (i64, u32)> = BTreeMap::new();
+    for e in entries {
+        let slot = by_cat.entry(&e.category).or_default();
+        slot.0 += e.active_secs;
+        slot.1 += 1;
+    }
+    by_cat
+}
+
+/// Active seconds per day of the period, zero for days with nothing tracked.
+fn by_day(period: &Period, entries: &[TimeEntry]) -> BTreeMap<NaiveDate, i64> {
+    let mut by_day: BTreeMap<NaiveDate, i64> = BTreeMap::new();
+    let mut day = period.from;
+    while day <= period.to {
+        by_day.insert(day, 0);
+        day += Duration::days(1);
+    }
+    for e in entries {
+        *by_day.entry(period.date_of(e)).or_insert(0) += e.active_secs;
+    }
+    by_day
+}
+
+/// The category and daily breakdowns as a self-contained HTML page (inline
+/// CSS, nothing fetched), written to `file` or printed.
+pub fn html(conn: &Connection, period: &Period, file: Option<&str>) {
+    let entries = period.entries(conn);
+    let mut rows: Vec<_> = by_category(&entries).into_iter().collect();
+    rows.sort_by_key(|(_, (secs, _))| std::cmp::Reverse(*secs));
+    let by_day = by_day(period, &entries);
+    let total: i64 = entries.iter().map(|e| e.active_secs).sum();
+    let title = html_escape(&period.title());
+
+    let mut page = format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<title>{title}</title>
+<style>
+body {{ font-family: -apple-system, \"Segoe UI\", Helvetica, Arial, sans-serif; color: #222; max-width: 40em; margin: 2em auto; padding: 0 1em; }}
+h1 {{ font-size: 1.4em; }}
+h2 {{ font-size: 1.1em; margin-top: 2em; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ padding: 0.4em 0.8em; border-bottom: 1px solid #ddd; text-align: left; }}
+th {{ background: #f4f4f4; }}
+td.num, th.num {{ text-align: right; font-variant-numeric: tabular-nums; }}
+tr.total td {{ font-weight: bold; border-top: 2px solid #999; }}
+td.none {{ color: #aaa; }}
+</style>
+</head>
+<body>
+<h1>{title}</h1>
+"
+    );
+    if entries.is_empty() {
+        page.push_str("<p>No entries in this period.</p>\n");
+    } else {
+        page.push_str("<h2>By category</h2>\n<table>\n");
+        page.push_str("<tr><th>Category</th><th class=\"num\">Time</th><th class=\"num\">Entries</th></tr>\n");
+        for (cat, (secs, count)) in &rows {
+            page.push_str(&format!(
+                "<tr><td>{}</td><td class=\"num\">{}</td><td class=\"num\">{count}</td></tr>\n",
+                html_escape(cat),
+                format_duration(*secs),
+            ));
+        }
+        page.push_str(&format!(
+            "<tr class=\"total\"><td>Total</td><td class=\"num\">{}</td><td class=\"num\">{}</td></tr>\n</table>\n",
+            format_duration(total),
+            entries.len(),
+        ));
+
+        page.push_str("<h2>By day</h2>\n<table>\n<tr><th>Day</th><th class=\"num\">Time</th></tr>\n");
+        for (day, secs) in &by_day {
+            let cell = if *secs > 0 {
+                format!("<td class=\"num\">{}</td>", format_duration(*secs))
+            } else {
+                "<td class=\"num none\">-</td>".to_string()
+            };
+            page.push_str(&format!("<tr><td>{}</td>{cell}</tr>\n", day.format("%a %Y-%m-%d")));
+        }
+        page.push_str(&format!(
+            "<tr class=\"total\"><td>Daily average</td><td class=\"num\">{}</td></tr>\n</table>\n",
+            format_duration(total / period.days()),
+        ));
+    }
+    page.push_str("</body>\n</html>\n");
+
+    match file {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, page) {
+                eprintln!("Couldn't write {path}: {e}");
+                std::process::exit(1);
+            }
+            println!("Wrote {} to {path}.", period.title());
+        }
+        None => print!("{page}"),
+    }
+}
+
+/// Category totals for the period next to those of the period before it
+/// (see `Period::previous`), with the change in time and percent.
+pub fn compare(conn: &Connection, period: &Period, markdown: bool) {
+    let before = period.previous();
+    let (now_entries, before_entries) = (period.entries(conn), before.entries(conn));
+    let title = format!("{} vs {} → {}", period.title(), before.from, before.to);
+    if markdown {
+        println!("## {title}\n");
+    } else {
+        println!("{title}\n");
+    }
+    if now_entries.is_empty() && before_entries.is_empty() {
+        println!("No entries in either period.");
+        return;
+    }
+
+    let (now, then) = (by_category(&now_entries), by_category(&before_entries));
+    let mut rows: Vec<(&str, i64, i64)> = now
+        .keys()
+        .chain(then.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|cat| (*cat, now.get(cat).map_or(0, |v| v.0), then.get(cat).map_or(0, |v| v.0)))
+        .collect();
+    rows.sort_by_key(|&(_, secs, prev)| (std::cmp::Reverse(secs), std::cmp::Reverse(prev)));
+    let total = now_entries.iter().map(|e| e.active_secs).sum();
+    let prev_total = before_entries.iter().map(|e| e.active_secs).sum();
+    let time = |secs: i64| if secs > 0 { format_duration(secs) } else { "-".into() };
+
+    if markdown {
+        println!("| Category | This period | Previous | Change |");
+        println!("|---|---:|---:|---:|");
+        for (cat, secs, prev) in &rows {
+            println!("| {} | {} | {} | {} |", md_escape(cat), time(*secs), time(*prev), change(*secs, *prev));
+        }
+        println!(
+            "| **Total** | **{}** | **{}** | **{}** |",
+            time(total),
+            time(prev_total),
+            change(total, prev_total),
+        );
+        return;
+    }
+
+    let palette = color::Palette::load(conn);
+    println!("{:<20} {:<12} {:<12} Change", "Category", "This period", "Previous");
+    println!("{}", "-".repeat(66));
+    for (cat, secs, prev) in &rows {
+        let label = palette.paint(cat, &format!("{:<20}", truncate(cat, 19)));
+        println!("{label} {:<12} {:<12} {}", time(*secs), time(*prev), change(*secs, *prev));
+    }
+    println!("{}", "-".repeat(66));
+    println!("{:<20} {:<12} {:<12} {}", "TOTAL", time(total), time(prev_total), change(total, prev_total));
+}
+
+/// `+1h 30m 00s (+25%)`, `-45m 00s (-100%)`, or `new` when there was
+/// nothing to compare against.
+fn change(secs: i64, prev: i64) -> String {
+    if prev == 0 {
+        return if secs == 0 { "-".into() } else { "new".into() };
+    }
+    let delta = secs - prev;
+    let sign = if delta < 0 { "-" } else { "+" };
+    format!("{sign}{} ({sign}{}%)", format_duration(delta.abs()), (delta * 100 / prev).abs())
+}
+
+/// One line per calendar day in the period, including days with nothing
+/// tracked, followed by the period total and daily average.
+pub fn daily(conn: &Connection, period: &Period, markdown: bool) {
+    let by_day = by_day(period, &period.entries(conn));
+    let total: i64 = by_day.values().sum();
+    let average = total / period.days();
+
+    if markdown {
+        println!("## {}\n", period.title());
+        println!("| Day | Time |");
+        println!("|---|---:|");
+        for (day, secs) in &by_day {
+            let time = if *secs > 0 { format_duration(*secs) } else { "-".into() };
+            println!("| {} | {time} |", day.format("%a %Y-%m-%d"));
+        }
+        println!("| **Total** | **{}** |", format_duration(total));
+        println!("| **Daily average** | **{}** |", format_duration(average));
+        return;
+    }
+
+    println!("{}\n", period.title());
+    for (day, secs) in &by_day {
+        let time = if *secs > 0 { format_duration(*secs) } else { "-".into() };
+        println!("{}  {time}", day.format("%a %Y-%m-%d"));
+    }
+
+    println!("{}", "-".repeat(30));
+    println!("{:<16}{}", "Total", format_duration(total));
+    println!("{:<16}{}", "Daily average", format_duration(average));
+}
+
+/// Categories × weekdays grid of active time, with row and column totals.
+/// Columns run from the configured week start (Mon–Sun or Sun–Sat).
+pub fn grid(conn: &Connection, period: &Period, markdown: bool) {
+    let entries = period.entries(conn);
+    if markdown {
+        println!("## {}\n", period.title());
+    } else {
+        println!("{}\n", period.title());
+    }
+    if entries.is_empty() {
+        println!("No entries in this period.");
+        return;
+    }
+
+    let mut categories: Vec<&str> = entries.iter().map(|e| e.category.as_str()).collect();
+    categories.sort_unstable();
+    categories.dedup();
+    let first = week_start(conn);
+    let mut cells: Vec<Vec<i64>> = vec![vec![0; 7]; categories.len()];
+    for e in &entries {
+        let row = categories.binary_search(&e.category.as_str()).unwrap();
+        let date = period.date_of(e);
+        let col = (date - week_start_for(date, first)).num_days() as usize;
+        cells[row][col] += e.active_secs;
+    }
+
+    // Busiest categories first.
+    let mut rows: Vec<(&str, Vec<i64>)> = categories.into_iter().zip(cells).collect();
+    rows.sort_by_key(|(_, days)| std::cmp::Reverse(days.iter().sum::<i64>()));
+    let col_totals: Vec<i64> = (0..7).map(|d| rows.iter().map(|(_, days)| days[d]).sum()).collect();
+    let total: i64 = col_totals.iter().sum();
+
+    let weekdays: Vec<String> = std::iter::successors(Some(first), |d| Some(d.succ()))
+        .take(7)
+        .map(|d| d.to_string())
+        .collect();
+    if markdown {
+        println!("| Category | {} | Total |", weekdays.join(" | "));
+        println!("|---|{}---:|", "---:|".repeat(7));
+        let md_row = |label: &str, days: &[i64], sum: i64| {
+            let cols: Vec<String> = days.iter().map(|&s| compact_duration(s)).collect();
+            println!("| {label} | {} | {} |", cols.join(" | "), compact_duration(sum));
+        };
+        for (cat, days) in &rows {
+            md_row(&md_escape(cat), days, days.iter().sum());
+        }
+        md_row("**Total**", &col_totals, total);
+        return;
+    }
+
+    let palette = color::Palette::load(conn);
+    let label = |s: &str| format!("{:<16}", truncate(s, 15));
+    let line = |label: String, days: &[i64], sum: i64| {
+        let cols: String = days.iter().map(|&s| format!("{:>7}", compact_duration(s))).collect();
+        println!("{label}{cols}{:>9}", compact_duration(sum));
+    };
+    let header: String = weekdays.iter().map(|d| format!("{d:>7}")).collect();
+    println!("{:<16}{header}{:>9}", "Category", "Total");
+    println!("{}", "-".repeat(16 + 7 * 7 + 9));
+    for (cat, days) in &rows {
+        line(palette.paint(cat, &label(cat)), days, days.iter().sum());
+    }
+    println!("{}", "-".repeat(16 + 7 * 7 + 9));
+    line(label("TOTAL"), &col_totals, total);
+}
+
+/// One segment of a slash-delimited category path, with the time of every
+/// entry at or below it.
+#[derive(Default)]
+struct CategoryNode<'a> {
+    secs: i64,
+    children: BTreeMap<&'a str, CategoryNode<'a>>,
+}
+
+impl<'a> CategoryNode<'a> {
+    /// Add `secs` to each node along `work/clientA/frontend`, so parents sum
+    /// their children. A category with no segments stays as-is.
+    fn add(&mut self, category: &'a str, secs: i64) {
+        let mut segments: Vec<&str> = category.split('/').map(str::trim).filter(|s| !s.is_empty()).collect();
+        if segments.is_empty() {
+            segments.push(category);
+        }
+        self.secs += secs;
+        let mut node = self;
+        for seg in segments {
+            node = node.children.entry(seg).or_default();
+            node.secs += secs;
+        }
+    }
+
+    /// Print the children of the node at `path` indented by depth, busiest
+    /// first. In plain text each is colored as the category it stands for.
+    fn print(&self, path: &str, depth: usize, markdown: bool, palette: &color::Palette) {
+        let mut children: Vec<_> = self.children.iter().collect();
+        children.sort_by_key(|(_, c)| std::cmp::Reverse(c.secs));
+        for (name, child) in children {
+            let indent = "  ".repeat(depth);
+            let path = if path.is_empty() { name.to_string() } else { format!("{path}/{name}") };
+            if markdown {
+                println!("{indent}- **{name}** {}", format_duration(child.secs));
+            } else {
+                let label = format!("{:<32}", truncate(&format!("{indent}{name}"), 31));
+                println!("{} {}", palette.paint(&path, &label), format_duration(child.secs));
+            }
+            child.print(&path, depth + 1, markdown, palette);
+        }
+    }
+}
+
+/// Time per category rolled up along `/` in category names, so
+/// `work/clientA/frontend` and `work/clientA/backend` both count towards
+/// `work/clientA` and `work`. Flat categories sit at the top level.
+pub fn tree(conn: &Connection, period: &Period, markdown: bool) {
+    let entries = period.entries(conn);
+    if markdown {
+        println!("## {}\n", period.title());
+    } else {
+        println!("{}\n", period.title());
+    }
+    if entries.is_empty() {
+        println!("No entries in this period.");
+        return;
+    }
+
+    let mut by_cat: HashMap<&str, i64> = HashMap::new();
+    for e in &entries {
+        *by_cat.entry(&e.category).or_insert(0) += e.active_secs;
+    }
+    let mut root = CategoryNode::default();
+    for (cat, secs) in by_cat {
+        root.add(cat, secs);
+    }
+
+    let palette = color::Palette::load(conn);
+    if markdown {
+        root.print("", 0, true, &palette);
+        println!("\n**Total** {}", format_duration(root.secs));
+        return;
+    }
+    println!("{:<32} Time", "Category");
+    println!("{}", "-".repeat(46));
+    root.print("", 0, false, &palette);
+    println!("{}", "-".repeat(46));
+    println!("{:<32} {}", "TOTAL", format_duration(root.secs));
+}
+
+/// Short cell-sized duration: `2h05`, `45m`, or `-` for nothing.
+fn compact_duration(secs: i64) -> String {
+    let (h, m) = (secs / 3600, secs % 3600 / 60);
+    match (h, m) {
+        (0, 0) if secs == 0 => "-".into(),
+        (0, 0) => "<1m".into(),
+        (0, m) => format!("{m}m"),
+        (h, m) => format!("{h}h{m:02}"),
+    }
+}
+
+/// Prose duration for the digest: `28h`, `6h 10m`, `45m`.
+fn prose_duration(secs: i64) -> String {
+    let (h, m) = (secs / 3600, secs % 3600 / 60);
+    match (h, m) {
+        (0, 0) => "under a minute".into(),
+        (0, m) => format!("{m}m"),
+        (h, 0) => format!("{h}h"),
+        (h, m) => format!("{h}h {m}m"),
+    }
+}
+
+fn plural(n: usize, one: &str, many: &str) -> String {
+    format!("{n} {}", if n == 1 { one } else { many })
+}
+
+/// A few sentences summing up the period, for pasting into chat, e.g.
+/// "This week you tracked 28h across 5 categories over 4 days. Top:
+/// development (12h), meetings (6h 10m), email (3h). You completed 7 todos."
+pub fn digest(conn: &Connection, period: &Period, month: bool) {
+    let entries = period.entries(conn);
+    let today = today_date(conn);
+    let opening = if period.to != today {
+        format!("From {} to {}", period.from, period.to)
+    } else if month {
+        "This month".to_string()
+    } else if period.days() == 7 {
+        "This week".to_string()
+    } else {
+        format!("Since {}", period.from)
+    };
+
+    let mut text = if entries.is_empty() {
+        format!("{opening} you didn't track any time.")
+    } else {
+        let mut by_cat: HashMap<&str, i64> = HashMap::new();
+        let mut days: BTreeSet<NaiveDate> = BTreeSet::new();
+        for e in &entries {
+            *by_cat.entry(&e.category).or_insert(0) += e.active_secs;
+            days.insert(period.date_of(e));
+        }
+        let total: i64 = entries.iter().map(|e| e.active_secs).sum();
+        let mut cats: Vec<(&str, i64)> = by_cat.into_iter().collect();
+        cats.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let top: Vec<String> = cats
+            .iter()
+            .take(3)
+            .map(|(cat, secs)| format!("{cat} ({})", prose_duration(*secs)))
+            .collect();
+        format!(
+            "{opening} you tracked {} across {} over {}. Top: {}.",
+            prose_duration(total),
+            plural(cats.len(), "category", "categories"),
+            plural(days.len(), "day", "days"),
+            top.join(", "),
+        )
+    };
+
+    let (since_ts, until_ts) = period.bounds();
+    let completed = todos_completed_between(conn, since_ts, until_ts);
+    match completed.len() {
+        0 => text.push_str(" You didn't complete any todos."),
+        n => text.push_str(&format!(" You completed {}.", plural(n, "todo", "todos"))),
+    }
+    println!("{text}");
+    for t in &completed {
+        println!("  ✓ {}", t.text);
+    }
+}
+
+/// All-time totals, tracking streaks and averages.
+pub fn stats(conn: &Connection) {
+    let entries = query_entries(conn, None);
+    if entries.is_empty() {
+        println!("Nothing tracked yet.");
+        return;
+    }
+
+    let hour = day_start_hour(conn);
+    let mut days: BTreeSet<NaiveDate> = BTreeSet::new();
+    let mut by_cat: HashMap<&str, i64> = HashMap::new();
+    let mut by_weekday = [0i64; 7];
+    let mut total = 0;
+    for e in &entries {
+        let date = day_of(e.started_at, hour);
+        days.insert(date);
+        *by_cat.entry(&e.category).or_insert(0) += e.active_secs;
+        by_weekday[date.weekday().num_days_from_monday() as usize] += e.active_secs;
+        total += e.active_secs;
+    }
+
+    // Longest run of consecutive tracked days, and the run ending today (or
+    // yesterday, so an untracked morning doesn't break the streak).
+    let mut longest = 0;
+    let mut run = 0;
+    let mut prev: Option<NaiveDate> = None;
+    for &d in &days {
+        run = if prev.is_some() && prev == d.pred_opt() { run + 1 } else { 1 };
+        longest = longest.max(run);
+        prev = Some(d);
+    }
+    let today = today_date(conn);
+    let mut day = if days.contains(&today) { today } else { today - Duration::days(1) };
+    let mut current = 0;
+    while days.contains(&day) {
+        current += 1;
+        day -= Duration::days(1);
+    }
+
+    let (top_cat, top_cat_secs) = by_cat.iter().max_by_key(|(_, s)| **s).unwrap();
+    let busiest = (0..7).max_by_key(|&i| by_weekday[i]).unwrap();
+    let weekday_name = chrono::Weekday::try_from(busiest as u8).unwrap();
+
+    let plural = |n: i64| if n == 1 { "day" } else { "days" };
+    println!("{:<22}{}", "Total tracked", format_duration(total));
+    println!("{:<22}{} ({} entries)", "Days tracked", days.len(), entries.len());
+    println!("{:<22}{current} {}", "Current streak", plural(current));
+    println!("{:<22}{longest} {}", "Longest streak", plural(longest));
+    println!("{:<22}{}", "Average per day", format_duration(total / days.len() as i64));
+    println!("{:<22}{} ({})", "Top category", color::category(conn, top_cat), format_duration(*top_cat_secs));
+    println!("{:<22}{weekday_name} ({})", "Busiest weekday", format_duration(by_weekday[busiest]));
+}
+
+/// Make user text safe to put in HTML text or an attribute.
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Keep user text from breaking out of a Markdown table cell.
+fn md_escape(s: &str) -> String {
+    s.replace('|', "\\|")
+}
@@ -1,13 +1,35 @@
-use chrono::Local;
+use chrono::{DateTime, Local, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Weekday};
 use prost::Message;
+use rusqlite::trace::{TraceEvent, TraceEventCodes};
 use rusqlite::{Connection, params};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub mod proto {
     include!(concat!(env!("OUT_DIR"), "/time_logging.rs"));
 }
 
+/// How much a command prints, from the global `--quiet` / `--verbose` flags.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Verbosity {
+    /// Only essential identifiers (e.g. the new entry id), for scripts.
+    Quiet,
+    #[default]
+    Normal,
+    /// Also log the DB path, migrations and SQL timing to stderr.
+    Verbose,
+}
+
+impl Verbosity {
+    pub fn quiet(self) -> bool {
+        self == Verbosity::Quiet
+    }
+
+    pub fn verbose(self) -> bool {
+        self == Verbosity::Verbose
+    }
+}
+
 fn db_path() -> PathBuf {
     let dir = dirs::config_dir()
         .unwrap_or_else(|| PathBuf::from("."))
@@ -16,29 +38,271 @@ fn db_path() -> PathBuf {
     dir.join("data.db")
 }
 
-pub fn open_db() -> Connection {
-    let conn = Connection::open(db_path()).expect("failed to open database");
+pub fn open_db(verbosity: Verbosity) -> Result<Connection, String> {
+    open_db_at(&db_path(), verbosity)
+}
 
-    // Migrate from old single-row active_timer to multi-row active_timers
-    let old_exists: bool = conn
-        .query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='active_timer'",
-            [],
-            |row| row.get::<_, i32>(0),
-        )
-        .unwrap_or(0)
-        > 0;
+/// Open (creating and migrating as needed) the database at `path`. Pass
+/// `:memory:` for a throwaway database, e.g. in tests. Fails, with a message
+/// to show, when the database was written by a newer tl.
+pub fn open_db_at(path: &Path, verbosity: Verbosity) -> Result<Connection, String> {
+    if verbosity.verbose() {
+        eprintln!("[tl] database: {}", path.display());
+    }
+    let conn = Connection::open(path).expect("failed to open database");
+
+    // WAL lets a shell-prompt hook read while another `tl` writes; the busy
+    // timeout makes concurrent writers wait instead of failing with "locked".
+    conn.busy_timeout(std::time::Duration::from_secs(5))
+        .expect("failed to set busy timeout");
+    conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+        .expect("failed to enable WAL mode");
+
+    let applied = migrate(&conn)?;
+    if verbosity.verbose() {
+        if applied.is_empty() {
+            eprintln!("[tl] schema up to date");
+        }
+        for step in &applied {
+            eprintln!("[tl] migration: {step}");
+        }
+        conn.trace_v2(TraceEventCodes::SQLITE_TRACE_PROFILE, Some(log_sql_timing));
+    }
+    Ok(conn)
+}
 
-    let new_exists: bool = conn
-        .query_row(
-            "SELECT COUNT(*) FROM sqlite_master WHERE type='table' AND name='active_timers'",
-            [],
-            |row| row.get::<_, i32>(0),
-        )
-        .unwrap_or(0)
-        > 0;
+fn log_sql_timing(event: TraceEvent<'_>) {
+    if let TraceEvent::Profile(stmt, duration) = event {
+        let sql = stmt.sql();
+        eprintln!("[tl] {duration:.2?}  {}", sql.split_whitespace().collect::<Vec<_>>().join(" "));
+    }
+}
+
+/// One step in the schema's history. Steps are only ever appended, so a
+/// database's `schema_version` is the number of steps it has been through.
+/// Every step is safe to run on a schema that already has its changes:
+/// databases from before versioning start at 0 and replay them all.
+struct Migration {
+    description: &'static str,
+    apply: fn(&Connection) -> rusqlite::Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration { description: "create active_timers, time_entries and todos", apply: create_core_tables },
+    Migration {
+        description: "add todo_id columns",
+        apply: |conn| {
+            add_column(conn, "active_timers", "todo_id", "INTEGER")?;
+            add_column(conn, "time_entries", "todo_id", "INTEGER")?;
+            Ok(())
+        },
+    },
+    Migration {
+        description: "add last_modified columns",
+        apply: |conn| {
+            for table in ["active_timers", "time_entries", "todos"] {
+                add_column(conn, table, "last_modified", "INTEGER NOT NULL DEFAULT 0")?;
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        description: "add time_entries.tags",
+        apply: |conn| add_column(conn, "time_entries", "tags", "TEXT NOT NULL DEFAULT ''").map(drop),
+    },
+    Migration {
+        description: "add todos.estimate_secs",
+        apply: |conn| add_column(conn, "todos", "estimate_secs", "INTEGER").map(drop),
+    },
+    Migration {
+        description: "add todos.archived",
+        apply: |conn| add_column(conn, "todos", "archived", "INTEGER NOT NULL DEFAULT 0").map(drop),
+    },
+    Migration {
+        description: "add todos.completed_at",
+        apply: |conn| {
+            if add_column(conn, "todos", "completed_at", "INTEGER")? {
+                // Best guess for todos finished before this column existed.
+                conn.execute_batch("UPDATE todos SET completed_at = last_modified WHERE done = 1;")?;
+            }
+            Ok(())
+        },
+    },
+    Migration {
+        // Older entries keep NULL: their zone wasn't recorded.
+        description: "add time_entries.utc_offset",
+        apply: |conn| add_column(conn, "time_entries", "utc_offset", "INTEGER").map(drop),
+    },
+    Migration {
+        // Older rows keep NULL: the machine wasn't recorded.
+        description: "add host columns",
+        apply: |conn| {
+            add_column(conn, "time_entries", "host", "TEXT")?;
+            add_column(conn, "active_timers", "host", "TEXT")?;
+            Ok(())
+        },
+    },
+    Migration {
+        description: "create sync tables",
+        apply: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS deleted_records (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    table_name TEXT NOT NULL,
+                    record_id INTEGER NOT NULL,
+                    deleted_at INTEGER NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS sync_clients (
+                    client_id TEXT PRIMARY KEY,
+                    last_sync_ts INTEGER NOT NULL DEFAULT 0
+                );",
+            )
+        },
+    },
+    Migration {
+        description: "create table pomodoros",
+        apply: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS pomodoros (
+                    timer_id INTEGER PRIMARY KEY,
+                    work_secs INTEGER NOT NULL,
+                    break_secs INTEGER NOT NULL
+                );",
+            )
+        },
+    },
+    Migration {
+        description: "create todo link tables",
+        apply: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS timer_todos (
+                    timer_id INTEGER NOT NULL,
+                    todo_id INTEGER NOT NULL,
+                    PRIMARY KEY (timer_id, todo_id)
+                );
+                CREATE TABLE IF NOT EXISTS entry_todos (
+                    entry_id INTEGER NOT NULL,
+                    todo_id INTEGER NOT NULL,
+                    PRIMARY KEY (entry_id, todo_id)
+                );",
+            )
+        },
+    },
+    Migration {
+        description: "create notes tables",
+        apply: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS timer_notes (
+                    timer_id INTEGER NOT NULL,
+                    ts INTEGER NOT NULL,
+                    text TEXT NOT NULL
+                );
+                CREATE TABLE IF NOT EXISTS entry_notes (
+                    entry_id INTEGER NOT NULL,
+                    ts INTEGER NOT NULL,
+                    text TEXT NOT NULL
+                );",
+            )
+        },
+    },
+    Migration {
+        description: "create table settings",
+        apply: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS settings (
+                    key TEXT PRIMARY KEY,
+                    value TEXT NOT NULL
+                );",
+            )
+        },
+    },
+    Migration {
+        description: "create table budgets",
+        apply: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS budgets (
+                    category TEXT PRIMARY KEY COLLATE NOCASE,
+                    weekly_secs INTEGER NOT NULL
+                );",
+            )
+        },
+    },
+    Migration {
+        description: "create table category_colors",
+        apply: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS category_colors (
+                    category TEXT PRIMARY KEY COLLATE NOCASE,
+                    color TEXT NOT NULL
+                );",
+            )
+        },
+    },
+    Migration {
+        description: "create table templates",
+        apply: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS templates (
+                    key TEXT PRIMARY KEY COLLATE NOCASE,
+                    name TEXT NOT NULL,
+                    category TEXT NOT NULL,
+                    todo_ids TEXT NOT NULL DEFAULT ''
+                );",
+            )
+        },
+    },
+    Migration {
+        // Same columns as time_entries. Entries keep their ids when archived,
+        // and AUTOINCREMENT on time_entries means no new entry can reuse one.
+        description: "create table archived_entries",
+        apply: |conn| {
+            conn.execute_batch(
+                "CREATE TABLE IF NOT EXISTS archived_entries (
+                    id INTEGER PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    category TEXT NOT NULL,
+                    started_at INTEGER NOT NULL,
+                    ended_at INTEGER NOT NULL,
+                    active_secs INTEGER NOT NULL,
+                    breaks BLOB NOT NULL,
+                    todo_id INTEGER,
+                    last_modified INTEGER NOT NULL DEFAULT 0,
+                    tags TEXT NOT NULL DEFAULT '',
+                    utc_offset INTEGER,
+                    host TEXT
+                );",
+            )
+        },
+    },
+];
+
+fn table_exists(conn: &Connection, table: &str) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = ?1",
+        params![table],
+        |row| row.get::<_, i64>(0),
+    )
+    .map(|n| n > 0)
+}
+
+/// Add `column` to `table` unless it's already there; returns whether it was
+/// added.
+fn add_column(conn: &Connection, table: &str, column: &str, decl: &str) -> rusqlite::Result<bool> {
+    let exists: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE name = ?2",
+        params![table, column],
+        |row| row.get(0),
+    )?;
+    if exists > 0 {
+        return Ok(false);
+    }
+    conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {decl};"))?;
+    Ok(true)
+}
 
-    if old_exists && !new_exists {
+fn create_core_tables(conn: &Connection) -> rusqlite::Result<()> {
+    // The very first schema kept a single running timer in `active_timer`.
+    if table_exists(conn, "active_timer")? && !table_exists(conn, "active_timers")? {
         conn.execute_batch(
             "CREATE TABLE active_timers (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -51,10 +315,12 @@ pub fn open_db() -> Connection {
             INSERT INTO active_timers (name, category, started_at, state, breaks)
                 SELECT name, category, started_at, state, breaks FROM active_timer;
             DROP TABLE active_timer;",
-        )
-        .expect("failed to migrate active_timer to active_timers");
+        )?;
     }
 
+    // AUTOINCREMENT rather than a bare INTEGER PRIMARY KEY: users refer to
+    // rows by their `#id` and `todo_id` links point at them, so the id of a
+    // deleted row must never be handed out again.
     conn.execute_batch(
         "CREATE TABLE IF NOT EXISTS active_timers (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -82,47 +348,50 @@ pub fn open_db() -> Connection {
             created_at INTEGER NOT NULL
         );",
     )
-    .expect("failed to create tables");
+}
 
-    // Migrate: add todo_id column to active_timers and time_entries
-    let has_todo_id: bool = conn
-        .prepare("SELECT todo_id FROM active_timers LIMIT 0")
-        .is_ok();
-    if !has_todo_id {
-        conn.execute_batch(
-            "ALTER TABLE active_timers ADD COLUMN todo_id INTEGER;
-             ALTER TABLE time_entries ADD COLUMN todo_id INTEGER;",
-        )
-        .expect("failed to add todo_id columns");
-    }
+/// The number of `MIGRATIONS` this database has been through, from the
+/// `meta` table; 0 for a new database or one from before versioning.
+fn schema_version(conn: &Connection) -> usize {
+    conn.query_row("SELECT value FROM meta WHERE key = 'schema_version'", [], |row| row.get::<_, String>(0))
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0)
+}
 
-    let has_last_modified: bool = conn
-        .prepare("SELECT last_modified FROM active_timers LIMIT 0")
-        .is_ok();
-    if !has_last_modified {
-        conn.execute_batch(
-            "ALTER TABLE active_timers ADD COLUMN last_modified INTEGER NOT NULL DEFAULT 0;
-             ALTER TABLE time_entries ADD COLUMN last_modified INTEGER NOT NULL DEFAULT 0;
-             ALTER TABLE todos ADD COLUMN last_modified INTEGER NOT NULL DEFAULT 0;",
-        )
-        .expect("failed to add last_modified columns");
+/// Run the `MIGRATIONS` this database hasn't had yet, in order and in one
+/// transaction, so a failed step leaves the schema as it was. Returns the
+/// description of each step that ran, or an error for a database from a
+/// newer tl, which is left untouched.
+fn migrate(conn: &Connection) -> Result<Vec<String>, String> {
+    if schema_version(conn) == MIGRATIONS.len() {
+        return Ok(Vec::new());
     }
-
-    conn.execute_batch(
-        "CREATE TABLE IF NOT EXISTS deleted_records (
-            id INTEGER PRIMARY KEY AUTOINCREMENT,
-            table_name TEXT NOT NULL,
-            record_id INTEGER NOT NULL,
-            deleted_at INTEGER NOT NULL
-        );
-        CREATE TABLE IF NOT EXISTS sync_clients (
-            client_id TEXT PRIMARY KEY,
-            last_sync_ts INTEGER NOT NULL DEFAULT 0
-        );",
+    // Take the write lock up front: another `tl` may be migrating too.
+    let tx = rusqlite::Transaction::new_unchecked(conn, rusqlite::TransactionBehavior::Immediate)
+        .expect("failed to start migration");
+    tx.execute_batch("CREATE TABLE IF NOT EXISTS meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);")
+        .expect("failed to create meta table");
+
+    let from = schema_version(&tx);
+    if from > MIGRATIONS.len() {
+        return Err(format!(
+            "This database is at schema version {from}, newer than this tl understands ({}). Upgrade tl to use it.",
+            MIGRATIONS.len(),
+        ));
+    }
+    let mut applied = Vec::new();
+    for (i, step) in MIGRATIONS.iter().enumerate().skip(from) {
+        (step.apply)(&tx).unwrap_or_else(|e| panic!("migration {} ({}) failed: {e}", i + 1, step.description));
+        applied.push(step.description.to_string());
+    }
+    tx.execute(
+        "INSERT OR REPLACE INTO meta (key, value) VALUES ('schema_version', ?1)",
+        params![MIGRATIONS.len().to_string()],
     )
-    .expect("failed to create sync tables");
-
-    conn
+    .expect("failed to record schema version");
+    tx.commit().expect("failed to commit migrations");
+    Ok(applied)
 }
 
 // --- Break helpers ---
@@ -140,6 +409,65 @@ pub fn decode_breaks(data: &[u8]) -> Vec<proto::Break> {
         .unwrap_or_default()
 }
 
+/// Open a break at `now_ts` unless one is already open, returning whether a
+/// break was added. All pausing goes through here so a timer never carries
+/// two open breaks. Pausing right after a resume reopens the previous break
+/// instead (see `coalesce_breaks`).
+pub fn push_break_if_closed(breaks: &mut Vec<proto::Break>, now_ts: i64, label: &str) -> bool {
+    if breaks.iter().any(|b| b.end_ts == 0) {
+        return false;
+    }
+    breaks.push(proto::Break {
+        start_ts: now_ts,
+        end_ts: 0,
+        label: label.trim().to_string(),
+        interruption: false,
+    });
+    coalesce_breaks(breaks);
+    true
+}
+
+/// Breaks closer together than this are merged into one.
+pub const BREAK_COALESCE_SECS: i64 = 1;
+
+/// Past this many breaks a timer is flagged: something is pausing and
+/// resuming it in a loop.
+pub const MAX_BREAKS: usize = 1000;
+
+/// Merge each break into the one before it when it starts within
+/// `BREAK_COALESCE_SECS` of that one's end, so a resume followed at once by a
+/// pause doesn't add a break. The merged break keeps the first non-empty
+/// label. Interruptions are always kept apart. Expects breaks in start order.
+pub fn coalesce_breaks(breaks: &mut Vec<proto::Break>) {
+    let mut merged: Vec<proto::Break> = Vec::with_capacity(breaks.len());
+    for b in breaks.drain(..) {
+        match merged.last_mut() {
+            Some(prev)
+                if prev.end_ts != 0
+                    && !prev.interruption
+                    && !b.interruption
+                    && b.start_ts - prev.end_ts <= BREAK_COALESCE_SECS =>
+            {
+                prev.end_ts = if b.end_ts == 0 { 0 } else { b.end_ts.max(prev.end_ts) };
+                if prev.label.is_empty() {
+                    prev.label = b.label;
+                }
+            }
+            _ => merged.push(b),
+        }
+    }
+    *breaks = merged;
+}
+
+/// How many of `breaks` are interruptions (`tl interrupt`), and their total
+/// length.
+pub fn interruption_stats(breaks: &[proto::Break]) -> (usize, i64) {
+    breaks
+        .iter()
+        .filter(|b| b.interruption && b.end_ts != 0)
+        .fold((0, 0), |(n, secs), b| (n + 1, secs + b.end_ts - b.start_ts))
+}
+
 pub fn total_break_secs(breaks: &[proto::Break], now_ts: i64) -> i64 {
     breaks
         .iter()
@@ -150,9 +478,130 @@ pub fn total_break_secs(breaks: &[proto::Break], now_ts: i64) -> i64 {
         .sum()
 }
 
+/// Active time `timer` has run from `since_ts` (or its start, if later)
+/// until `now_ts`, so a timer started before midnight only counts its part
+/// of today.
+pub fn active_secs_since(timer: &ActiveTimer, since_ts: i64, now_ts: i64) -> i64 {
+    let from = timer.started_at.max(since_ts);
+    let break_secs: i64 = timer
+        .breaks
+        .iter()
+        .map(|b| {
+            let end = if b.end_ts == 0 { now_ts } else { b.end_ts };
+            (end.min(now_ts) - b.start_ts.max(from)).max(0)
+        })
+        .sum();
+    (now_ts - from - break_secs).max(0)
+}
+
+/// The moment a timer started at `started_at` reached `active_secs` of
+/// active time, skipping over its breaks (open breaks are treated as endless).
+pub fn time_at_active(started_at: i64, breaks: &[proto::Break], active_secs: i64) -> i64 {
+    let mut sorted: Vec<&proto::Break> = breaks.iter().collect();
+    sorted.sort_by_key(|b| b.start_ts);
+
+    let mut t = started_at;
+    let mut remaining = active_secs;
+    for b in sorted {
+        if b.start_ts - t >= remaining {
+            break;
+        }
+        remaining -= (b.start_ts - t).max(0);
+        if b.end_ts == 0 {
+            return i64::MAX;
+        }
+        t = t.max(b.end_ts);
+    }
+    t + remaining
+}
+
+/// Partition breaks around `at`: breaks ending before it go left, breaks
+/// starting after it go right, and a break spanning `at` is cut in two.
+pub fn split_breaks(breaks: &[proto::Break], at: i64) -> (Vec<proto::Break>, Vec<proto::Break>) {
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+    for b in breaks {
+        if b.end_ts != 0 && b.end_ts <= at {
+            before.push(b.clone());
+        } else if b.start_ts >= at {
+            after.push(b.clone());
+        } else {
+            before.push(proto::Break { end_ts: at, ..b.clone() });
+            after.push(proto::Break { start_ts: at, ..b.clone() });
+        }
+    }
+    (before, after)
+}
+
+/// Check that every break is closed, lies within `start..=end`, and doesn't
+/// overlap the next one. `breaks` must be sorted by start.
+pub fn validate_breaks(breaks: &[proto::Break], start: i64, end: i64) -> Result<(), String> {
+    for (i, b) in breaks.iter().enumerate() {
+        let n = i + 1;
+        if b.end_ts <= b.start_ts {
+            return Err(format!("break {n} must end after it starts"));
+        }
+        if b.start_ts < start || b.end_ts > end {
+            return Err(format!("break {n} falls outside the entry"));
+        }
+        if let Some(next) = breaks.get(i + 1)
+            && next.start_ts < b.end_ts
+        {
+            return Err(format!("breaks {n} and {} overlap", n + 1));
+        }
+    }
+    Ok(())
+}
+
 // --- Formatting ---
 
+/// How `format_duration` writes durations: `1h 23m 45s` (the default),
+/// `1:23:45`, or decimal hours like `1.40` for timesheets.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DurationFormat {
+    #[default]
+    Hms,
+    Clock,
+    Decimal,
+}
+
+impl DurationFormat {
+    pub fn parse(s: &str) -> Option<DurationFormat> {
+        match s {
+            "hms" => Some(DurationFormat::Hms),
+            "clock" => Some(DurationFormat::Clock),
+            "decimal" => Some(DurationFormat::Decimal),
+            _ => None,
+        }
+    }
+}
+
+/// The `duration-format` setting.
+pub fn duration_format(conn: &Connection) -> DurationFormat {
+    get_setting(conn, "duration_format")
+        .and_then(|v| DurationFormat::parse(&v))
+        .unwrap_or_default()
+}
+
+/// A duration in the default format, e.g. `1h 23m 45s`; `format_duration_as`
+/// picks another.
 pub fn format_duration(secs: i64) -> String {
+    format_duration_as(secs, DurationFormat::Hms)
+}
+
+pub fn format_duration_as(secs: i64, format: DurationFormat) -> String {
+    match format {
+        DurationFormat::Hms => format_hms(secs),
+        DurationFormat::Clock => {
+            let sign = if secs < 0 { "-" } else { "" };
+            let secs = secs.abs();
+            format!("{sign}{}:{:02}:{:02}", secs / 3600, (secs % 3600) / 60, secs % 60)
+        }
+        DurationFormat::Decimal => format!("{:.2}", secs as f64 / 3600.0),
+    }
+}
+
+fn format_hms(secs: i64) -> String {
     let h = secs / 3600;
     let m = (secs % 3600) / 60;
     let s = secs % 60;
@@ -165,6 +614,176 @@ pub fn format_duration(secs: i64) -> String {
     }
 }
 
+/// Ids as `#3, #4, #9`, cut short with `…` after the first ten.
+pub fn format_id_list(ids: &[u32]) -> String {
+    let mut list = ids.iter().take(10).map(|id| format!("#{id}")).collect::<Vec<_>>().join(", ");
+    if ids.len() > 10 {
+        list.push_str(", …");
+    }
+    list
+}
+
+/// How long before `now` `ts` was, for a glance: `45s ago`, `12m ago`,
+/// `2h ago`, `1 day ago`, `3 days ago`. Anything not in the past is
+/// `just now`.
+pub fn humanize_ago(ts: i64, now: i64) -> String {
+    let secs = now - ts;
+    match secs {
+        ..=0 => "just now".to_string(),
+        1..60 => format!("{secs}s ago"),
+        60..3600 => format!("{}m ago", secs / 60),
+        3600..86_400 => format!("{}h ago", secs / 3600),
+        86_400..172_800 => "1 day ago".to_string(),
+        _ => format!("{} days ago", secs / 86_400),
+    }
+}
+
+/// How finely `format_duration_prec` renders a duration.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum Precision {
+    #[default]
+    Seconds,
+    Minutes,
+    Hours,
+}
+
+/// `format_duration_as` cut down to `precision`, e.g. `2h 05m` for minutes;
+/// `format` only applies to full precision. Partial units are dropped rather
+/// than rounded up, so the display never runs ahead of the clock.
+pub fn format_duration_prec(secs: i64, precision: Precision, format: DurationFormat) -> String {
+    let h = secs / 3600;
+    let m = (secs % 3600) / 60;
+    match precision {
+        Precision::Seconds => format_duration_as(secs, format),
+        Precision::Minutes if h > 0 => format!("{h}h {m:02}m"),
+        Precision::Minutes => format!("{m}m"),
+        Precision::Hours => format!("{h}h"),
+    }
+}
+
+/// Parse a duration like `6h`, `4h30m`, `90m`, `1h 15m 30s` or a bare number
+/// of minutes into seconds.
+pub fn parse_duration(s: &str) -> Option<i64> {
+    let s = s.trim();
+    if s.is_empty() {
+        return None;
+    }
+    if let Ok(mins) = s.parse::<i64>() {
+        return mins.checked_mul(60).filter(|&secs| secs >= 0);
+    }
+
+    let mut total: i64 = 0;
+    let mut num = String::new();
+    for c in s.chars() {
+        match c {
+            '0'..='9' => num.push(c),
+            'h' | 'm' | 's' => {
+                let n: i64 = num.parse().ok()?;
+                num.clear();
+                let secs = match c {
+                    'h' => n.checked_mul(3600)?,
+                    'm' => n.checked_mul(60)?,
+                    _ => n,
+                };
+                total = total.checked_add(secs)?;
+            }
+            ' ' => {}
+            _ => return None,
+        }
+    }
+    if !num.is_empty() {
+        return None;
+    }
+    Some(total)
+}
+
+/// Parse a wall-clock time like `14:30`, `14:30:15` (on `date`) or a full
+/// `2024-05-01 14:30` into a local timestamp.
+pub fn parse_clock_time(s: &str, date: NaiveDate) -> Option<i64> {
+    let s = s.trim();
+    let naive = NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S"))
+        .ok()
+        .or_else(|| {
+            let time = NaiveTime::parse_from_str(s, "%H:%M")
+                .or_else(|_| NaiveTime::parse_from_str(s, "%H:%M:%S"))
+                .ok()?;
+            Some(date.and_time(time))
+        })?;
+    Some(local_ts(naive))
+}
+
+/// Local wall-clock time for a Unix timestamp. Going through UTC means this
+/// can't fail, unlike `Local.timestamp_opt(..).single()`.
+pub fn local_time(ts: i64) -> DateTime<Local> {
+    DateTime::from_timestamp(ts, 0).unwrap_or_default().with_timezone(&Local)
+}
+
+/// The local zone's UTC offset in seconds at `ts`.
+pub fn local_offset(ts: i64) -> i32 {
+    local_time(ts).offset().local_minus_utc()
+}
+
+/// Date and time of `ts` as it read on the clock where it was recorded
+/// (`offset` seconds east of UTC), followed by that offset when it differs
+/// from the local zone's. Unknown offsets use the local zone.
+pub fn format_datetime_at(conn: &Connection, ts: i64, offset: Option<i32>) -> String {
+    let Some(offset) = offset.filter(|&o| o != local_offset(ts)) else {
+        return format_datetime(conn, ts);
+    };
+    let Some(zone) = chrono::FixedOffset::east_opt(offset) else {
+        return format_datetime(conn, ts);
+    };
+    let at = DateTime::from_timestamp(ts, 0).unwrap_or_default().with_timezone(&zone);
+    let time = if uses_12h(conn) { "%-I:%M:%S %p" } else { "%H:%M:%S" };
+    format!("{} (UTC{})", at.format(&format!("%Y-%m-%d {time}")), zone)
+}
+
+/// Unix timestamp of a local wall-clock time. In a repeated DST hour the
+/// earlier occurrence wins; a time skipped by a DST jump moves forward to the
+/// first moment that exists.
+pub fn local_ts(naive: NaiveDateTime) -> i64 {
+    // Not `.earliest()`: chrono orders ambiguous results by UTC offset, which
+    // puts the later (standard-time) instant first.
+    let resolve = |n: NaiveDateTime| match n.and_local_timezone(Local) {
+        LocalResult::Single(dt) => Some(dt.timestamp()),
+        LocalResult::Ambiguous(a, b) => Some(a.timestamp().min(b.timestamp())),
+        LocalResult::None => None,
+    };
+    (0..=24 * 4)
+        .find_map(|q| resolve(naive + TimeDelta::minutes(15 * q)))
+        .unwrap_or_else(|| naive.and_utc().timestamp())
+}
+
+/// Unix timestamp of the start of `date` in local time.
+pub fn local_midnight(date: NaiveDate) -> i64 {
+    local_ts(date.and_time(NaiveTime::MIN))
+}
+
+/// Unix timestamp at which the tracking day `date` starts: `hour` o'clock
+/// local time (see [`day_start_hour`]), which is midnight by default.
+pub fn day_start_ts(date: NaiveDate, hour: u32) -> i64 {
+    let start = NaiveTime::from_hms_opt(hour, 0, 0).unwrap_or(NaiveTime::MIN);
+    local_ts(date.and_time(start))
+}
+
+/// The tracking day `ts` belongs to. With days starting at 04:00, a session
+/// at 02:00 still counts towards the previous date.
+pub fn day_of(ts: i64, hour: u32) -> NaiveDate {
+    (local_time(ts).naive_local() - TimeDelta::hours(hour.into())).date()
+}
+
+/// A `[████░░░░]` bar showing `done` out of `goal`, `width` cells wide.
+pub fn progress_bar(done: i64, goal: i64, width: usize) -> String {
+    let filled = if goal > 0 {
+        ((done.max(0) as f64 / goal as f64) * width as f64).round() as usize
+    } else {
+        width
+    };
+    let filled = filled.min(width);
+    format!("[{}{}]", "█".repeat(filled), "░".repeat(width - filled))
+}
+
 // --- Active timer DB ops ---
 
 pub struct ActiveTimer {
@@ -178,6 +797,114 @@ pub struct ActiveTimer {
     pub last_modified: i64,
 }
 
+// Timer transitions take the current time as `now_ts` rather than reading the
+// clock, so the break math can be exercised with fixed timestamps.
+impl ActiveTimer {
+    /// Active (non-break) seconds as of `now_ts`.
+    pub fn active_secs(&self, now_ts: i64) -> i64 {
+        (now_ts - self.started_at - total_break_secs(&self.breaks, now_ts)).max(0)
+    }
+
+    /// Pause at `now_ts`, opening a break (label may be empty). At most one
+    /// break is ever open, so pausing an already-paused timer changes nothing.
+    pub fn pause(&mut self, now_ts: i64, label: &str) {
+        self.state = "paused".into();
+        push_break_if_closed(&mut self.breaks, now_ts, label);
+    }
+
+    /// Run again from `now_ts`, closing the open break if there is one.
+    /// Should older data hold several open breaks, everything after the
+    /// first lies inside it and is dropped rather than counted twice.
+    pub fn resume(&mut self, now_ts: i64) {
+        self.state = "running".into();
+        if let Some(open) = self.breaks.iter().position(|b| b.end_ts == 0) {
+            self.breaks.truncate(open + 1);
+            self.breaks[open].end_ts = now_ts;
+        }
+    }
+
+    /// Record an interruption of up to `secs` ending at `now_ts` on a running
+    /// timer, as a closed break. It's cut short so it doesn't reach back past
+    /// the start or the last break; returns the length recorded, 0 if none.
+    pub fn interrupt(&mut self, now_ts: i64, secs: i64, reason: &str) -> i64 {
+        let last_end = self.breaks.iter().map(|b| b.end_ts.max(b.start_ts)).max().unwrap_or(0);
+        let start_ts = (now_ts - secs).max(self.started_at).max(last_end);
+        if start_ts >= now_ts {
+            return 0;
+        }
+        self.breaks.push(proto::Break {
+            start_ts,
+            end_ts: now_ts,
+            label: reason.trim().to_string(),
+            interruption: true,
+        });
+        now_ts - start_ts
+    }
+
+    /// When the current pause began: the latest start among open breaks, or
+    /// `None` if no break is open.
+    pub fn paused_at(&self) -> Option<i64> {
+        self.breaks.iter().filter(|b| b.end_ts == 0).map(|b| b.start_ts).max()
+    }
+
+    /// Where a suspected sleep began: `last_seen` (the last `tl` heartbeat),
+    /// if the timer has run without a break since then for at least
+    /// `min_gap` up to `now_ts`. A `min_gap` of 0 disables the check.
+    pub fn sleep_start(&self, last_seen: i64, now_ts: i64, min_gap: i64) -> Option<i64> {
+        if self.state != "running" || min_gap <= 0 {
+            return None;
+        }
+        let last_activity = self.breaks.iter().map(|b| b.end_ts).fold(self.started_at, i64::max);
+        let from = last_seen.max(last_activity);
+        (now_ts - from >= min_gap).then_some(from)
+    }
+
+    /// The log entry for stopping this timer at `ended_at`.
+    ///
+    /// If the clock went backwards while the timer ran, `ended_at` can fall
+    /// before the start or inside a recorded break. Rather than log an
+    /// inverted entry, it ends at the latest recorded moment instead, breaks
+    /// are clipped to fit, and the entry is tagged [`CLOCK_SKEW_TAG`].
+    pub fn to_entry(&self, ended_at: i64, mut tags: Vec<String>) -> TimeEntry {
+        let latest = self
+            .breaks
+            .iter()
+            .flat_map(|b| [b.start_ts, b.end_ts])
+            .fold(self.started_at, i64::max);
+        let mut skewed = ended_at < latest;
+        let ended_at = ended_at.max(latest);
+
+        let mut breaks = self.breaks.clone();
+        for b in &mut breaks {
+            let start = b.start_ts.max(self.started_at);
+            let end = if b.end_ts == 0 { 0 } else { b.end_ts.max(start) };
+            skewed |= start != b.start_ts || end != b.end_ts;
+            (b.start_ts, b.end_ts) = (start, end);
+        }
+        if skewed && !tags.iter().any(|t| t == CLOCK_SKEW_TAG) {
+            tags.push(CLOCK_SKEW_TAG.to_string());
+        }
+
+        TimeEntry {
+            id: 0,
+            name: self.name.clone(),
+            category: self.category.clone(),
+            started_at: self.started_at,
+            ended_at,
+            active_secs: (ended_at - self.started_at - total_break_secs(&breaks, ended_at)).max(0),
+            breaks,
+            todo_id: self.todo_id,
+            last_modified: 0,
+            tags,
+            utc_offset: Some(local_offset(self.started_at)),
+            host: None,
+        }
+    }
+}
+
+/// Tag put on entries whose timer saw the system clock jump backwards.
+pub const CLOCK_SKEW_TAG: &str = "clock-skew";
+
 fn row_to_timer(row: &rusqlite::Row) -> rusqlite::Result<ActiveTimer> {
     let breaks_blob: Vec<u8> = row.get(5)?;
     Ok(ActiveTimer {
@@ -196,13 +923,23 @@ fn now_ts() -> i64 {
     Local::now().timestamp()
 }
 
+/// The running timer. Writes keep at most one running, but should an older
+/// database hold several, this is the most recently changed (see
+/// `running_timers` to find out).
 pub fn get_running(conn: &Connection) -> Option<ActiveTimer> {
-    conn.query_row(
-        "SELECT id, name, category, started_at, state, breaks, todo_id, last_modified FROM active_timers WHERE state = 'running'",
-        [],
-        row_to_timer,
-    )
-    .ok()
+    running_timers(conn).into_iter().next()
+}
+
+/// Every timer marked running, most recently changed first. More than one
+/// means the database needs `tl doctor`.
+pub fn running_timers(conn: &Connection) -> Vec<ActiveTimer> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, name, category, started_at, state, breaks, todo_id, last_modified FROM active_timers
+             WHERE state = 'running' ORDER BY last_modified DESC, id DESC",
+        )
+        .unwrap();
+    stmt.query_map([], row_to_timer).unwrap().filter_map(|r| r.ok()).collect()
 }
 
 pub fn get_all_active(conn: &Connection) -> Vec<ActiveTimer> {
@@ -222,11 +959,47 @@ pub fn get_active_by_id(conn: &Connection, id: u32) -> Option<ActiveTimer> {
     .ok()
 }
 
+/// Pause every running timer except `keep`.
+fn pause_other_running(conn: &Connection, keep: u32) {
+    let now = now_ts();
+    for mut t in get_all_active(conn) {
+        if t.state == "running" && t.id != Some(keep) {
+            t.pause(now, "");
+            update_active(conn, &t);
+        }
+    }
+}
+
+/// Run a timer write and, if it leaves the timer running, pause any other
+/// running one in the same savepoint (which nests inside a caller's
+/// transaction), so at most one timer is ever running.
+fn keeping_one_running(conn: &Connection, running: bool, write: impl FnOnce() -> u32) -> u32 {
+    if !running {
+        return write();
+    }
+    conn.execute_batch("SAVEPOINT one_running").expect("failed to begin savepoint");
+    let id = write();
+    pause_other_running(conn, id);
+    conn.execute_batch("RELEASE one_running").expect("failed to release savepoint");
+    id
+}
+
+/// Insert a timer started on this machine.
 pub fn insert_active(conn: &Connection, timer: &ActiveTimer) -> u32 {
+    insert_active_from(conn, timer, Some(&hostname()))
+}
+
+/// Insert a timer that came from elsewhere (a synced device, an import):
+/// `host` is the machine it ran on, `None` if unknown.
+pub fn insert_active_from(conn: &Connection, timer: &ActiveTimer, host: Option<&str>) -> u32 {
+    keeping_one_running(conn, timer.state == "running", || insert_active_row(conn, timer, host))
+}
+
+fn insert_active_row(conn: &Connection, timer: &ActiveTimer, host: Option<&str>) -> u32 {
     let modified = now_ts();
     conn.execute(
-        "INSERT INTO active_timers (name, category, started_at, state, breaks, todo_id, last_modified)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        "INSERT INTO active_timers (name, category, started_at, state, breaks, todo_id, last_modified, host)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
         params![
             timer.name,
             timer.category,
@@ -235,14 +1008,35 @@ pub fn insert_active(conn: &Connection, timer: &ActiveTimer) -> u32 {
             encode_breaks(&timer.breaks),
             timer.todo_id,
             modified,
+            host,
         ],
     )
     .expect("failed to insert active timer");
     conn.last_insert_rowid() as u32
 }
 
+/// This machine's hostname, recorded on the timers started here.
+pub fn hostname() -> String {
+    gethostname::gethostname().to_string_lossy().into_owned()
+}
+
+/// The machine an active timer was started on; `None` for timers from
+/// before hosts were recorded.
+pub fn timer_host(conn: &Connection, id: u32) -> Option<String> {
+    conn.query_row("SELECT host FROM active_timers WHERE id = ?1", params![id], |row| row.get(0))
+        .ok()
+        .flatten()
+}
+
 pub fn update_active(conn: &Connection, timer: &ActiveTimer) {
     let id = timer.id.expect("cannot update timer without id");
+    keeping_one_running(conn, timer.state == "running", || {
+        update_active_row(conn, id, timer);
+        id
+    });
+}
+
+fn update_active_row(conn: &Connection, id: u32, timer: &ActiveTimer) {
     let modified = now_ts();
     conn.execute(
         "UPDATE active_timers SET name = ?1, category = ?2, started_at = ?3, state = ?4, breaks = ?5, todo_id = ?6, last_modified = ?7 WHERE id = ?8",
@@ -260,6 +1054,42 @@ pub fn update_active(conn: &Connection, timer: &ActiveTimer) {
     .expect("failed to update active timer");
 }
 
+/// Write several timers in one transaction, e.g. pausing one and resuming
+/// another on `tl switch`, so they never end up both running or both paused.
+pub fn update_actives(conn: &Connection, timers: &[&ActiveTimer]) {
+    let tx = conn.unchecked_transaction().expect("failed to begin transaction");
+    for timer in timers {
+        update_active(&tx, timer);
+    }
+    tx.commit().expect("failed to commit timer updates");
+}
+
+/// Delete every entry (archived ones too), todo and active timer along with
+/// their links, notes and pomodoros, recording the deletions for sync.
+/// Settings, budgets and templates stay.
+pub fn wipe_tracking_data(conn: &Connection) {
+    let now = now_ts();
+    for table in ["time_entries", "todos", "active_timers"] {
+        conn.execute(
+            &format!("INSERT INTO deleted_records (table_name, record_id, deleted_at) SELECT '{table}', id, ?1 FROM {table}"),
+            params![now],
+        )
+        .expect("failed to record deletions");
+    }
+    conn.execute_batch(
+        "DELETE FROM time_entries;
+         DELETE FROM archived_entries;
+         DELETE FROM todos;
+         DELETE FROM active_timers;
+         DELETE FROM pomodoros;
+         DELETE FROM timer_todos;
+         DELETE FROM entry_todos;
+         DELETE FROM timer_notes;
+         DELETE FROM entry_notes;",
+    )
+    .expect("failed to clear tracking data");
+}
+
 pub fn clear_active(conn: &Connection, id: u32) {
     conn.execute(
         "INSERT INTO deleted_records (table_name, record_id, deleted_at) VALUES ('active_timers', ?1, ?2)",
@@ -267,44 +1097,188 @@ pub fn clear_active(conn: &Connection, id: u32) {
     ).expect("failed to record deletion");
     conn.execute("DELETE FROM active_timers WHERE id = ?1", params![id])
         .expect("failed to clear active timer");
+    conn.execute("DELETE FROM pomodoros WHERE timer_id = ?1", params![id])
+        .expect("failed to clear pomodoro");
+    conn.execute("DELETE FROM timer_todos WHERE timer_id = ?1", params![id])
+        .expect("failed to clear timer todo links");
+    conn.execute("DELETE FROM timer_notes WHERE timer_id = ?1", params![id])
+        .expect("failed to clear timer notes");
 }
 
-// --- Time entry DB ops ---
+/// Turn an active timer into a logged entry: the entry insert and the timer
+/// removal commit together, so an interruption can't lose the entry or leave
+/// a zombie timer behind. Returns the new entry ID.
+pub fn finish_timer(conn: &Connection, timer_id: u32, entry: &TimeEntry) -> u32 {
+    let tx = conn.unchecked_transaction().expect("failed to begin transaction");
+    let entry_id = insert_entry(&tx, entry);
+    tx.execute(
+        "UPDATE time_entries SET host = COALESCE(host, (SELECT host FROM active_timers WHERE id = ?2)) WHERE id = ?1",
+        params![entry_id, timer_id],
+    )
+    .expect("failed to copy timer host");
+    let todos = get_links(&tx, "timer_todos", "timer_id", timer_id);
+    set_links(&tx, "entry_todos", "entry_id", entry_id, &todos);
+    for note in timer_notes(&tx, timer_id) {
+        add_entry_note(&tx, entry_id, &note);
+    }
+    clear_active(&tx, timer_id);
+    tx.commit().expect("failed to commit stop");
+    entry_id
+}
 
-pub struct TimeEntry {
-    pub id: u32,
-    pub name: String,
-    pub category: String,
-    pub started_at: i64,
-    pub ended_at: i64,
-    pub active_secs: i64,
-    pub breaks: Vec<proto::Break>,
-    pub todo_id: Option<u32>,
-    pub last_modified: i64,
+// --- Notes ---
+
+/// A timestamped line of text jotted down while a timer ran (`tl note`).
+/// It lives with the timer and moves to the log entry when it stops.
+pub struct Note {
+    pub ts: i64,
+    pub text: String,
 }
 
-pub fn insert_entry(conn: &Connection, entry: &TimeEntry) {
-    let modified = now_ts();
+fn get_notes(conn: &Connection, table: &str, owner_col: &str, owner: u32) -> Vec<Note> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT ts, text FROM {table} WHERE {owner_col} = ?1 ORDER BY ts, rowid"))
+        .unwrap();
+    stmt.query_map(params![owner], |row| Ok(Note { ts: row.get(0)?, text: row.get(1)? }))
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+pub fn add_timer_note(conn: &Connection, timer_id: u32, note: &Note) {
     conn.execute(
-        "INSERT INTO time_entries (name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
-        params![
-            entry.name,
-            entry.category,
-            entry.started_at,
-            entry.ended_at,
-            entry.active_secs,
-            encode_breaks(&entry.breaks),
-            entry.todo_id,
-            modified,
-        ],
+        "INSERT INTO timer_notes (timer_id, ts, text) VALUES (?1, ?2, ?3)",
+        params![timer_id, note.ts, note.text],
     )
-    .expect("failed to insert time entry");
+    .expect("failed to add note");
 }
 
-pub fn get_entry_by_id(conn: &Connection, id: u32) -> Option<TimeEntry> {
-    conn.query_row(
-        "SELECT id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified FROM time_entries WHERE id = ?1",
+pub fn add_entry_note(conn: &Connection, entry_id: u32, note: &Note) {
+    conn.execute(
+        "INSERT INTO entry_notes (entry_id, ts, text) VALUES (?1, ?2, ?3)",
+        params![entry_id, note.ts, note.text],
+    )
+    .expect("failed to add note");
+}
+
+/// An active timer's notes, oldest first.
+pub fn timer_notes(conn: &Connection, timer_id: u32) -> Vec<Note> {
+    get_notes(conn, "timer_notes", "timer_id", timer_id)
+}
+
+/// A log entry's notes, oldest first.
+pub fn entry_notes(conn: &Connection, entry_id: u32) -> Vec<Note> {
+    get_notes(conn, "entry_notes", "entry_id", entry_id)
+}
+
+// --- Pomodoro DB ops ---
+
+/// Work/break interval lengths attached to an active timer started in
+/// Pomodoro mode. The timer itself is a plain `ActiveTimer`; this only drives
+/// the reminders in `tl pomodoro` and the count in the stop summary.
+pub struct Pomodoro {
+    pub work_secs: i64,
+    pub break_secs: i64,
+}
+
+/// Attach Pomodoro intervals to a timer. A work interval under a second
+/// can't be counted in, so none is attached then.
+pub fn set_pomodoro(conn: &Connection, timer_id: u32, work_secs: i64, break_secs: i64) {
+    if work_secs <= 0 {
+        return;
+    }
+    conn.execute(
+        "INSERT INTO pomodoros (timer_id, work_secs, break_secs) VALUES (?1, ?2, ?3)
+         ON CONFLICT(timer_id) DO UPDATE SET work_secs = ?2, break_secs = ?3",
+        params![timer_id, work_secs, break_secs],
+    )
+    .expect("failed to save pomodoro");
+}
+
+pub fn get_pomodoro(conn: &Connection, timer_id: u32) -> Option<Pomodoro> {
+    conn.query_row(
+        "SELECT work_secs, break_secs FROM pomodoros WHERE timer_id = ?1",
+        params![timer_id],
+        |row| {
+            Ok(Pomodoro {
+                work_secs: row.get(0)?,
+                break_secs: row.get(1)?,
+            })
+        },
+    )
+    .ok()
+}
+
+/// Number of full work intervals contained in `active_secs`.
+pub fn completed_pomodoros(active_secs: i64, work_secs: i64) -> i64 {
+    if work_secs <= 0 {
+        return 0;
+    }
+    active_secs / work_secs
+}
+
+// --- Time entry DB ops ---
+
+pub struct TimeEntry {
+    pub id: u32,
+    pub name: String,
+    pub category: String,
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub active_secs: i64,
+    pub breaks: Vec<proto::Break>,
+    pub todo_id: Option<u32>,
+    pub last_modified: i64,
+    pub tags: Vec<String>,
+    /// Seconds east of UTC where the entry was recorded; `None` if unknown.
+    pub utc_offset: Option<i32>,
+    /// Hostname of the machine the timer ran on; `None` if unknown.
+    pub host: Option<String>,
+}
+
+/// Clean up `--tag` values: splits on commas, trims, drops a leading `#`,
+/// and removes empties and duplicates.
+pub fn normalize_tags(raw: &[String]) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for t in raw.iter().flat_map(|r| r.split(',')) {
+        let t = t.trim().trim_start_matches('#').trim().to_lowercase();
+        if !t.is_empty() && !tags.contains(&t) {
+            tags.push(t);
+        }
+    }
+    tags
+}
+
+fn split_tags(s: &str) -> Vec<String> {
+    s.split(',').filter(|t| !t.is_empty()).map(String::from).collect()
+}
+
+pub fn insert_entry(conn: &Connection, entry: &TimeEntry) -> u32 {
+    let modified = now_ts();
+    conn.execute(
+        "INSERT INTO time_entries (name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified, tags, utc_offset, host)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+        params![
+            entry.name,
+            entry.category,
+            entry.started_at,
+            entry.ended_at,
+            entry.active_secs,
+            encode_breaks(&entry.breaks),
+            entry.todo_id,
+            modified,
+            entry.tags.join(","),
+            entry.utc_offset,
+            entry.host,
+        ],
+    )
+    .expect("failed to insert time entry");
+    conn.last_insert_rowid() as u32
+}
+
+pub fn get_entry_by_id(conn: &Connection, id: u32) -> Option<TimeEntry> {
+    conn.query_row(
+        "SELECT id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified, tags, utc_offset, host FROM time_entries WHERE id = ?1",
         params![id],
         row_to_entry,
     )
@@ -323,24 +1297,51 @@ fn row_to_entry(row: &rusqlite::Row) -> rusqlite::Result<TimeEntry> {
         breaks: decode_breaks(&breaks_blob),
         todo_id: row.get(7)?,
         last_modified: row.get(8)?,
+        tags: split_tags(&row.get::<_, String>(9)?),
+        utc_offset: row.get(10)?,
+        host: row.get(11)?,
     })
 }
 
 pub fn update_entry(conn: &Connection, entry: &TimeEntry) {
     let modified = now_ts();
     conn.execute(
-        "UPDATE time_entries SET name = ?1, category = ?2, active_secs = ?3, last_modified = ?4 WHERE id = ?5",
+        "UPDATE time_entries SET name = ?1, category = ?2, started_at = ?3, ended_at = ?4, active_secs = ?5,
+         breaks = ?6, todo_id = ?7, last_modified = ?8, tags = ?9 WHERE id = ?10",
         params![
             entry.name,
             entry.category,
+            entry.started_at,
+            entry.ended_at,
             entry.active_secs,
+            encode_breaks(&entry.breaks),
+            entry.todo_id,
             modified,
+            entry.tags.join(","),
             entry.id,
         ],
     )
     .expect("failed to update time entry");
 }
 
+/// Re-link an existing entry to `todos`: unlike `set_entry_todos`, which only
+/// writes the link rows, this also makes the first its `todo_id` (an empty
+/// slice unlinks it). Returns `false` if the entry doesn't exist.
+pub fn relink_entry(conn: &Connection, entry_id: u32, todos: &[u32]) -> bool {
+    let tx = conn.unchecked_transaction().expect("failed to begin transaction");
+    let changed = tx
+        .execute(
+            "UPDATE time_entries SET todo_id = ?1, last_modified = ?2 WHERE id = ?3",
+            params![todos.first(), now_ts(), entry_id],
+        )
+        .expect("failed to update entry todo link");
+    if changed > 0 {
+        set_entry_todos(&tx, entry_id, todos);
+    }
+    tx.commit().expect("failed to commit entry todo link");
+    changed > 0
+}
+
 pub fn delete_entry(conn: &Connection, id: u32) -> bool {
     conn.execute(
         "INSERT INTO deleted_records (table_name, record_id, deleted_at) VALUES ('time_entries', ?1, ?2)",
@@ -349,34 +1350,294 @@ pub fn delete_entry(conn: &Connection, id: u32) -> bool {
     let changed = conn
         .execute("DELETE FROM time_entries WHERE id = ?1", params![id])
         .unwrap_or(0);
+    conn.execute("DELETE FROM entry_todos WHERE entry_id = ?1", params![id])
+        .expect("failed to clear entry todo links");
+    conn.execute("DELETE FROM entry_notes WHERE entry_id = ?1", params![id])
+        .expect("failed to clear entry notes");
     changed > 0
 }
 
-pub fn query_entries(conn: &Connection, since_ts: Option<i64>) -> Vec<TimeEntry> {
-    let (sql, bind_ts) = match since_ts {
-        Some(ts) => (
-            "SELECT id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified FROM time_entries WHERE started_at >= ?1 ORDER BY started_at",
-            Some(ts),
-        ),
-        None => (
-            "SELECT id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified FROM time_entries ORDER BY started_at",
-            None,
+/// Move every entry started before `before_ts` into `archived_entries`,
+/// returning the ids moved, or only those that would be with `dry_run`.
+/// Their todo links and notes stay put, and no deletion is recorded, so
+/// synced clients keep their copies.
+pub fn archive_entries_before(conn: &Connection, before_ts: i64, dry_run: bool) -> Vec<u32> {
+    let tx = conn.unchecked_transaction().expect("failed to begin transaction");
+    let ids: Vec<u32> = filter_entries(&tx, &EntryFilter { until_ts: Some(before_ts), ..Default::default() })
+        .into_iter()
+        .map(|e| e.id)
+        .collect();
+    if dry_run || ids.is_empty() {
+        return ids;
+    }
+    // Move by id, so the ids returned are exactly the rows moved.
+    let id_list = ids.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(", ");
+    tx.execute(
+        &format!(
+            "INSERT INTO archived_entries
+             SELECT id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified, tags, utc_offset, host
+             FROM time_entries WHERE id IN ({id_list})"
         ),
-    };
+        [],
+    )
+    .expect("failed to archive entries");
+    tx.execute(&format!("DELETE FROM time_entries WHERE id IN ({id_list})"), [])
+        .expect("failed to remove archived entries");
+    tx.commit().expect("failed to commit archive");
+    ids
+}
+
+/// Every archived entry, oldest first.
+pub fn archived_entries(conn: &Connection) -> Vec<TimeEntry> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified, tags, utc_offset, host
+             FROM archived_entries ORDER BY started_at",
+        )
+        .unwrap();
+    stmt.query_map([], row_to_entry).unwrap().filter_map(|r| r.ok()).collect()
+}
+
+/// Add `entry` straight to the archive, e.g. from `tl import`. It takes its
+/// id from `time_entries` so it can never clash with a live entry's.
+pub fn insert_archived_entry(conn: &Connection, entry: &TimeEntry) -> u32 {
+    let id = insert_entry(conn, entry);
+    conn.execute(
+        "INSERT INTO archived_entries
+         SELECT id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified, tags, utc_offset, host
+         FROM time_entries WHERE id = ?1",
+        params![id],
+    )
+    .expect("failed to archive entry");
+    conn.execute("DELETE FROM time_entries WHERE id = ?1", params![id])
+        .expect("failed to remove archived entry");
+    id
+}
+
+/// Atomically delete the entries in `remove` and insert `insert` in their
+/// place, returning the new entry IDs. Used by split and merge; every new
+/// entry inherits the todos linked to any of the removed ones, and each note
+/// moves to the last new entry started by the time it was written.
+pub fn replace_entries(conn: &Connection, remove: &[u32], insert: &[TimeEntry]) -> Vec<u32> {
+    let tx = conn.unchecked_transaction().expect("failed to begin transaction");
+    let mut todos: Vec<u32> = Vec::new();
+    let mut notes: Vec<Note> = Vec::new();
+    for &id in remove {
+        if let Some(e) = get_entry_by_id(&tx, id) {
+            for tid in entry_todos(&tx, &e) {
+                if !todos.contains(&tid) {
+                    todos.push(tid);
+                }
+            }
+        }
+        notes.extend(entry_notes(&tx, id));
+        delete_entry(&tx, id);
+    }
+    let ids: Vec<u32> = insert.iter().map(|e| insert_entry(&tx, e)).collect();
+    for &id in &ids {
+        set_links(&tx, "entry_todos", "entry_id", id, &todos);
+    }
+    for note in &notes {
+        let owner = insert
+            .iter()
+            .zip(&ids)
+            .rfind(|(e, _)| e.started_at <= note.ts)
+            .or(insert.iter().zip(&ids).next());
+        if let Some((_, &id)) = owner {
+            add_entry_note(&tx, id, note);
+        }
+    }
+    tx.commit().expect("failed to commit entry replacement");
+    ids
+}
+
+pub fn query_entries(conn: &Connection, since_ts: Option<i64>) -> Vec<TimeEntry> {
+    filter_entries(
+        conn,
+        &EntryFilter {
+            since_ts,
+            ..Default::default()
+        },
+    )
+}
+
+/// Optional constraints for `filter_entries`; unset fields match everything.
+#[derive(Default)]
+pub struct EntryFilter<'a> {
+    pub since_ts: Option<i64>,
+    pub until_ts: Option<i64>,
+    pub tag: Option<&'a str>,
+    /// Keep entries logged on this host (case-insensitive).
+    pub host: Option<&'a str>,
+    /// Keep entries in any of these categories (case-insensitive); empty
+    /// means every category.
+    pub categories: &'a [String],
+    /// Also search `archived_entries` (see `archive_entries_before`).
+    pub include_archived: bool,
+    /// Keep only the N most recently started matches.
+    pub limit: Option<u32>,
+    pub sort: EntrySort,
+    /// Descending instead of ascending order.
+    pub reverse: bool,
+}
+
+/// Result order for `filter_entries`.
+#[derive(Clone, Copy, Debug, Default)]
+pub enum EntrySort {
+    #[default]
+    Date,
+    Active,
+    Category,
+}
+
+impl EntrySort {
+    fn order_by(self, reverse: bool) -> String {
+        let dir = if reverse { "DESC" } else { "ASC" };
+        match self {
+            EntrySort::Date => format!("started_at {dir}"),
+            EntrySort::Active => format!("active_secs {dir}, started_at {dir}"),
+            EntrySort::Category => format!("category COLLATE NOCASE {dir}, started_at {dir}"),
+        }
+    }
+}
+
+/// `s` with `\`, `%` and `_` escaped for a `LIKE ... ESCAPE '\'` pattern,
+/// so user input only ever matches itself.
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
 
-    let mut stmt = conn.prepare(sql).unwrap();
-    let rows = if let Some(ts) = bind_ts {
-        stmt.query_map(params![ts], row_to_entry).unwrap()
+pub fn filter_entries(conn: &Connection, filter: &EntryFilter) -> Vec<TimeEntry> {
+    const COLUMNS: &str = "id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified, tags, utc_offset, host";
+    let table = if filter.include_archived {
+        format!("(SELECT {COLUMNS} FROM time_entries UNION ALL SELECT {COLUMNS} FROM archived_entries)")
     } else {
-        stmt.query_map([], row_to_entry).unwrap()
+        "time_entries".to_string()
     };
+    let mut sql = format!("SELECT {COLUMNS} FROM {table} WHERE 1 = 1");
+    let mut binds: Vec<rusqlite::types::Value> = Vec::new();
+
+    if let Some(ts) = filter.since_ts {
+        binds.push(ts.into());
+        sql.push_str(&format!(" AND started_at >= ?{}", binds.len()));
+    }
+    if let Some(ts) = filter.until_ts {
+        binds.push(ts.into());
+        sql.push_str(&format!(" AND started_at < ?{}", binds.len()));
+    }
+    if let Some(tag) = filter.tag {
+        binds.push(escape_like(tag.trim_start_matches('#')).into());
+        sql.push_str(&format!(" AND (',' || tags || ',') LIKE ('%,' || ?{} || ',%') ESCAPE '\\'", binds.len()));
+    }
+    if let Some(host) = filter.host {
+        binds.push(host.trim().to_string().into());
+        sql.push_str(&format!(" AND host = ?{} COLLATE NOCASE", binds.len()));
+    }
+    if !filter.categories.is_empty() {
+        let mut placeholders = Vec::new();
+        for category in filter.categories {
+            binds.push(category.trim().to_string().into());
+            placeholders.push(format!("?{}", binds.len()));
+        }
+        sql.push_str(&format!(" AND category COLLATE NOCASE IN ({})", placeholders.join(", ")));
+    }
+    if let Some(limit) = filter.limit {
+        binds.push(limit.into());
+        sql = format!("SELECT * FROM ({sql} ORDER BY started_at DESC LIMIT ?{})", binds.len());
+    }
+    sql.push_str(&format!(" ORDER BY {}", filter.sort.order_by(filter.reverse)));
+
+    let mut stmt = conn.prepare(&sql).unwrap();
+    let rows = stmt
+        .query_map(rusqlite::params_from_iter(binds), row_to_entry)
+        .unwrap();
+    rows.filter_map(|r| r.ok()).collect()
+}
 
+/// The stretches of `entry` spent working: its span minus its breaks.
+fn active_spans(entry: &TimeEntry) -> Vec<(i64, i64)> {
+    let mut breaks: Vec<(i64, i64)> = entry
+        .breaks
+        .iter()
+        .map(|b| (b.start_ts, if b.end_ts == 0 { entry.ended_at } else { b.end_ts }))
+        .collect();
+    breaks.sort_unstable();
+    let mut spans = Vec::new();
+    let mut from = entry.started_at;
+    for (start, end) in breaks {
+        if start > from {
+            spans.push((from, start.min(entry.ended_at)));
+        }
+        from = from.max(end);
+    }
+    if from < entry.ended_at {
+        spans.push((from, entry.ended_at));
+    }
+    spans.retain(|(start, end)| start < end);
+    spans
+}
+
+/// Seconds during which both `a` and `b` were running, breaks excluded.
+pub fn active_overlap_secs(a: &TimeEntry, b: &TimeEntry) -> i64 {
+    let b_spans = active_spans(b);
+    active_spans(a)
+        .iter()
+        .flat_map(|&(a0, a1)| b_spans.iter().map(move |&(b0, b1)| (a1.min(b1) - a0.max(b0)).max(0)))
+        .sum()
+}
+
+/// Logged entries that were running at the same time as `entry` for at least
+/// a second, ignoring `exclude_id` so an entry being edited doesn't match
+/// itself. Time one of them spent on a break doesn't count, so pausing one
+/// timer to work on another isn't an overlap.
+pub fn find_overlapping(conn: &Connection, entry: &TimeEntry, exclude_id: Option<u32>) -> Vec<TimeEntry> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified, tags, utc_offset, host FROM time_entries
+             WHERE started_at < ?2 AND ended_at > ?1 AND id IS NOT ?3 ORDER BY started_at",
+        )
+        .unwrap();
+    stmt.query_map(params![entry.started_at, entry.ended_at, exclude_id], row_to_entry)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .filter(|e| active_overlap_secs(entry, e) > 0)
+        .collect()
+}
+
+/// How far apart the starts (and ends) of two same-named entries may be for
+/// them to count as the same session logged twice.
+pub const DUPLICATE_TOLERANCE_SECS: i64 = 5 * 60;
+
+/// Whether `a` and `b` look like one session logged twice: same name and
+/// category (ignoring case), with start and end times within
+/// [`DUPLICATE_TOLERANCE_SECS`] of each other.
+pub fn is_near_duplicate(a: &TimeEntry, b: &TimeEntry) -> bool {
+    a.name.trim().eq_ignore_ascii_case(b.name.trim())
+        && a.category.eq_ignore_ascii_case(&b.category)
+        && (a.started_at - b.started_at).abs() <= DUPLICATE_TOLERANCE_SECS
+        && (a.ended_at - b.ended_at).abs() <= DUPLICATE_TOLERANCE_SECS
+}
+
+/// Entries whose name contains `query` (case-insensitive), optionally limited
+/// to those started at or after `since_ts`.
+pub fn search_entries(conn: &Connection, query: &str, since_ts: Option<i64>) -> Vec<TimeEntry> {
+    let pattern = format!("%{}%", escape_like(query));
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified, tags, utc_offset, host FROM time_entries
+             WHERE name LIKE ?1 ESCAPE '\\' AND started_at >= ?2 ORDER BY started_at",
+        )
+        .unwrap();
+    let rows = stmt
+        .query_map(params![pattern, since_ts.unwrap_or(i64::MIN)], row_to_entry)
+        .unwrap();
     rows.filter_map(|r| r.ok()).collect()
 }
 
 pub fn get_last_entry(conn: &Connection) -> Option<TimeEntry> {
     conn.query_row(
-        "SELECT id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified FROM time_entries ORDER BY ended_at DESC LIMIT 1",
+        "SELECT id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified, tags, utc_offset, host FROM time_entries ORDER BY ended_at DESC LIMIT 1",
         [],
         row_to_entry,
     )
@@ -403,14 +1664,61 @@ pub fn distinct_categories(conn: &Connection) -> Vec<String> {
         .collect()
 }
 
+/// Distinct categories, most recently used first. Variants differing only in
+/// case count once, under their latest spelling.
+pub fn list_recent_categories(conn: &Connection, limit: u32) -> Vec<String> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT category, MAX(started_at) AS last_used FROM time_entries WHERE category != ''
+             GROUP BY lower(category) ORDER BY last_used DESC LIMIT ?1",
+        )
+        .unwrap();
+    stmt.query_map(params![limit], |row| row.get(0))
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+/// Trim a typed name and collapse runs of whitespace inside it, so
+/// "Email " and " Email" are the same thing.
+pub fn normalize_name(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// `normalize_name` for a category, also lowercased with `lowercase`.
+pub fn normalize_category(category: &str, lowercase: bool) -> String {
+    let category = normalize_name(category);
+    if lowercase { category.to_lowercase() } else { category }
+}
+
+/// Whether the `lowercase-categories` setting is on.
+pub fn lowercase_categories(conn: &Connection) -> bool {
+    get_setting(conn, "lowercase_categories").as_deref() == Some("on")
+}
+
+/// Normalize a typed category, then map it onto the existing spelling when
+/// one matches case-insensitively, so "email" and "Email" don't split
+/// reports.
+pub fn canonical_category(conn: &Connection, category: &str) -> String {
+    let lowercase = lowercase_categories(conn);
+    let category = normalize_category(category, lowercase);
+    if lowercase {
+        return category;
+    }
+    conn.query_row(
+        "SELECT category FROM time_entries WHERE lower(category) = lower(?1) ORDER BY started_at DESC LIMIT 1",
+        params![category],
+        |row| row.get(0),
+    )
+    .unwrap_or(category)
+}
+
+/// (total_secs, by_day, by_category, streak_days) as returned by `aggregate_entries`.
+pub type Aggregate = (i64, Vec<(String, i64)>, Vec<(String, i64)>, u32);
+
 /// Aggregate entries since `since_ts`, grouped by day (yyyy-MM-dd in local tz)
 /// and by category. Also computes a streak (consecutive days with any entry).
-pub fn aggregate_entries(
-    conn: &Connection,
-    since_ts: i64,
-) -> (i64, Vec<(String, i64)>, Vec<(String, i64)>, u32) {
-    use chrono::{Local, TimeZone};
-
+pub fn aggregate_entries(conn: &Connection, since_ts: i64) -> Aggregate {
     // by day
     let mut day_stmt = conn
         .prepare(
@@ -426,27 +1734,25 @@ pub fn aggregate_entries(
         })
         .unwrap();
 
+    let hour = day_start_hour(conn);
     let mut by_day_map: std::collections::BTreeMap<String, i64> = std::collections::BTreeMap::new();
     let mut by_cat_map: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
     let mut total: i64 = 0;
 
     for r in rows.flatten() {
         let (ts, secs, cat) = r;
-        let dt = Local.timestamp_opt(ts, 0).single();
-        if let Some(dt) = dt {
-            let key = dt.format("%Y-%m-%d").to_string();
-            *by_day_map.entry(key).or_insert(0) += secs;
-        }
+        let key = day_of(ts, hour).format("%Y-%m-%d").to_string();
+        *by_day_map.entry(key).or_insert(0) += secs;
         *by_cat_map.entry(cat).or_insert(0) += secs;
         total += secs;
     }
 
     let by_day: Vec<(String, i64)> = by_day_map.into_iter().collect();
     let mut by_cat: Vec<(String, i64)> = by_cat_map.into_iter().collect();
-    by_cat.sort_by(|a, b| b.1.cmp(&a.1));
+    by_cat.sort_by_key(|c| std::cmp::Reverse(c.1));
 
     // streak — consecutive days ending today (local) with any entry
-    let today = Local::now().date_naive();
+    let today = day_of(now_ts(), hour);
     let mut day_set: std::collections::HashSet<String> = std::collections::HashSet::new();
     for (d, _) in &by_day {
         day_set.insert(d.clone());
@@ -472,24 +1778,40 @@ pub fn aggregate_entries(
 pub fn recent_todos(conn: &Connection, limit: u32) -> Vec<TodoItem> {
     let mut stmt = conn
         .prepare(
-            "SELECT id, text, done, created_at, last_modified FROM todos ORDER BY last_modified DESC LIMIT ?1",
+            "SELECT id, text, done, created_at, last_modified, estimate_secs, archived, completed_at FROM todos ORDER BY last_modified DESC LIMIT ?1",
         )
         .unwrap();
-    stmt.query_map(params![limit], |row| {
-        Ok(TodoItem {
-            id: row.get(0)?,
-            text: row.get(1)?,
-            done: {
-                let v: i64 = row.get(2)?;
-                v != 0
-            },
-            created_at: row.get(3)?,
-            last_modified: row.get(4)?,
-        })
-    })
-    .unwrap()
-    .filter_map(|r| r.ok())
-    .collect()
+    stmt.query_map(params![limit], row_to_todo)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+/// Today's tracking day, which only turns over at the configured day start.
+pub fn today_date(conn: &Connection) -> NaiveDate {
+    day_of(now_ts(), day_start_hour(conn))
+}
+
+/// Unix timestamp at which today's tracking day began: local midnight, or
+/// the configured day start.
+pub fn today_start_ts(conn: &Connection) -> i64 {
+    day_start_ts(today_date(conn), day_start_hour(conn))
+}
+
+/// Active seconds logged today plus the live active time of all active
+/// timers since the day started.
+pub fn today_active_secs(conn: &Connection) -> i64 {
+    let today_start = today_start_ts(conn);
+    let logged: i64 = query_entries(conn, Some(today_start))
+        .iter()
+        .map(|e| e.active_secs)
+        .sum();
+    let now_ts = now_ts();
+    let live: i64 = get_all_active(conn)
+        .iter()
+        .map(|t| active_secs_since(t, today_start, now_ts))
+        .sum();
+    logged + live
 }
 
 // --- Todo DB ops ---
@@ -500,6 +1822,23 @@ pub struct TodoItem {
     pub done: bool,
     pub created_at: i64,
     pub last_modified: i64,
+    pub estimate_secs: Option<i64>,
+    pub archived: bool,
+    /// When the todo was last marked done; `None` while open.
+    pub completed_at: Option<i64>,
+}
+
+fn row_to_todo(row: &rusqlite::Row) -> rusqlite::Result<TodoItem> {
+    Ok(TodoItem {
+        id: row.get(0)?,
+        text: row.get(1)?,
+        done: row.get::<_, i32>(2)? != 0,
+        created_at: row.get(3)?,
+        last_modified: row.get(4)?,
+        estimate_secs: row.get(5)?,
+        archived: row.get::<_, i32>(6)? != 0,
+        completed_at: row.get(7)?,
+    })
 }
 
 pub fn add_todo(conn: &Connection, text: &str, created_at: i64) -> u32 {
@@ -512,36 +1851,150 @@ pub fn add_todo(conn: &Connection, text: &str, created_at: i64) -> u32 {
     conn.last_insert_rowid() as u32
 }
 
+/// Insert a todo with every field as given (except the id), e.g. on import.
+pub fn insert_todo(conn: &Connection, todo: &TodoItem) -> u32 {
+    conn.execute(
+        "INSERT INTO todos (text, done, created_at, last_modified, estimate_secs, archived, completed_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            todo.text,
+            todo.done as i32,
+            todo.created_at,
+            todo.last_modified,
+            todo.estimate_secs,
+            todo.archived as i32,
+            todo.completed_at,
+        ],
+    )
+    .expect("failed to insert todo");
+    conn.last_insert_rowid() as u32
+}
+
 pub fn list_todos(conn: &Connection) -> Vec<TodoItem> {
     let mut stmt = conn
-        .prepare("SELECT id, text, done, created_at, last_modified FROM todos ORDER BY id")
+        .prepare("SELECT id, text, done, created_at, last_modified, estimate_secs, archived, completed_at FROM todos ORDER BY id")
         .unwrap();
     let rows = stmt
-        .query_map([], |row| {
-            Ok(TodoItem {
-                id: row.get(0)?,
-                text: row.get(1)?,
-                done: row.get::<_, i32>(2)? != 0,
-                created_at: row.get(3)?,
-                last_modified: row.get(4)?,
-            })
-        })
+        .query_map([], row_to_todo)
         .unwrap();
     rows.filter_map(|r| r.ok()).collect()
 }
 
-pub fn mark_todo_done(conn: &Connection, id: u32) -> bool {
+/// Todos that haven't been archived — what pickers and the TUI show.
+pub fn list_active_todos(conn: &Connection) -> Vec<TodoItem> {
+    list_todos(conn).into_iter().filter(|t| !t.archived).collect()
+}
+
+/// The todo `tl start --next` works on: the oldest open todo that no timer
+/// is tracking yet.
+pub fn next_todo(conn: &Connection) -> Option<TodoItem> {
+    let busy: Vec<u32> = get_all_active(conn).iter().flat_map(|t| timer_todos(conn, t)).collect();
+    list_active_todos(conn)
+        .into_iter()
+        .filter(|t| !t.done && !busy.contains(&t.id))
+        .min_by_key(|t| (t.created_at, t.id))
+}
+
+/// Category of the most recent entry logged against a todo, if any.
+pub fn todo_category(conn: &Connection, todo_id: u32) -> Option<String> {
+    conn.query_row(
+        "SELECT category FROM time_entries
+         WHERE id IN (SELECT entry_id FROM entry_todos WHERE todo_id = ?1) OR todo_id = ?1
+         ORDER BY started_at DESC LIMIT 1",
+        params![todo_id],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+/// Archive (or un-archive) a todo. Its time links are left untouched.
+pub fn set_todo_archived(conn: &Connection, id: u32, archived: bool) -> bool {
     let modified = now_ts();
     let changed = conn
-        .execute("UPDATE todos SET done = 1, last_modified = ?1 WHERE id = ?2", params![modified, id])
+        .execute(
+            "UPDATE todos SET archived = ?1, last_modified = ?2 WHERE id = ?3",
+            params![archived as i32, modified, id],
+        )
         .unwrap_or(0);
     changed > 0
 }
 
+/// Archive every completed todo, returning their ids, or only the ids that
+/// would be archived with `dry_run`.
+pub fn archive_done_todos(conn: &Connection, dry_run: bool) -> Vec<u32> {
+    let mut stmt = conn
+        .prepare("SELECT id FROM todos WHERE done = 1 AND archived = 0 ORDER BY id")
+        .unwrap();
+    let ids: Vec<u32> = stmt.query_map([], |row| row.get(0)).unwrap().filter_map(|r| r.ok()).collect();
+    if !dry_run {
+        conn.execute(
+            "UPDATE todos SET archived = 1, last_modified = ?1 WHERE done = 1 AND archived = 0",
+            params![now_ts()],
+        )
+        .expect("failed to archive todos");
+    }
+    ids
+}
+
+pub fn mark_todo_done(conn: &Connection, id: u32) -> bool {
+    mark_todos_done(conn, &[id])[0].1
+}
+
+/// Mark several todos done in one transaction, returning each id with
+/// whether it was found.
+pub fn mark_todos_done(conn: &Connection, ids: &[u32]) -> Vec<(u32, bool)> {
+    let modified = now_ts();
+    let tx = conn.unchecked_transaction().expect("failed to begin transaction");
+    let results = ids
+        .iter()
+        .map(|&id| {
+            let changed = tx
+                .execute(
+                    "UPDATE todos SET completed_at = CASE WHEN done = 1 THEN completed_at ELSE ?1 END,
+                     done = 1, last_modified = ?1 WHERE id = ?2",
+                    params![modified, id],
+                )
+                .unwrap_or(0);
+            (id, changed > 0)
+        })
+        .collect();
+    tx.commit().expect("failed to commit todo updates");
+    results
+}
+
 pub fn unmark_todo_done(conn: &Connection, id: u32) -> bool {
     let modified = now_ts();
     let changed = conn
-        .execute("UPDATE todos SET done = 0, last_modified = ?1 WHERE id = ?2", params![modified, id])
+        .execute(
+            "UPDATE todos SET done = 0, completed_at = NULL, last_modified = ?1 WHERE id = ?2",
+            params![modified, id],
+        )
+        .unwrap_or(0);
+    changed > 0
+}
+
+/// Todos marked done in `[since_ts, until_ts)`, oldest first.
+pub fn todos_completed_between(conn: &Connection, since_ts: i64, until_ts: i64) -> Vec<TodoItem> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, text, done, created_at, last_modified, estimate_secs, archived, completed_at FROM todos
+             WHERE done = 1 AND completed_at >= ?1 AND completed_at < ?2 ORDER BY completed_at",
+        )
+        .unwrap();
+    stmt.query_map(params![since_ts, until_ts], row_to_todo)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+/// Set (or with `None`, clear) a todo's time estimate.
+pub fn set_todo_estimate(conn: &Connection, id: u32, estimate_secs: Option<i64>) -> bool {
+    let modified = now_ts();
+    let changed = conn
+        .execute(
+            "UPDATE todos SET estimate_secs = ?1, last_modified = ?2 WHERE id = ?3",
+            params![estimate_secs, modified, id],
+        )
         .unwrap_or(0);
     changed > 0
 }
@@ -554,40 +2007,510 @@ pub fn edit_todo(conn: &Connection, id: u32, text: &str) -> bool {
     changed > 0
 }
 
-pub fn remove_todo(conn: &Connection, id: u32) -> bool {
-    conn.execute(
-        "INSERT INTO deleted_records (table_name, record_id, deleted_at) VALUES ('todos', ?1, ?2)",
-        params![id, now_ts()],
+/// Number of (time entries, active timers) linked to a todo, archived entries
+/// included. Link rows count, and `todo_id` where an owner has none, the same
+/// way `get_todo_secs_since` attributes time.
+pub fn count_todo_links(conn: &Connection, todo_id: u32) -> (u32, u32) {
+    let count = |table: &str, links: &str, owner_col: &str| -> u32 {
+        conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM {table} o
+                 WHERE o.id IN (SELECT {owner_col} FROM {links} WHERE todo_id = ?1)
+                    OR (o.todo_id = ?1 AND NOT EXISTS (SELECT 1 FROM {links} x WHERE x.{owner_col} = o.id))"
+            ),
+            params![todo_id],
+            |row| row.get(0),
+        )
+        .unwrap_or(0)
+    };
+    (
+        count("time_entries", "entry_todos", "entry_id") + count("archived_entries", "entry_todos", "entry_id"),
+        count("active_timers", "timer_todos", "timer_id"),
+    )
+}
+
+/// Delete a todo and unlink every entry and active timer that pointed at it,
+/// all in one transaction so nothing is left with a dangling `todo_id`. One
+/// linked to several todos keeps the rest, the first becoming its `todo_id`.
+pub fn remove_todo(conn: &Connection, id: u32) -> bool {
+    let modified = now_ts();
+    let tx = conn.unchecked_transaction().expect("failed to begin transaction");
+    tx.execute(
+        "INSERT INTO deleted_records (table_name, record_id, deleted_at) VALUES ('todos', ?1, ?2)",
+        params![id, modified],
     ).expect("failed to record deletion");
-    let changed = conn
+    let changed = tx
         .execute("DELETE FROM todos WHERE id = ?1", params![id])
         .unwrap_or(0);
+    if changed > 0 {
+        relink_todo(&tx, id, None, modified);
+    }
+    tx.commit().expect("failed to commit todo removal");
     changed > 0
 }
 
+/// Fold todo `from` into `into`: every entry, active timer and template
+/// linked to `from` now counts toward `into` (which takes `text`), and `from`
+/// is deleted, all in one transaction. Returns `false` if either todo is
+/// missing or `text` is blank.
+pub fn merge_todos(conn: &Connection, from: u32, into: u32, text: &str) -> bool {
+    let text = normalize_name(text);
+    if text.is_empty() {
+        return false;
+    }
+    let modified = now_ts();
+    let tx = conn.unchecked_transaction().expect("failed to begin transaction");
+    if get_todo_by_id(&tx, from).is_none() || get_todo_by_id(&tx, into).is_none() {
+        return false;
+    }
+    relink_todo(&tx, from, Some(into), modified);
+    tx.execute(
+        "UPDATE todos SET text = ?1, last_modified = ?2 WHERE id = ?3",
+        params![text, modified, into],
+    ).expect("failed to update merged todo");
+    tx.execute(
+        "INSERT INTO deleted_records (table_name, record_id, deleted_at) VALUES ('todos', ?1, ?2)",
+        params![from, modified],
+    ).expect("failed to record deletion");
+    tx.execute("DELETE FROM todos WHERE id = ?1", params![from])
+        .expect("failed to delete merged todo");
+    tx.commit().expect("failed to commit todo merge");
+    true
+}
+
+/// Point every entry (archived ones too), active timer and template linked
+/// to todo `from` at `into` instead, or drop the link with `None`. Only those
+/// owners change; each keeps its other links, with `todo_id` the first.
+fn relink_todo(conn: &Connection, from: u32, into: Option<u32>, modified: i64) {
+    let relink = |todos: &[u32]| -> Vec<u32> {
+        let mut relinked = Vec::new();
+        for id in todos.iter().filter_map(|&id| if id == from { into } else { Some(id) }) {
+            if !relinked.contains(&id) {
+                relinked.push(id);
+            }
+        }
+        relinked
+    };
+    for (table, links, owner_col) in [
+        ("time_entries", "entry_todos", "entry_id"),
+        ("archived_entries", "entry_todos", "entry_id"),
+        ("active_timers", "timer_todos", "timer_id"),
+    ] {
+        let owners: Vec<(u32, Option<u32>)> = {
+            let mut stmt = conn
+                .prepare(&format!(
+                    "SELECT id, todo_id FROM {table} WHERE todo_id = ?1
+                       OR id IN (SELECT {owner_col} FROM {links} WHERE todo_id = ?1)"
+                ))
+                .unwrap();
+            stmt.query_map(params![from], |row| Ok((row.get(0)?, row.get(1)?)))
+                .unwrap()
+                .filter_map(|r| r.ok())
+                .collect()
+        };
+        for (owner, todo_id) in owners {
+            let current = get_links(conn, links, owner_col, owner);
+            let todos = relink(if current.is_empty() { todo_id.as_slice() } else { &current });
+            conn.execute(
+                &format!("UPDATE {table} SET todo_id = ?2, last_modified = ?3 WHERE id = ?1"),
+                params![owner, todos.first(), modified],
+            ).expect("failed to relink todo");
+            set_links(conn, links, owner_col, owner, &todos);
+        }
+    }
+    for mut template in list_templates(conn) {
+        if template.todo_ids.contains(&from) {
+            template.todo_ids = relink(&template.todo_ids);
+            save_template(conn, &template);
+        }
+    }
+}
+
+// --- Multi-todo links ---
+//
+// A timer or entry linked to several todos lists all of them in
+// `timer_todos` / `entry_todos`, while `todo_id` keeps the first one so
+// single-todo consumers (sync, the API) are unaffected. With no link rows,
+// `todo_id` alone applies.
+
+fn get_links(conn: &Connection, table: &str, owner_col: &str, owner: u32) -> Vec<u32> {
+    let mut stmt = conn
+        .prepare(&format!("SELECT todo_id FROM {table} WHERE {owner_col} = ?1 ORDER BY rowid"))
+        .unwrap();
+    stmt.query_map(params![owner], |row| row.get(0))
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+/// Replace an owner's links. One todo or none needs no rows — `todo_id` covers it.
+fn set_links(conn: &Connection, table: &str, owner_col: &str, owner: u32, todos: &[u32]) {
+    conn.execute(&format!("DELETE FROM {table} WHERE {owner_col} = ?1"), params![owner])
+        .expect("failed to clear todo links");
+    if todos.len() > 1 {
+        for tid in todos {
+            conn.execute(
+                &format!("INSERT OR IGNORE INTO {table} ({owner_col}, todo_id) VALUES (?1, ?2)"),
+                params![owner, tid],
+            )
+            .expect("failed to link todo");
+        }
+    }
+}
+
+/// Link an active timer to `todos` (the first should also be its `todo_id`).
+pub fn set_timer_todos(conn: &Connection, timer_id: u32, todos: &[u32]) {
+    set_links(conn, "timer_todos", "timer_id", timer_id, todos);
+}
+
+/// Link a log entry to `todos` (the first should also be its `todo_id`).
+pub fn set_entry_todos(conn: &Connection, entry_id: u32, todos: &[u32]) {
+    set_links(conn, "entry_todos", "entry_id", entry_id, todos);
+}
+
+/// Each todo link table, what owns its rows, and the condition picking out
+/// rows whose owner is gone. An archived entry still owns its links.
+const LINK_OWNERS: [(&str, &str, &str); 2] = [
+    (
+        "entry_todos",
+        "entries",
+        "entry_id NOT IN (SELECT id FROM time_entries UNION SELECT id FROM archived_entries)",
+    ),
+    ("timer_todos", "active timers", "timer_id NOT IN (SELECT id FROM active_timers)"),
+];
+
+/// Todo links whose entry or timer no longer exists, as `(table, owners,
+/// count)` for each link table that has any.
+pub fn orphaned_todo_links(conn: &Connection) -> Vec<(&'static str, &'static str, i64)> {
+    LINK_OWNERS
+        .iter()
+        .filter_map(|&(table, owners, orphaned)| {
+            let n: i64 = conn
+                .query_row(&format!("SELECT COUNT(*) FROM {table} WHERE {orphaned}"), [], |row| row.get(0))
+                .unwrap_or(0);
+            (n > 0).then_some((table, owners, n))
+        })
+        .collect()
+}
+
+/// Delete the rows of link table `table` that `orphaned_todo_links` counts.
+pub fn remove_orphaned_todo_links(conn: &Connection, table: &str) {
+    if let Some((_, _, orphaned)) = LINK_OWNERS.iter().find(|(t, _, _)| *t == table) {
+        conn.execute(&format!("DELETE FROM {table} WHERE {orphaned}"), [])
+            .expect("failed to remove orphaned links");
+    }
+}
+
+/// Every todo an active timer counts toward.
+pub fn timer_todos(conn: &Connection, timer: &ActiveTimer) -> Vec<u32> {
+    let links = timer.id.map(|id| get_links(conn, "timer_todos", "timer_id", id)).unwrap_or_default();
+    if links.is_empty() { timer.todo_id.into_iter().collect() } else { links }
+}
+
+/// Every todo a log entry counts toward.
+pub fn entry_todos(conn: &Connection, entry: &TimeEntry) -> Vec<u32> {
+    let links = get_links(conn, "entry_todos", "entry_id", entry.id);
+    if links.is_empty() { entry.todo_id.into_iter().collect() } else { links }
+}
+
+/// Logged active time for a todo. Entries linked to several todos are split
+/// evenly between them.
 pub fn get_todo_total_secs(conn: &Connection, todo_id: u32) -> i64 {
+    get_todo_secs_since(conn, todo_id, i64::MIN)
+}
+
+/// Logged active time for a todo from entries started at or after `since_ts`,
+/// archived ones included: archiving shouldn't take time off an open todo.
+pub fn get_todo_secs_since(conn: &Connection, todo_id: u32, since_ts: i64) -> i64 {
     conn.query_row(
-        "SELECT COALESCE(SUM(active_secs), 0) FROM time_entries WHERE todo_id = ?1",
-        params![todo_id],
+        "SELECT COALESCE(SUM(e.active_secs / MAX(1, (SELECT COUNT(*) FROM entry_todos x WHERE x.entry_id = e.id))), 0)
+         FROM (SELECT id, todo_id, active_secs, started_at FROM time_entries
+               UNION ALL SELECT id, todo_id, active_secs, started_at FROM archived_entries) e
+         WHERE (e.id IN (SELECT entry_id FROM entry_todos WHERE todo_id = ?1)
+            OR (e.todo_id = ?1 AND NOT EXISTS (SELECT 1 FROM entry_todos x WHERE x.entry_id = e.id)))
+           AND e.started_at >= ?2",
+        params![todo_id, since_ts],
         |row| row.get(0),
     )
     .unwrap_or(0)
 }
 
 pub fn get_active_todo_secs(conn: &Connection, todo_id: u32) -> i64 {
+    get_active_todo_secs_since(conn, todo_id, i64::MIN)
+}
+
+/// Active time for a todo from running or paused timers, counting only
+/// what falls at or after `since_ts`.
+pub fn get_active_todo_secs_since(conn: &Connection, todo_id: u32, since_ts: i64) -> i64 {
     let now_ts = chrono::Local::now().timestamp();
     let timers = get_all_active(conn);
     timers
         .iter()
-        .filter(|t| t.todo_id == Some(todo_id))
-        .map(|t| {
-            let elapsed = now_ts - t.started_at;
-            let break_secs = total_break_secs(&t.breaks, now_ts);
-            (elapsed - break_secs).max(0)
+        .filter_map(|t| {
+            let todos = timer_todos(conn, t);
+            if !todos.contains(&todo_id) {
+                return None;
+            }
+            Some(active_secs_since(t, since_ts, now_ts) / todos.len() as i64)
         })
         .sum()
 }
 
+/// Time spent on a todo as `(logged, active, total)`: finished entries,
+/// timers still running or paused on it, and the two together. Use this
+/// wherever a todo's time is shown so every command agrees.
+pub fn get_todo_combined_secs(conn: &Connection, todo_id: u32) -> (i64, i64, i64) {
+    let logged = get_todo_total_secs(conn, todo_id);
+    let active = get_active_todo_secs(conn, todo_id);
+    (logged, active, logged + active)
+}
+
+// --- Settings DB ops ---
+
+pub fn get_setting(conn: &Connection, key: &str) -> Option<String> {
+    conn.query_row(
+        "SELECT value FROM settings WHERE key = ?1",
+        params![key],
+        |row| row.get(0),
+    )
+    .ok()
+}
+
+pub fn set_setting(conn: &Connection, key: &str, value: &str) {
+    conn.execute(
+        "INSERT INTO settings (key, value) VALUES (?1, ?2)
+         ON CONFLICT(key) DO UPDATE SET value = ?2",
+        params![key, value],
+    )
+    .expect("failed to save setting");
+}
+
+pub fn clear_setting(conn: &Connection, key: &str) -> bool {
+    let changed = conn
+        .execute("DELETE FROM settings WHERE key = ?1", params![key])
+        .unwrap_or(0);
+    changed > 0
+}
+
+/// Whether this is the first time the CLI has opened an empty database.
+/// True at most once: a `onboarded` settings row is written on the first
+/// call, and a database that already holds data never counts as new.
+pub fn first_run(conn: &Connection) -> bool {
+    if get_setting(conn, "onboarded").is_some() {
+        return false;
+    }
+    set_setting(conn, "onboarded", "1");
+    conn.query_row(
+        "SELECT NOT EXISTS (SELECT 1 FROM time_entries)
+            AND NOT EXISTS (SELECT 1 FROM active_timers)
+            AND NOT EXISTS (SELECT 1 FROM todos)",
+        [],
+        |row| row.get(0),
+    )
+    .unwrap_or(false)
+}
+
+/// The configured daily goal in seconds, if any.
+pub fn daily_goal_secs(conn: &Connection) -> Option<i64> {
+    get_setting(conn, "daily_goal_secs").and_then(|v| v.parse().ok())
+}
+
+/// Configured cap on a single timer's active time (`tl config set max-duration`).
+pub fn max_duration_secs(conn: &Connection) -> Option<i64> {
+    get_setting(conn, "max_duration_secs").and_then(|v| v.parse().ok())
+}
+
+/// Whether times display as 12-hour with am/pm (`tl config set time-format 12h`).
+pub fn uses_12h(conn: &Connection) -> bool {
+    get_setting(conn, "time_format").as_deref() == Some("12h")
+}
+
+/// A pause longer than this is probably a forgotten timer; `tl resume` offers
+/// to correct it (`tl config set long-break`, 12h by default).
+pub fn long_break_secs(conn: &Connection) -> i64 {
+    get_setting(conn, "long_break_secs").and_then(|v| v.parse().ok()).unwrap_or(12 * 3600)
+}
+
+/// When `tl` last ran, or a long-running `tl` last ticked: a gap since then
+/// with a timer running suggests the machine was asleep.
+pub fn last_seen(conn: &Connection) -> Option<i64> {
+    get_setting(conn, "last_seen").and_then(|v| v.parse().ok())
+}
+
+/// Record that `tl` is alive at `now_ts`. Writes at most once a minute, so
+/// polling loops can call it on every tick.
+pub fn touch_heartbeat(conn: &Connection, now_ts: i64) {
+    if last_seen(conn).is_some_and(|ts| (0..60).contains(&(now_ts - ts))) {
+        return;
+    }
+    set_setting(conn, "last_seen", &now_ts.to_string());
+}
+
+/// A heartbeat gap at least this long while a timer runs is offered as a
+/// break (`tl config set sleep-gap`, 4h by default; 0 turns it off).
+pub fn sleep_gap_secs(conn: &Connection) -> i64 {
+    get_setting(conn, "sleep_gap_secs").and_then(|v| v.parse().ok()).unwrap_or(4 * 3600)
+}
+
+/// Hour (0–23) at which a tracking day begins (`tl config set day-start 4`),
+/// so work past midnight counts towards the day it started; 0 unless set.
+pub fn day_start_hour(conn: &Connection) -> u32 {
+    get_setting(conn, "day_start_hour").and_then(|v| v.parse().ok()).filter(|&h| h < 24).unwrap_or(0)
+}
+
+/// First day of the week (`tl config set week-start sunday`); Monday unless set.
+pub fn week_start(conn: &Connection) -> Weekday {
+    match get_setting(conn, "week_start").as_deref() {
+        Some("sunday") => Weekday::Sun,
+        _ => Weekday::Mon,
+    }
+}
+
+/// The date the week containing `date` starts on, for weeks beginning on `start`.
+pub fn week_start_for(date: NaiveDate, start: Weekday) -> NaiveDate {
+    date.week(start).first_day()
+}
+
+/// The first tracking day of the current week, by `week-start`.
+pub fn this_week_start(conn: &Connection) -> NaiveDate {
+    week_start_for(today_date(conn), week_start(conn))
+}
+
+// --- Category budgets ---
+
+pub fn set_budget(conn: &Connection, category: &str, weekly_secs: i64) {
+    conn.execute(
+        "INSERT INTO budgets (category, weekly_secs) VALUES (?1, ?2)
+         ON CONFLICT(category) DO UPDATE SET category = ?1, weekly_secs = ?2",
+        params![category, weekly_secs],
+    )
+    .expect("failed to save budget");
+}
+
+pub fn clear_budget(conn: &Connection, category: &str) -> bool {
+    let changed = conn
+        .execute("DELETE FROM budgets WHERE category = ?1", params![category.trim()])
+        .unwrap_or(0);
+    changed > 0
+}
+
+/// Weekly budgets as `(category, weekly_secs)`, alphabetically.
+pub fn list_budgets(conn: &Connection) -> Vec<(String, i64)> {
+    let mut stmt = conn
+        .prepare("SELECT category, weekly_secs FROM budgets ORDER BY category")
+        .unwrap();
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+// --- Category colors ---
+
+/// Show `category` in `color` (one of the CLI's color names) everywhere.
+pub fn set_category_color(conn: &Connection, category: &str, color: &str) {
+    conn.execute(
+        "INSERT INTO category_colors (category, color) VALUES (?1, ?2)
+         ON CONFLICT(category) DO UPDATE SET color = ?2",
+        params![category.trim(), color],
+    )
+    .expect("failed to save category color");
+}
+
+pub fn clear_category_color(conn: &Connection, category: &str) -> bool {
+    let changed = conn
+        .execute("DELETE FROM category_colors WHERE category = ?1", params![category.trim()])
+        .unwrap_or(0);
+    changed > 0
+}
+
+/// Every (category, color) set explicitly, by category.
+pub fn list_category_colors(conn: &Connection) -> Vec<(String, String)> {
+    let mut stmt = conn
+        .prepare("SELECT category, color FROM category_colors ORDER BY category")
+        .unwrap();
+    stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+// --- Timer templates ---
+
+/// A saved name/category (and todo links) for `tl start --template`.
+pub struct Template {
+    pub key: String,
+    pub name: String,
+    pub category: String,
+    pub todo_ids: Vec<u32>,
+}
+
+fn row_to_template(row: &rusqlite::Row) -> rusqlite::Result<Template> {
+    let todo_ids: String = row.get(3)?;
+    Ok(Template {
+        key: row.get(0)?,
+        name: row.get(1)?,
+        category: row.get(2)?,
+        todo_ids: todo_ids.split(',').filter_map(|id| id.parse().ok()).collect(),
+    })
+}
+
+/// Add a template, or replace the one with the same key.
+pub fn save_template(conn: &Connection, template: &Template) {
+    let todo_ids: Vec<String> = template.todo_ids.iter().map(|id| id.to_string()).collect();
+    conn.execute(
+        "INSERT INTO templates (key, name, category, todo_ids) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(key) DO UPDATE SET key = ?1, name = ?2, category = ?3, todo_ids = ?4",
+        params![template.key, template.name, template.category, todo_ids.join(",")],
+    )
+    .expect("failed to save template");
+}
+
+pub fn get_template(conn: &Connection, key: &str) -> Option<Template> {
+    conn.query_row(
+        "SELECT key, name, category, todo_ids FROM templates WHERE key = ?1",
+        params![key],
+        row_to_template,
+    )
+    .ok()
+}
+
+pub fn list_templates(conn: &Connection) -> Vec<Template> {
+    let mut stmt = conn
+        .prepare("SELECT key, name, category, todo_ids FROM templates ORDER BY key")
+        .unwrap();
+    stmt.query_map([], row_to_template)
+        .unwrap()
+        .filter_map(|r| r.ok())
+        .collect()
+}
+
+pub fn remove_template(conn: &Connection, key: &str) -> bool {
+    let changed = conn
+        .execute("DELETE FROM templates WHERE key = ?1", params![key])
+        .unwrap_or(0);
+    changed > 0
+}
+
+/// Time of day with seconds, in the configured 12h/24h format.
+pub fn format_time(conn: &Connection, ts: i64) -> String {
+    local_time(ts).format(if uses_12h(conn) { "%-I:%M:%S %p" } else { "%H:%M:%S" }).to_string()
+}
+
+/// Time of day to the minute, in the configured 12h/24h format.
+pub fn format_hm(conn: &Connection, ts: i64) -> String {
+    local_time(ts).format(if uses_12h(conn) { "%-I:%M %p" } else { "%H:%M" }).to_string()
+}
+
+pub fn format_date(ts: i64) -> String {
+    local_time(ts).format("%Y-%m-%d").to_string()
+}
+
+/// Date plus time of day with seconds.
+pub fn format_datetime(conn: &Connection, ts: i64) -> String {
+    format!("{} {}", format_date(ts), format_time(conn, ts))
+}
+
 // --- Sync query functions ---
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -608,7 +2531,7 @@ pub fn query_modified_timers(conn: &Connection, since_ts: i64) -> Vec<ActiveTime
 
 pub fn query_modified_entries(conn: &Connection, since_ts: i64) -> Vec<TimeEntry> {
     let mut stmt = conn
-        .prepare("SELECT id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified FROM time_entries WHERE last_modified > ?1 ORDER BY id")
+        .prepare("SELECT id, name, category, started_at, ended_at, active_secs, breaks, todo_id, last_modified, tags, utc_offset, host FROM time_entries WHERE last_modified > ?1 ORDER BY id")
         .unwrap();
     let rows = stmt.query_map(params![since_ts], row_to_entry).unwrap();
     rows.filter_map(|r| r.ok()).collect()
@@ -616,18 +2539,10 @@ pub fn query_modified_entries(conn: &Connection, since_ts: i64) -> Vec<TimeEntry
 
 pub fn query_modified_todos(conn: &Connection, since_ts: i64) -> Vec<TodoItem> {
     let mut stmt = conn
-        .prepare("SELECT id, text, done, created_at, last_modified FROM todos WHERE last_modified > ?1 ORDER BY id")
+        .prepare("SELECT id, text, done, created_at, last_modified, estimate_secs, archived, completed_at FROM todos WHERE last_modified > ?1 ORDER BY id")
         .unwrap();
     let rows = stmt
-        .query_map(params![since_ts], |row| {
-            Ok(TodoItem {
-                id: row.get(0)?,
-                text: row.get(1)?,
-                done: row.get::<_, i32>(2)? != 0,
-                created_at: row.get(3)?,
-                last_modified: row.get(4)?,
-            })
-        })
+        .query_map(params![since_ts], row_to_todo)
         .unwrap();
     rows.filter_map(|r| r.ok()).collect()
 }
@@ -668,6 +2583,13 @@ pub fn set_sync_ts(conn: &Connection, client_id: &str, ts: i64) {
 }
 
 pub fn upsert_active_timer(conn: &Connection, id: u32, timer: &ActiveTimer) {
+    keeping_one_running(conn, timer.state == "running", || {
+        upsert_active_row(conn, id, timer);
+        id
+    });
+}
+
+fn upsert_active_row(conn: &Connection, id: u32, timer: &ActiveTimer) {
     let exists: bool = conn
         .query_row("SELECT COUNT(*) FROM active_timers WHERE id = ?1", params![id], |row| row.get::<_, i32>(0))
         .unwrap_or(0) > 0;
@@ -710,12 +2632,15 @@ pub fn upsert_todo(conn: &Connection, id: u32, todo: &TodoItem) {
 
     if exists {
         conn.execute(
-            "UPDATE todos SET text = ?1, done = ?2, created_at = ?3, last_modified = ?4 WHERE id = ?5",
+            "UPDATE todos SET text = ?1,
+             completed_at = CASE WHEN ?2 = 0 THEN NULL WHEN done = 1 THEN completed_at ELSE ?4 END,
+             done = ?2, created_at = ?3, last_modified = ?4 WHERE id = ?5",
             params![todo.text, todo.done as i32, todo.created_at, todo.last_modified, id],
         ).expect("failed to upsert todo");
     } else {
         conn.execute(
-            "INSERT INTO todos (id, text, done, created_at, last_modified) VALUES (?1, ?2, ?3, ?4, ?5)",
+            "INSERT INTO todos (id, text, done, created_at, last_modified, completed_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, CASE WHEN ?3 = 1 THEN ?5 END)",
             params![id, todo.text, todo.done as i32, todo.created_at, todo.last_modified],
         ).expect("failed to upsert todo");
     }
@@ -723,17 +2648,849 @@ pub fn upsert_todo(conn: &Connection, id: u32, todo: &TodoItem) {
 
 pub fn get_todo_by_id(conn: &Connection, id: u32) -> Option<TodoItem> {
     conn.query_row(
-        "SELECT id, text, done, created_at, last_modified FROM todos WHERE id = ?1",
+        "SELECT id, text, done, created_at, last_modified, estimate_secs, archived, completed_at FROM todos WHERE id = ?1",
         params![id],
-        |row| {
-            Ok(TodoItem {
-                id: row.get(0)?,
-                text: row.get(1)?,
-                done: row.get::<_, i32>(2)? != 0,
-                created_at: row.get(3)?,
-                last_modified: row.get(4)?,
-            })
-        },
+        row_to_todo,
     )
     .ok()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::{AssertUnwindSafe, catch_unwind};
+
+    fn test_db() -> Connection {
+        open_db_at(Path::new(":memory:"), Verbosity::Normal).unwrap()
+    }
+
+    fn timer(name: &str, state: &str) -> ActiveTimer {
+        ActiveTimer {
+            id: None,
+            name: name.into(),
+            category: "dev".into(),
+            started_at: 1_000,
+            state: state.into(),
+            breaks: vec![],
+            todo_id: None,
+            last_modified: 0,
+        }
+    }
+
+    fn entry_for(t: &ActiveTimer) -> TimeEntry {
+        TimeEntry {
+            id: 0,
+            name: t.name.clone(),
+            category: t.category.clone(),
+            started_at: t.started_at,
+            ended_at: t.started_at + 600,
+            active_secs: 600,
+            breaks: vec![],
+            todo_id: None,
+            last_modified: 0,
+            tags: vec![],
+            utc_offset: None,
+            host: None,
+        }
+    }
+
+    fn entry_count(conn: &Connection) -> i64 {
+        conn.query_row("SELECT COUNT(*) FROM time_entries", [], |r| r.get(0)).unwrap()
+    }
+
+    #[test]
+    fn finish_timer_logs_entry_and_clears_timer() {
+        let conn = test_db();
+        let t = timer("Write tests", "running");
+        let id = insert_active(&conn, &t);
+
+        let entry_id = finish_timer(&conn, id, &entry_for(&t));
+
+        assert!(get_active_by_id(&conn, id).is_none());
+        let entry = get_entry_by_id(&conn, entry_id).unwrap();
+        assert_eq!(entry.name, "Write tests");
+        assert_eq!(entry.host, Some(hostname()), "the entry keeps the timer's host");
+
+        let id = insert_active_from(&conn, &t, Some("watch"));
+        assert_eq!(timer_host(&conn, id).as_deref(), Some("watch"));
+        let id = insert_active_from(&conn, &t, None);
+        let entry_id = finish_timer(&conn, id, &entry_for(&t));
+        assert_eq!(get_entry_by_id(&conn, entry_id).unwrap().host, None, "nor is one made up for a timer from elsewhere");
+    }
+
+    #[test]
+    fn active_time_since_a_cutoff_skips_what_came_before() {
+        let mut t = timer("Write tests", "running");
+        t.breaks.push(proto::Break { start_ts: 1_500, end_ts: 2_500, label: String::new(), interruption: false });
+        assert_eq!(active_secs_since(&t, i64::MIN, 4_000), 2_000);
+        // From 2_000 on: half the break and the 1_500s after it.
+        assert_eq!(active_secs_since(&t, 2_000, 4_000), 1_500);
+        assert_eq!(active_secs_since(&t, 5_000, 4_000), 0);
+    }
+
+    #[test]
+    fn parse_duration_refuses_overflowing_input() {
+        assert_eq!(parse_duration("1h30m"), Some(5_400));
+        assert_eq!(parse_duration("99999999999999999h"), None);
+        assert_eq!(parse_duration("9223372036854775807"), None);
+        assert_eq!(parse_duration("9223372036854775807s 1s"), None);
+    }
+
+    #[test]
+    fn finish_timer_rolls_back_when_clearing_fails() {
+        let conn = test_db();
+        let t = timer("Write tests", "running");
+        let id = insert_active(&conn, &t);
+        conn.execute_batch(
+            "CREATE TRIGGER fail_clear BEFORE DELETE ON active_timers
+             BEGIN SELECT RAISE(ABORT, 'simulated failure'); END;",
+        )
+        .unwrap();
+
+        let result = catch_unwind(AssertUnwindSafe(|| finish_timer(&conn, id, &entry_for(&t))));
+
+        assert!(result.is_err());
+        assert_eq!(entry_count(&conn), 0, "entry insert must be rolled back");
+        assert!(get_active_by_id(&conn, id).is_some(), "timer must survive");
+    }
+
+    #[test]
+    fn update_actives_rolls_back_when_second_write_fails() {
+        let conn = test_db();
+        let running_id = insert_active(&conn, &timer("A", "running"));
+        let paused_id = insert_active(&conn, &timer("B", "paused"));
+        conn.execute_batch(&format!(
+            "CREATE TRIGGER fail_resume BEFORE UPDATE ON active_timers WHEN NEW.id = {paused_id}
+             BEGIN SELECT RAISE(ABORT, 'simulated failure'); END;"
+        ))
+        .unwrap();
+
+        let mut pause = get_active_by_id(&conn, running_id).unwrap();
+        pause.state = "paused".into();
+        let mut resume = get_active_by_id(&conn, paused_id).unwrap();
+        resume.state = "running".into();
+        let result = catch_unwind(AssertUnwindSafe(|| update_actives(&conn, &[&pause, &resume])));
+
+        assert!(result.is_err());
+        assert_eq!(get_active_by_id(&conn, running_id).unwrap().state, "running");
+        assert_eq!(get_active_by_id(&conn, paused_id).unwrap().state, "paused");
+    }
+
+    #[test]
+    fn near_duplicates_need_matching_name_category_and_times() {
+        let t = timer("Write tests", "running");
+        let logged = entry_for(&t);
+        let mut again = entry_for(&t);
+        again.name = "write tests".into();
+        again.started_at += 60;
+        again.ended_at -= 90;
+        assert!(is_near_duplicate(&logged, &again));
+
+        again.ended_at += DUPLICATE_TOLERANCE_SECS + 120;
+        assert!(!is_near_duplicate(&logged, &again), "ends too far apart");
+
+        let mut other = entry_for(&t);
+        other.category = "meetings".into();
+        assert!(!is_near_duplicate(&logged, &other), "different category");
+    }
+
+    #[test]
+    fn filtering_by_several_categories_keeps_any_of_them() {
+        let conn = test_db();
+        for category in ["clientA-dev", "clientA-meetings", "clientB-dev"] {
+            let mut t = timer("Work", "paused");
+            t.category = category.into();
+            insert_entry(&conn, &entry_for(&t));
+        }
+        let categories = vec!["clienta-dev".to_string(), " clientA-meetings ".to_string()];
+
+        let found = filter_entries(&conn, &EntryFilter { categories: &categories, ..Default::default() });
+
+        let names: Vec<&str> = found.iter().map(|e| e.category.as_str()).collect();
+        assert_eq!(names, ["clientA-dev", "clientA-meetings"]);
+    }
+
+    #[test]
+    fn tag_and_name_matches_treat_wildcards_literally() {
+        let conn = test_db();
+        for (name, tag) in [("Done 100%", "q_1"), ("Done 1000", "qx1")] {
+            let mut e = entry_for(&timer(name, "paused"));
+            e.tags = vec![tag.into()];
+            insert_entry(&conn, &e);
+        }
+
+        let found = filter_entries(&conn, &EntryFilter { tag: Some("q_1"), ..Default::default() });
+        assert_eq!(found.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(), ["Done 100%"]);
+        let found = search_entries(&conn, "100%", None);
+        assert_eq!(found.iter().map(|e| e.name.as_str()).collect::<Vec<_>>(), ["Done 100%"]);
+    }
+
+    #[test]
+    fn todo_time_combines_finished_entries_and_running_timers() {
+        let conn = test_db();
+        let todo = add_todo(&conn, "ship it", 0);
+        let mut entry = entry_for(&timer("Earlier", "paused"));
+        entry.todo_id = Some(todo);
+        insert_entry(&conn, &entry);
+        let mut running = timer("Now", "running");
+        running.started_at = chrono::Local::now().timestamp() - 300;
+        running.todo_id = Some(todo);
+        insert_active(&conn, &running);
+
+        let (logged, active, total) = get_todo_combined_secs(&conn, todo);
+
+        assert_eq!(logged, 600, "only the finished entry is logged");
+        assert!((300..=302).contains(&active), "running timer counts separately, got {active}");
+        assert_eq!(total, logged + active);
+        assert_eq!(get_todo_total_secs(&conn, todo), 600);
+    }
+
+    #[test]
+    fn next_todo_is_the_oldest_open_one_without_a_timer() {
+        let conn = test_db();
+        let done = add_todo(&conn, "done", 100);
+        mark_todo_done(&conn, done);
+        let archived = add_todo(&conn, "archived", 200);
+        set_todo_archived(&conn, archived, true);
+        let busy = add_todo(&conn, "busy", 300);
+        let mut running = timer("Busy", "running");
+        running.todo_id = Some(busy);
+        insert_active(&conn, &running);
+        let newer = add_todo(&conn, "newer", 500);
+        let next = add_todo(&conn, "next", 400);
+
+        assert_eq!(next_todo(&conn).map(|t| t.id), Some(next));
+        assert_eq!(todo_category(&conn, next), None);
+
+        let mut entry = entry_for(&timer("Next", "paused"));
+        entry.todo_id = Some(next);
+        entry.category = "writing".into();
+        insert_entry(&conn, &entry);
+        assert_eq!(todo_category(&conn, next).as_deref(), Some("writing"));
+
+        mark_todo_done(&conn, next);
+        assert_eq!(next_todo(&conn).map(|t| t.id), Some(newer));
+    }
+
+    #[test]
+    fn names_and_categories_are_tidied_before_saving() {
+        assert_eq!(normalize_name("  Reply to   Bob\t "), "Reply to Bob");
+        assert_eq!(normalize_name("   "), "");
+        assert_eq!(normalize_category(" Email ", false), "Email");
+        assert_eq!(normalize_category(" Client  A ", true), "client a");
+
+        let conn = test_db();
+        let mut t = timer("Inbox", "paused");
+        t.category = "Email".into();
+        insert_entry(&conn, &entry_for(&t));
+        assert_eq!(canonical_category(&conn, "email  "), "Email", "matches the existing spelling");
+        set_setting(&conn, "lowercase_categories", "on");
+        assert_eq!(canonical_category(&conn, "Email"), "email");
+    }
+
+    #[test]
+    fn wiping_tracking_data_keeps_settings_and_records_deletions() {
+        let conn = test_db();
+        let todo = add_todo(&conn, "todo", 0);
+        let mut entry = entry_for(&timer("Done", "paused"));
+        entry.todo_id = Some(todo);
+        insert_entry(&conn, &entry);
+        insert_active(&conn, &timer("Running", "running"));
+        set_setting(&conn, "day_start_hour", "4");
+
+        wipe_tracking_data(&conn);
+
+        assert_eq!(entry_count(&conn), 0);
+        assert!(list_todos(&conn).is_empty() && get_all_active(&conn).is_empty());
+        assert_eq!(day_start_hour(&conn), 4);
+        let deletions: i64 = conn.query_row("SELECT COUNT(*) FROM deleted_records", [], |r| r.get(0)).unwrap();
+        assert_eq!(deletions, 3);
+    }
+
+    #[test]
+    fn interruptions_count_as_breaks_but_are_tallied_apart() {
+        let mut t = timer("Focus", "running");
+        t.pause(1_100, "lunch");
+        t.resume(1_200);
+
+        assert_eq!(t.interrupt(1_500, 60, "slack"), 60);
+        assert_eq!(t.interrupt(1_520, 300, ""), 20, "cut short at the previous break");
+        assert_eq!(t.interrupt(1_520, 60, ""), 0, "nothing left to record");
+
+        assert_eq!(interruption_stats(&t.breaks), (2, 80));
+        assert_eq!(total_break_secs(&t.breaks, 1_600), 100 + 80);
+        let mut breaks = t.breaks.clone();
+        coalesce_breaks(&mut breaks);
+        assert_eq!(breaks.len(), 3, "back-to-back interruptions aren't merged");
+    }
+
+    #[test]
+    fn old_schema_databases_upgrade_cleanly() {
+        // The first schema: a single-row `active_timer`, no links or sync columns.
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE active_timer (
+                id INTEGER PRIMARY KEY, name TEXT NOT NULL, category TEXT NOT NULL,
+                started_at INTEGER NOT NULL, state TEXT NOT NULL, breaks BLOB NOT NULL
+            );
+            CREATE TABLE time_entries (
+                id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL, category TEXT NOT NULL,
+                started_at INTEGER NOT NULL, ended_at INTEGER NOT NULL,
+                active_secs INTEGER NOT NULL, breaks BLOB NOT NULL
+            );
+            CREATE TABLE todos (
+                id INTEGER PRIMARY KEY AUTOINCREMENT, text TEXT NOT NULL,
+                done INTEGER NOT NULL DEFAULT 0, created_at INTEGER NOT NULL
+            );
+            INSERT INTO active_timer VALUES (1, 'Focus', 'work', 1000, 'running', x'');
+            INSERT INTO time_entries VALUES (1, 'Old', 'work', 100, 400, 300, x'');
+            INSERT INTO todos VALUES (1, 'Ship it', 1, 50);",
+        )
+        .unwrap();
+
+        assert_eq!(migrate(&conn).unwrap().len(), MIGRATIONS.len());
+        assert_eq!(schema_version(&conn), MIGRATIONS.len());
+        assert!(migrate(&conn).unwrap().is_empty(), "nothing left to do on the next open");
+
+        assert_eq!(get_all_active(&conn)[0].name, "Focus");
+        let entry = get_entry_by_id(&conn, 1).unwrap();
+        assert_eq!((entry.name.as_str(), entry.active_secs), ("Old", 300));
+        assert!(entry.tags.is_empty());
+        assert_eq!(get_todo_by_id(&conn, 1).unwrap().completed_at, Some(0));
+
+        // A fully migrated database from before versioning replays every step
+        // without tripping over the columns it already has.
+        let conn = test_db();
+        conn.execute_batch("DROP TABLE meta;").unwrap();
+        assert_eq!(migrate(&conn).unwrap().len(), MIGRATIONS.len());
+        assert_eq!(schema_version(&conn), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn a_newer_schema_is_an_error_not_an_exit() {
+        let conn = test_db();
+        conn.execute("UPDATE meta SET value = '999' WHERE key = 'schema_version'", []).unwrap();
+        assert!(migrate(&conn).unwrap_err().contains("schema version 999"));
+        assert_eq!(schema_version(&conn), 999, "left as it was");
+    }
+
+    #[test]
+    fn duration_formats() {
+        let secs = 3600 + 23 * 60 + 45;
+        assert_eq!(format_duration_as(secs, DurationFormat::Hms), "1h 23m 45s");
+        assert_eq!(format_duration_as(secs, DurationFormat::Clock), "1:23:45");
+        assert_eq!(format_duration_as(312, DurationFormat::Clock), "0:05:12");
+        assert_eq!(format_duration_as(5_040, DurationFormat::Decimal), "1.40");
+        assert_eq!(format_duration_as(secs, DurationFormat::Decimal), "1.40", "rounded to two places");
+        assert_eq!(format_duration_as(0, DurationFormat::Decimal), "0.00");
+    }
+
+    #[test]
+    fn humanize_ago_rounds_down_to_the_largest_unit() {
+        let now = 1_000_000;
+        let cases = [
+            (0, "just now"),
+            (-5, "just now"),
+            (1, "1s ago"),
+            (59, "59s ago"),
+            (60, "1m ago"),
+            (3_599, "59m ago"),
+            (3_600, "1h ago"),
+            (86_399, "23h ago"),
+            (86_400, "1 day ago"),
+            (172_799, "1 day ago"),
+            (172_800, "2 days ago"),
+            (400 * 86_400, "400 days ago"),
+        ];
+        for (ago, expected) in cases {
+            assert_eq!(humanize_ago(now - ago, now), expected, "{ago}s ago");
+        }
+    }
+
+    #[test]
+    fn archived_entries_leave_the_main_table_but_can_be_included() {
+        let conn = test_db();
+        for (name, started_at) in [("Old", 1_000), ("New", 5_000)] {
+            let mut t = timer(name, "paused");
+            t.started_at = started_at;
+            insert_entry(&conn, &entry_for(&t));
+        }
+
+        let old_id = filter_entries(&conn, &EntryFilter::default())[0].id;
+        assert_eq!(archive_entries_before(&conn, 5_000, true), [old_id]);
+        assert_eq!(entry_count(&conn), 2, "a dry run moves nothing");
+        assert_eq!(archive_entries_before(&conn, 5_000, false), [old_id]);
+
+        let names = |filter: &EntryFilter| -> Vec<String> {
+            filter_entries(&conn, filter).into_iter().map(|e| e.name).collect()
+        };
+        assert_eq!(names(&EntryFilter::default()), ["New"]);
+        assert_eq!(names(&EntryFilter { include_archived: true, ..Default::default() }), ["Old", "New"]);
+        assert!(archive_entries_before(&conn, 5_000, false).is_empty(), "nothing left to move");
+    }
+
+    #[test]
+    fn archived_entries_keep_their_todo_links() {
+        let conn = test_db();
+        let (a, b) = (add_todo(&conn, "A", 0), add_todo(&conn, "B", 0));
+        let mut ids = Vec::new();
+        for started_at in [1_000, 2_000] {
+            let mut t = timer("Linked", "paused");
+            t.started_at = started_at;
+            t.todo_id = Some(a);
+            let id = insert_entry(&conn, &entry_for(&t));
+            set_entry_todos(&conn, id, &[a, b]);
+            ids.push(id);
+        }
+
+        archive_entries_before(&conn, 1_500, false);
+        assert!(orphaned_todo_links(&conn).is_empty(), "the archived entry still owns its links");
+        assert_eq!(get_todo_total_secs(&conn, a), 300 + 300, "and still counts toward its todos");
+
+        conn.execute("DELETE FROM time_entries WHERE id = ?1", params![ids[1]]).unwrap();
+        assert_eq!(orphaned_todo_links(&conn), [("entry_todos", "entries", 2)]);
+        remove_orphaned_todo_links(&conn, "entry_todos");
+        assert!(orphaned_todo_links(&conn).is_empty());
+        let archived: i64 = conn
+            .query_row("SELECT COUNT(*) FROM entry_todos WHERE entry_id = ?1", params![ids[0]], |row| row.get(0))
+            .unwrap();
+        assert_eq!(archived, 2, "the fix leaves archived entries' links alone");
+    }
+
+    #[test]
+    fn overlaps_only_count_time_both_entries_were_running() {
+        let conn = test_db();
+        // B ran while A was paused for it.
+        let mut b = timer("B", "running");
+        b.started_at = 1_100;
+        let mut b = entry_for(&b);
+        b.ended_at = 1_500;
+        insert_entry(&conn, &b);
+
+        let mut a = timer("A", "running");
+        a.pause(1_100, "");
+        a.resume(1_500);
+        let a = a.to_entry(1_600, vec![]);
+        assert_eq!(active_overlap_secs(&a, &b), 0);
+        assert!(find_overlapping(&conn, &a, None).is_empty(), "pausing one timer for another isn't an overlap");
+
+        let mut c = timer("C", "running");
+        c.started_at = 1_050;
+        let mut c = entry_for(&c);
+        c.ended_at = 1_150;
+        assert_eq!(active_overlap_secs(&a, &c), 50);
+        assert_eq!(find_overlapping(&conn, &c, None).len(), 1);
+    }
+
+    #[test]
+    fn pausing_right_after_a_resume_reopens_the_last_break() {
+        let mut t = timer("Loop", "running");
+        t.pause(1_100, "");
+        t.resume(1_200);
+        t.pause(1_201, "lunch");
+        assert_eq!(t.breaks.len(), 1);
+        assert_eq!((t.breaks[0].end_ts, t.breaks[0].label.as_str()), (0, "lunch"));
+
+        t.resume(1_300);
+        t.pause(1_400, "");
+        assert_eq!(t.breaks.len(), 2, "a real gap keeps its own break");
+
+        let brk = |start_ts, end_ts| proto::Break { start_ts, end_ts, label: String::new(), interruption: false };
+        let mut breaks = vec![brk(10, 20), brk(20, 30), brk(31, 40), brk(50, 60)];
+        coalesce_breaks(&mut breaks);
+        let spans: Vec<_> = breaks.iter().map(|b| (b.start_ts, b.end_ts)).collect();
+        assert_eq!(spans, [(10, 40), (50, 60)]);
+    }
+
+    #[test]
+    fn marking_several_todos_done_reports_each_id() {
+        let conn = test_db();
+        let a = add_todo(&conn, "a", 0);
+        let b = add_todo(&conn, "b", 0);
+
+        assert_eq!(mark_todos_done(&conn, &[a, 99, b]), [(a, true), (99, false), (b, true)]);
+        assert!(get_todo_by_id(&conn, a).unwrap().done);
+        assert!(get_todo_by_id(&conn, b).unwrap().done);
+    }
+
+    #[test]
+    fn ids_of_deleted_rows_are_never_reused() {
+        let conn = test_db();
+        add_todo(&conn, "first", 0);
+        let last = add_todo(&conn, "second", 0);
+        assert!(remove_todo(&conn, last));
+        assert!(add_todo(&conn, "third", 0) > last);
+
+        let t = timer("Write tests", "paused");
+        insert_entry(&conn, &entry_for(&t));
+        let last = insert_entry(&conn, &entry_for(&t));
+        assert!(delete_entry(&conn, last));
+        assert!(insert_entry(&conn, &entry_for(&t)) > last);
+    }
+
+    #[test]
+    fn todo_link_counts_include_multi_todo_links() {
+        let conn = test_db();
+        let first = add_todo(&conn, "write tests", 0);
+        let second = add_todo(&conn, "docs", 0);
+        let t = timer("Write tests", "paused");
+        let mut e = entry_for(&t);
+        e.todo_id = Some(first);
+        let entry_id = insert_entry(&conn, &e);
+        set_entry_todos(&conn, entry_id, &[first, second]);
+        let mut running = timer("Docs", "running");
+        running.todo_id = Some(second);
+        insert_active(&conn, &running);
+
+        assert_eq!(count_todo_links(&conn, first), (1, 0));
+        assert_eq!(count_todo_links(&conn, second), (1, 1));
+    }
+
+    #[test]
+    fn removing_a_todo_promotes_the_next_link() {
+        let conn = test_db();
+        let gone = add_todo(&conn, "write tests", 0);
+        let kept = add_todo(&conn, "docs", 0);
+        let t = timer("Write tests", "paused");
+        let mut e = entry_for(&t);
+        e.todo_id = Some(gone);
+        let entry_id = insert_entry(&conn, &e);
+        set_entry_todos(&conn, entry_id, &[gone, kept]);
+        let mut running = timer("Write tests", "running");
+        running.todo_id = Some(gone);
+        let timer_id = insert_active(&conn, &running);
+
+        assert!(remove_todo(&conn, gone));
+
+        let e = get_entry_by_id(&conn, entry_id).unwrap();
+        assert_eq!(e.todo_id, Some(kept));
+        assert_eq!(entry_todos(&conn, &e), vec![kept]);
+        assert_eq!(get_active_by_id(&conn, timer_id).unwrap().todo_id, None);
+    }
+
+    #[test]
+    fn merging_todos_moves_their_time_and_links() {
+        let conn = test_db();
+        let from = add_todo(&conn, "write tests", 0);
+        let into = add_todo(&conn, "add tests", 0);
+        let other = add_todo(&conn, "docs", 0);
+        let t = timer("Write tests", "paused");
+
+        let mut only_from = entry_for(&t);
+        only_from.todo_id = Some(from);
+        insert_entry(&conn, &only_from);
+        let mut both = entry_for(&t);
+        both.todo_id = Some(from);
+        let both_id = insert_entry(&conn, &both);
+        set_entry_todos(&conn, both_id, &[from, into]);
+        let mut shared = entry_for(&t);
+        shared.todo_id = Some(from);
+        let shared_id = insert_entry(&conn, &shared);
+        set_entry_todos(&conn, shared_id, &[from, other]);
+        let mut running = timer("Write tests", "running");
+        running.todo_id = Some(from);
+        let timer_id = insert_active(&conn, &running);
+
+        assert!(merge_todos(&conn, from, into, "tests"));
+
+        assert!(get_todo_by_id(&conn, from).is_none());
+        assert_eq!(get_todo_by_id(&conn, into).unwrap().text, "tests");
+        assert_eq!(get_todo_total_secs(&conn, into), 600 + 600 + 300);
+        assert_eq!(get_todo_total_secs(&conn, other), 300);
+        let both = get_entry_by_id(&conn, both_id).unwrap();
+        assert_eq!(entry_todos(&conn, &both), vec![into]);
+        let timer = get_active_by_id(&conn, timer_id).unwrap();
+        assert_eq!(timer_todos(&conn, &timer), vec![into]);
+        assert!(!merge_todos(&conn, from, into, "tests"), "source is gone");
+    }
+
+    #[test]
+    fn merging_todos_leaves_unrelated_links_and_fixes_templates() {
+        let conn = test_db();
+        let from = add_todo(&conn, "write tests", 0);
+        let into = add_todo(&conn, "add tests", 0);
+        let other = add_todo(&conn, "docs", 0);
+        let t = timer("Docs", "paused");
+
+        // A lone link row (as sync can leave) on an entry the merge doesn't touch.
+        let mut unrelated = entry_for(&t);
+        unrelated.todo_id = Some(other);
+        let unrelated_id = insert_entry(&conn, &unrelated);
+        conn.execute("INSERT INTO entry_todos (entry_id, todo_id) VALUES (?1, ?2)", params![unrelated_id, other])
+            .unwrap();
+        save_template(&conn, &Template {
+            key: "t".into(),
+            name: "Tests".into(),
+            category: "dev".into(),
+            todo_ids: vec![from, other],
+        });
+
+        assert!(!merge_todos(&conn, from, into, "   "), "blank text");
+        assert!(get_todo_by_id(&conn, from).is_some());
+        assert!(merge_todos(&conn, from, into, "  all   tests "));
+
+        assert_eq!(get_todo_by_id(&conn, into).unwrap().text, "all tests");
+        let links: i64 = conn
+            .query_row("SELECT COUNT(*) FROM entry_todos WHERE entry_id = ?1", params![unrelated_id], |row| row.get(0))
+            .unwrap();
+        assert_eq!(links, 1);
+        assert_eq!(get_template(&conn, "t").unwrap().todo_ids, vec![into, other]);
+    }
+
+    #[test]
+    fn first_run_fires_once_and_only_on_an_empty_db() {
+        let conn = test_db();
+        assert!(first_run(&conn));
+        assert!(!first_run(&conn));
+
+        let used = test_db();
+        add_todo(&used, "existing", 0);
+        assert!(!first_run(&used));
+    }
+
+    #[test]
+    fn day_start_hour_moves_the_day_boundary() {
+        let jun12 = NaiveDate::from_ymd_opt(2024, 6, 12).unwrap();
+        let jun11 = jun12.pred_opt().unwrap();
+        let two_am = local_ts(jun12.and_hms_opt(2, 0, 0).unwrap());
+
+        assert_eq!(day_of(two_am, 0), jun12);
+        assert_eq!(day_of(two_am, 4), jun11);
+        assert_eq!(day_start_ts(jun12, 0), local_midnight(jun12));
+        assert_eq!(day_start_ts(jun12, 4), two_am + 2 * 3600);
+
+        let conn = test_db();
+        assert_eq!(day_start_hour(&conn), 0);
+        set_setting(&conn, "day_start_hour", "4");
+        assert_eq!(day_start_hour(&conn), 4);
+    }
+
+    #[test]
+    fn notes_follow_the_timer_into_its_entry_and_through_a_split() {
+        let conn = test_db();
+        let t = timer("Write tests", "running");
+        let id = insert_active(&conn, &t);
+        add_timer_note(&conn, id, &Note { ts: 1_100, text: "fixtures done".into() });
+        add_timer_note(&conn, id, &Note { ts: 1_500, text: "flaky one".into() });
+
+        let entry_id = finish_timer(&conn, id, &entry_for(&t));
+        assert!(timer_notes(&conn, id).is_empty());
+        let texts: Vec<String> = entry_notes(&conn, entry_id).into_iter().map(|n| n.text).collect();
+        assert_eq!(texts, ["fixtures done", "flaky one"]);
+
+        let (mut first, mut second) = (entry_for(&t), entry_for(&t));
+        first.ended_at = 1_300;
+        second.started_at = 1_300;
+        let ids = replace_entries(&conn, &[entry_id], &[first, second]);
+        assert_eq!(entry_notes(&conn, ids[0])[0].text, "fixtures done");
+        assert_eq!(entry_notes(&conn, ids[1])[0].text, "flaky one");
+    }
+
+    #[test]
+    fn sleep_gap_starts_at_the_later_of_heartbeat_and_last_break() {
+        let mut t = timer("Write tests", "running");
+        t.breaks.push(proto::Break { start_ts: 2_000, end_ts: 3_000, label: String::new(), interruption: false });
+        let hour = 3_600;
+
+        assert_eq!(t.sleep_start(1_500, 3_000 + 5 * hour, 4 * hour), Some(3_000));
+        assert_eq!(t.sleep_start(9_000, 9_000 + 5 * hour, 4 * hour), Some(9_000));
+        assert_eq!(t.sleep_start(9_000, 9_000 + 3 * hour, 4 * hour), None, "gap too short");
+        assert_eq!(t.sleep_start(9_000, 9_000 + 5 * hour, 0), None, "check turned off");
+        t.pause(9_000 + 5 * hour, "");
+        assert_eq!(t.sleep_start(9_000, 9_000 + 5 * hour, 4 * hour), None, "paused");
+    }
+
+    #[test]
+    fn category_colors_match_case_insensitively() {
+        let conn = test_db();
+        set_category_color(&conn, "Meetings", "blue");
+        set_category_color(&conn, "meetings ", "cyan");
+        assert_eq!(list_category_colors(&conn), vec![("Meetings".to_string(), "cyan".to_string())]);
+        assert!(clear_category_color(&conn, "MEETINGS"));
+        assert!(list_category_colors(&conn).is_empty());
+    }
+
+    #[test]
+    fn local_conversions_survive_dst_transitions() {
+        // Brazil-style rules as a POSIX TZ string (no tzdata needed): clocks
+        // jump 00:00 -> 01:00 on the first Sunday of October and fall back
+        // 00:00 -> 23:00 on the third Sunday of February. Setting TZ here
+        // would race the other tests, so the checks run in a child process
+        // with it set from the start.
+        const TZ: &str = "<-03>3<-02>,M10.1.0/0,M2.3.0/0";
+        if std::env::var("TZ").as_deref() != Ok(TZ) {
+            let status = std::process::Command::new(std::env::current_exe().unwrap())
+                .args(["--exact", "state::tests::local_conversions_survive_dst_transitions", "--quiet"])
+                .env("TZ", TZ)
+                .status()
+                .unwrap();
+            assert!(status.success(), "the checks failed under TZ={TZ}");
+            return;
+        }
+        let ts = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().timestamp();
+
+        // 2024-10-06 has no midnight; the day starts at 01:00.
+        let oct6 = NaiveDate::from_ymd_opt(2024, 10, 6).unwrap();
+        assert_eq!(local_midnight(oct6), ts("2024-10-06T01:00:00-02:00"));
+
+        // 23:30 on 2025-02-15 happens twice.
+        let first = ts("2025-02-15T23:30:00-02:00");
+        let second = ts("2025-02-15T23:30:00-03:00");
+        for t in [first, second] {
+            assert_eq!(local_time(t).format("%Y-%m-%d %H:%M").to_string(), "2025-02-15 23:30");
+        }
+        let feb15 = NaiveDate::from_ymd_opt(2025, 2, 15).unwrap();
+        assert_eq!(parse_clock_time("23:30", feb15), Some(first));
+    }
+
+    #[test]
+    fn start_pause_resume_stop_counts_only_active_time() {
+        let conn = test_db();
+        let id = insert_active(&conn, &timer("focus", "running"));
+
+        let mut t = get_active_by_id(&conn, id).unwrap();
+        t.pause(1_600, "coffee");
+        update_active(&conn, &t);
+
+        let mut t = get_active_by_id(&conn, id).unwrap();
+        assert_eq!(t.state, "paused");
+        assert_eq!(t.active_secs(1_900), 600);
+        t.resume(1_900);
+        update_active(&conn, &t);
+
+        let t = get_active_by_id(&conn, id).unwrap();
+        let entry_id = finish_timer(&conn, id, &t.to_entry(2_500, vec![]));
+        let e = get_entry_by_id(&conn, entry_id).unwrap();
+        assert_eq!(e.active_secs, 1_200);
+        assert_eq!(total_break_secs(&e.breaks, e.ended_at), 300);
+        assert_eq!(e.breaks[0].label, "coffee");
+        assert!(get_active_by_id(&conn, id).is_none());
+    }
+
+    #[test]
+    fn open_break_counts_until_stop() {
+        let mut t = timer("focus", "running");
+        t.pause(1_100, "");
+        t.resume(1_200);
+        t.pause(1_500, "");
+        let e = t.to_entry(2_000, vec![]);
+        assert_eq!(e.active_secs, 100 + 300);
+        assert_eq!(total_break_secs(&e.breaks, e.ended_at), 100 + 500);
+    }
+
+    #[test]
+    fn pausing_a_paused_timer_keeps_one_open_break() {
+        // `tl start` auto-pauses whatever it finds; doing that to a timer
+        // that's already paused must not open a second break.
+        let mut t = timer("focus", "running");
+        t.pause(1_100, "");
+        t.pause(1_300, "");
+        assert_eq!(t.breaks.len(), 1);
+        t.resume(1_500);
+        assert_eq!(total_break_secs(&t.breaks, 2_000), 400);
+        assert_eq!(t.active_secs(2_000), 600);
+    }
+
+    #[test]
+    fn resume_closes_stray_open_breaks() {
+        let open = |start_ts| proto::Break { start_ts, end_ts: 0, label: String::new(), interruption: false };
+        let mut t = timer("focus", "paused");
+        t.breaks = vec![open(1_100), open(1_300)];
+        t.resume(1_500);
+        assert!(t.breaks.iter().all(|b| b.end_ts != 0));
+        assert_eq!(total_break_secs(&t.breaks, 2_000), 400);
+    }
+
+    #[test]
+    fn pausing_a_stored_paused_timer_is_a_no_op() {
+        let conn = test_db();
+        let mut t = timer("focus", "running");
+        t.pause(1_100, "lunch");
+        let id = insert_active(&conn, &t);
+        let before = get_active_by_id(&conn, id).unwrap().breaks;
+
+        let mut t = get_active_by_id(&conn, id).unwrap();
+        assert!(!push_break_if_closed(&mut t.breaks, 1_300, ""));
+        t.pause(1_400, "again");
+        update_active(&conn, &t);
+
+        let after = get_active_by_id(&conn, id).unwrap();
+        assert_eq!(after.state, "paused");
+        assert_eq!(after.breaks, before);
+    }
+
+    #[test]
+    fn inserting_a_running_timer_pauses_the_old_one() {
+        let conn = test_db();
+        let old = insert_active(&conn, &timer("old", "running"));
+        let new = insert_active(&conn, &timer("new", "running"));
+
+        let old = get_active_by_id(&conn, old).unwrap();
+        assert_eq!(old.state, "paused");
+        assert_eq!(old.breaks.iter().filter(|b| b.end_ts == 0).count(), 1);
+        assert_eq!(get_running(&conn).unwrap().id, Some(new));
+    }
+
+    #[test]
+    fn resuming_pauses_the_running_timer_inside_a_transaction() {
+        let conn = test_db();
+        let running = insert_active(&conn, &timer("A", "running"));
+        let paused = insert_active(&conn, &timer("B", "paused"));
+
+        let tx = conn.unchecked_transaction().unwrap();
+        let mut b = get_active_by_id(&tx, paused).unwrap();
+        b.resume(2_000);
+        update_active(&tx, &b);
+        tx.commit().unwrap();
+
+        assert_eq!(get_active_by_id(&conn, running).unwrap().state, "paused");
+        assert_eq!(get_running(&conn).unwrap().id, Some(paused));
+    }
+
+    #[test]
+    fn get_running_picks_the_latest_of_several() {
+        let conn = test_db();
+        // Bypass the guard the way an old or corrupted database would.
+        for (name, modified) in [("A", 10), ("B", 30), ("C", 20)] {
+            conn.execute(
+                "INSERT INTO active_timers (name, category, started_at, state, breaks, last_modified)
+                 VALUES (?1, 'dev', 1000, 'running', x'', ?2)",
+                params![name, modified],
+            )
+            .unwrap();
+        }
+        assert_eq!(get_running(&conn).unwrap().name, "B");
+    }
+
+    #[test]
+    fn stopping_after_the_clock_went_back_logs_a_sane_flagged_entry() {
+        let brk = |start_ts, end_ts| proto::Break { start_ts, end_ts, label: String::new(), interruption: false };
+
+        // Stopped "before" it started: zero-length, never inverted.
+        let e = timer("focus", "running").to_entry(900, vec![]);
+        assert_eq!((e.ended_at, e.active_secs), (1_000, 0));
+        assert_eq!(e.tags, [CLOCK_SKEW_TAG]);
+
+        // Stopped inside a break recorded before the jump.
+        let mut t = timer("focus", "running");
+        t.breaks = vec![brk(1_100, 1_500)];
+        let e = t.to_entry(1_300, vec!["billable".into()]);
+        assert_eq!((e.ended_at, e.active_secs), (1_500, 100));
+        assert_eq!(e.tags, ["billable", CLOCK_SKEW_TAG]);
+
+        // A break that "started" before the timer and ended before it began.
+        let mut t = timer("focus", "running");
+        t.breaks = vec![brk(900, 950)];
+        let e = t.to_entry(1_200, vec![]);
+        assert_eq!(e.breaks, [brk(1_000, 1_000)]);
+        assert_eq!(e.active_secs, 200);
+        assert_eq!(e.tags, [CLOCK_SKEW_TAG]);
+
+        // A normal stop is left alone.
+        let mut t = timer("focus", "running");
+        t.breaks = vec![brk(1_100, 1_200)];
+        let e = t.to_entry(1_600, vec![]);
+        assert_eq!((e.ended_at, e.active_secs), (1_600, 500));
+        assert!(e.tags.is_empty());
+    }
+}
@@ -8,11 +8,11 @@ use crate::state;
 use crate::state::proto;
 
 fn periods_to_breaks(periods: &[BreakPeriod]) -> Vec<proto::Break> {
-    periods.iter().map(|p| proto::Break { start_ts: p.start_ts, end_ts: p.end_ts }).collect()
+    periods.iter().map(|p| proto::Break { start_ts: p.start_ts, end_ts: p.end_ts, label: p.label.clone(), interruption: p.interruption }).collect()
 }
 
 fn breaks_to_periods(breaks: &[proto::Break]) -> Vec<BreakPeriod> {
-    breaks.iter().map(|b| BreakPeriod { start_ts: b.start_ts, end_ts: b.end_ts }).collect()
+    breaks.iter().map(|b| BreakPeriod { start_ts: b.start_ts, end_ts: b.end_ts, label: b.label.clone(), interruption: b.interruption }).collect()
 }
 
 pub async fn handle_sync(
@@ -30,21 +30,21 @@ pub async fn handle_sync(
     // --- Apply Watch timer changes ---
     for wt in &req.changes.active_timers {
         if let Some(server_id) = wt.server_id {
-            if let Some(existing) = state::get_active_by_id(&conn, server_id) {
-                if wt.last_modified > existing.last_modified {
-                    let timer = state::ActiveTimer {
-                        id: Some(server_id),
-                        name: wt.name.clone(),
-                        category: wt.category.clone(),
-                        started_at: wt.started_at,
-                        state: wt.state.clone(),
-                        breaks: periods_to_breaks(&wt.breaks),
-                        todo_id: wt.todo_id,
-                        last_modified: wt.last_modified,
-                    };
-                    state::upsert_active_timer(&conn, server_id, &timer);
-                    updated_server_ids.push(("active_timers".into(), server_id));
-                }
+            if let Some(existing) = state::get_active_by_id(&conn, server_id)
+                && wt.last_modified > existing.last_modified
+            {
+                let timer = state::ActiveTimer {
+                    id: Some(server_id),
+                    name: wt.name.clone(),
+                    category: wt.category.clone(),
+                    started_at: wt.started_at,
+                    state: wt.state.clone(),
+                    breaks: periods_to_breaks(&wt.breaks),
+                    todo_id: wt.todo_id,
+                    last_modified: wt.last_modified,
+                };
+                state::upsert_active_timer(&conn, server_id, &timer);
+                updated_server_ids.push(("active_timers".into(), server_id));
             }
         } else {
             let timer = state::ActiveTimer {
@@ -57,7 +57,7 @@ pub async fn handle_sync(
                 todo_id: wt.todo_id,
                 last_modified: wt.last_modified,
             };
-            let new_id = state::insert_active(&conn, &timer);
+            let new_id = state::insert_active_from(&conn, &timer, None);
             id_mappings.push(IdMapping {
                 table_name: "active_timers".into(),
                 local_id: wt.local_id.clone(),
@@ -94,7 +94,7 @@ pub async fn handle_sync(
                     todo_id: t.todo_id,
                     last_modified: 0,
                 };
-                paused.breaks.push(proto::Break { start_ts: now_ts, end_ts: 0 });
+                paused.pause(now_ts, "");
                 state::update_active(&conn, &paused);
             }
         }
@@ -103,22 +103,25 @@ pub async fn handle_sync(
     // --- Apply Watch entry changes ---
     for we in &req.changes.time_entries {
         if let Some(server_id) = we.server_id {
-            if let Some(existing) = state::get_entry_by_id(&conn, server_id) {
-                if we.last_modified > existing.last_modified {
-                    let entry = state::TimeEntry {
-                        id: server_id,
-                        name: we.name.clone(),
-                        category: we.category.clone(),
-                        started_at: we.started_at,
-                        ended_at: we.ended_at,
-                        active_secs: we.active_secs,
-                        breaks: periods_to_breaks(&we.breaks),
-                        todo_id: we.todo_id,
-                        last_modified: we.last_modified,
-                    };
-                    state::upsert_entry(&conn, server_id, &entry);
-                    updated_server_ids.push(("time_entries".into(), server_id));
-                }
+            if let Some(existing) = state::get_entry_by_id(&conn, server_id)
+                && we.last_modified > existing.last_modified
+            {
+                let entry = state::TimeEntry {
+                    id: server_id,
+                    name: we.name.clone(),
+                    category: we.category.clone(),
+                    started_at: we.started_at,
+                    ended_at: we.ended_at,
+                    active_secs: we.active_secs,
+                    breaks: periods_to_breaks(&we.breaks),
+                    todo_id: we.todo_id,
+                    last_modified: we.last_modified,
+                    tags: vec![],
+                    utc_offset: existing.utc_offset,
+                    host: existing.host,
+                };
+                state::upsert_entry(&conn, server_id, &entry);
+                updated_server_ids.push(("time_entries".into(), server_id));
             }
         } else {
             let entry = state::TimeEntry {
@@ -131,6 +134,9 @@ pub async fn handle_sync(
                 breaks: periods_to_breaks(&we.breaks),
                 todo_id: we.todo_id,
                 last_modified: we.last_modified,
+                tags: vec![],
+                utc_offset: Some(state::local_offset(we.started_at)),
+                host: None,
             };
             state::insert_entry(&conn, &entry);
             let last = state::get_last_entry(&conn).unwrap();
@@ -146,18 +152,21 @@ pub async fn handle_sync(
     // --- Apply Watch todo changes ---
     for wt in &req.changes.todos {
         if let Some(server_id) = wt.server_id {
-            if let Some(existing) = state::get_todo_by_id(&conn, server_id) {
-                if wt.last_modified > existing.last_modified {
-                    let todo = state::TodoItem {
-                        id: server_id,
-                        text: wt.text.clone(),
-                        done: wt.done,
-                        created_at: wt.created_at,
-                        last_modified: wt.last_modified,
-                    };
-                    state::upsert_todo(&conn, server_id, &todo);
-                    updated_server_ids.push(("todos".into(), server_id));
-                }
+            if let Some(existing) = state::get_todo_by_id(&conn, server_id)
+                && wt.last_modified > existing.last_modified
+            {
+                let todo = state::TodoItem {
+                    id: server_id,
+                    text: wt.text.clone(),
+                    done: wt.done,
+                    created_at: wt.created_at,
+                    last_modified: wt.last_modified,
+                    estimate_secs: None,
+                    archived: false,
+                    completed_at: None,
+                };
+                state::upsert_todo(&conn, server_id, &todo);
+                updated_server_ids.push(("todos".into(), server_id));
             }
         } else {
             let id = state::add_todo(&conn, &wt.text, wt.created_at);
@@ -177,24 +186,24 @@ pub async fn handle_sync(
     for del in &req.changes.deletions {
         match del.table_name.as_str() {
             "active_timers" => {
-                if let Some(existing) = state::get_active_by_id(&conn, del.record_id) {
-                    if del.deleted_at > existing.last_modified {
-                        state::clear_active(&conn, del.record_id);
-                    }
+                if let Some(existing) = state::get_active_by_id(&conn, del.record_id)
+                    && del.deleted_at > existing.last_modified
+                {
+                    state::clear_active(&conn, del.record_id);
                 }
             }
             "time_entries" => {
-                if let Some(existing) = state::get_entry_by_id(&conn, del.record_id) {
-                    if del.deleted_at > existing.last_modified {
-                        state::delete_entry(&conn, del.record_id);
-                    }
+                if let Some(existing) = state::get_entry_by_id(&conn, del.record_id)
+                    && del.deleted_at > existing.last_modified
+                {
+                    state::delete_entry(&conn, del.record_id);
                 }
             }
             "todos" => {
-                if let Some(existing) = state::get_todo_by_id(&conn, del.record_id) {
-                    if del.deleted_at > existing.last_modified {
-                        state::remove_todo(&conn, del.record_id);
-                    }
+                if let Some(existing) = state::get_todo_by_id(&conn, del.record_id)
+                    && del.deleted_at > existing.last_modified
+                {
+                    state::remove_todo(&conn, del.record_id);
                 }
             }
             _ => {}
@@ -0,0 +1,61 @@
+//! `tl template`: saved name/category/todo combinations that `tl start
+//! --template <key>` (or `tl t <key>`) starts without asking anything.
+
+use rusqlite::Connection;
+
+use crate::color;
+use crate::state::*;
+use crate::timer::{require_name, truncate};
+
+pub fn add(conn: &Connection, key: &str, name: &str, category: &str, todo_ids: &[u32]) {
+    let key = key.trim();
+    let name = name.trim();
+    if key.is_empty() || name.is_empty() || category.trim().is_empty() {
+        eprintln!("Template key, name and category must not be empty.");
+        std::process::exit(1);
+    }
+    if let Some(id) = todo_ids.iter().find(|&&id| get_todo_by_id(conn, id).is_none()) {
+        eprintln!("Todo #{id} not found.");
+        std::process::exit(1);
+    }
+    let replaced = get_template(conn, key).is_some();
+    let template = Template {
+        key: key.to_string(),
+        name: require_name(name, "Name"),
+        category: canonical_category(conn, &require_name(category, "Category")),
+        todo_ids: todo_ids.to_vec(),
+    };
+    save_template(conn, &template);
+    println!(
+        "{} template \"{key}\": \"{}\" [{}]. Start it with `tl t {key}`.",
+        if replaced { "Updated" } else { "Saved" },
+        template.name,
+        template.category,
+    );
+}
+
+pub fn list(conn: &Connection) {
+    let templates = list_templates(conn);
+    if templates.is_empty() {
+        println!("No templates. Save one with `tl template add standup --name Standup --category meetings`.");
+        return;
+    }
+    let palette = color::Palette::load(conn);
+    for t in templates {
+        let todos = if t.todo_ids.is_empty() {
+            String::new()
+        } else {
+            format!("  todos: {}", t.todo_ids.iter().map(|id| format!("#{id}")).collect::<Vec<_>>().join(", "))
+        };
+        println!("  {:<12} {:<30} [{}]{todos}", truncate(&t.key, 12), truncate(&t.name, 30), palette.category(&t.category));
+    }
+}
+
+pub fn rm(conn: &Connection, key: &str) {
+    if remove_template(conn, key.trim()) {
+        println!("Template \"{}\" removed.", key.trim());
+    } else {
+        eprintln!("No template \"{}\".", key.trim());
+        std::process::exit(1);
+    }
+}
@@ -1,98 +1,217 @@
-use chrono::{Local, TimeZone};
-use dialoguer::{Confirm, Input, Select};
+use std::io::IsTerminal;
+
+use chrono::Local;
+use dialoguer::{Confirm, FuzzySelect, Input, Select};
 use rusqlite::Connection;
 
+use crate::budget;
+use crate::color;
+use crate::duration::{format_duration, format_duration_prec};
+use crate::goal;
+use crate::idle;
+use crate::notify;
+use crate::prompt::{Answer, Choice};
 use crate::state::*;
 
-pub fn start(conn: &Connection) {
+/// Getting-started notes, shown once on a brand-new database. Goes to stderr
+/// so it never ends up in captured output like `$(tl query total)`.
+pub fn welcome() {
+    eprintln!(
+        "Welcome to tl! A quick start:
+  tl start        Start a timer (asks for a name and category)
+  tl pause        Take a break; `tl resume` picks it back up
+  tl stop         Stop the timer and add it to your log
+  tl todo add     Keep a todo list and link timers to it
+  tl log --today  See what you did today
+Run `tl --help` for everything else.
+"
+    );
+}
+
+/// Resolve `--at <time>` / `--ago <duration>` into a timestamp, or `None` when
+/// neither was given. Exits if the value can't be parsed or is in the future.
+pub fn past_ts(at: Option<&str>, ago: Option<&str>) -> Option<i64> {
+    let now = Local::now();
+    let ts = match (at, ago) {
+        (Some(at), _) => match parse_clock_time(at, now.date_naive()) {
+            Some(ts) => ts,
+            None => {
+                eprintln!("Invalid time \"{at}\" (try 09:15 or 2024-05-01 09:15).");
+                std::process::exit(1);
+            }
+        },
+        (None, Some(ago)) => match parse_duration(ago) {
+            Some(secs) => now.timestamp() - secs,
+            None => {
+                eprintln!("Invalid duration \"{ago}\" (try 10m or 1h30m).");
+                std::process::exit(1);
+            }
+        },
+        (None, None) => return None,
+    };
+    if ts > now.timestamp() {
+        eprintln!("That time is in the future.");
+        std::process::exit(1);
+    }
+    Some(ts)
+}
+
+/// Start a new timer. `pomodoro` carries (work, break) minutes when the timer
+/// should be tracked in Pomodoro intervals; `started_at` backdates the start.
+/// With a `template` key nothing is asked: the saved name, category and todos
+/// are used and a running timer is paused.
+pub fn start(
+    conn: &Connection,
+    pomodoro: Option<(u32, u32)>,
+    started_at: Option<i64>,
+    template: Option<&str>,
+    next: bool,
+    verbosity: Verbosity,
+) {
+    // Name, category (if known) and todos decided without prompting: from a
+    // template, or from the next todo (`--next`).
+    let preset: Option<(String, Option<String>, Vec<u32>)> = if let Some(key) = template {
+        let Some(t) = get_template(conn, key) else {
+            eprintln!("No template \"{key}\". See `tl template list`.");
+            std::process::exit(1);
+        };
+        // Skip todos deleted since the template was saved.
+        let todo_ids = t.todo_ids.into_iter().filter(|&id| get_todo_by_id(conn, id).is_some()).collect();
+        Some((t.name, Some(t.category), todo_ids))
+    } else if next {
+        let Some(todo) = next_todo(conn) else {
+            eprintln!("Nothing to start — no open todos without a timer. Add one with `tl todo add <text>`.");
+            std::process::exit(1);
+        };
+        Some((todo.text, todo_category(conn, todo.id), vec![todo.id]))
+    } else {
+        None
+    };
+
+    let mut to_pause = None;
     if let Some(running) = get_running(conn) {
         let now_ts = Local::now().timestamp();
         let elapsed = now_ts - running.started_at;
         let break_secs = total_break_secs(&running.breaks, now_ts);
         let active_secs = (elapsed - break_secs).max(0);
 
-        println!(
-            "Running: \"{}\" [{}] — active: {}",
-            running.name,
-            running.category,
-            format_duration(active_secs),
-        );
+        if !verbosity.quiet() {
+            println!(
+                "Running: \"{}\" [{}] — active: {}",
+                running.name,
+                color::category(conn, &running.category),
+                format_duration(active_secs),
+            );
+        }
 
-        let confirm = Confirm::new()
-            .with_prompt("Pause current timer and start a new one?")
-            .default(false)
-            .interact()
-            .unwrap();
+        let confirm = preset.is_some()
+            || Confirm::new()
+                .with_prompt("Pause current timer and start a new one?")
+                .default(false)
+                .interact_opt()
+                .chosen();
 
         if !confirm {
             return;
         }
-
-        // Pause the running timer
-        let mut paused = running;
-        paused.state = "paused".into();
-        paused.breaks.push(proto::Break {
-            start_ts: now_ts,
-            end_ts: 0,
-        });
-        update_active(conn, &paused);
-
-        println!("Paused \"{}\".", paused.name);
+        to_pause = Some((running, now_ts));
     }
 
-    // Offer to link a todo first — if linked, use the todo text as the name
-    let mut todo_id: Option<u32> = None;
-    let mut name = String::new();
-    let open_todos: Vec<_> = list_todos(conn).into_iter().filter(|t| !t.done).collect();
-    if !open_todos.is_empty() {
-        let mut items: Vec<String> = open_todos
-            .iter()
-            .map(|t| format!("#{} {}", t.id, t.text))
-            .collect();
-        items.push("None".into());
+    let (name, category, todo_ids) = match preset {
+        Some((name, Some(category), todo_ids)) => (name, category, todo_ids),
+        Some((name, None, todo_ids)) => {
+            if !verbosity.quiet() {
+                println!("Starting \"{name}\".");
+            }
+            (name, prompt_category(conn), todo_ids)
+        }
+        None => prompt_details(conn),
+    };
 
-        let selection = Select::new()
-            .with_prompt("Link to a todo?")
-            .items(&items)
-            .default(items.len() - 1)
-            .interact()
-            .unwrap();
+    // Only now that every prompt is answered, so backing out of one leaves
+    // the running timer alone.
+    if let Some((mut paused, now_ts)) = to_pause {
+        paused.pause(now_ts, "");
+        update_active(conn, &paused);
 
-        if selection < open_todos.len() {
-            todo_id = Some(open_todos[selection].id);
-            name = open_todos[selection].text.clone();
+        if !verbosity.quiet() {
+            println!("Paused \"{}\".", paused.name);
         }
     }
+    let todo_id = todo_ids.first().copied();
 
-    if name.is_empty() {
-        name = Input::new()
-            .with_prompt("Activity name")
-            .interact_text()
-            .unwrap();
-    }
-
-    let category: String = Input::new()
-        .with_prompt("Category")
-        .interact_text()
-        .unwrap();
-
-    let now = Local::now();
+    let started = started_at
+        .map_or_else(Local::now, local_time);
     let timer = ActiveTimer {
         id: None,
         name: name.clone(),
         category: category.clone(),
-        started_at: now.timestamp(),
+        started_at: started.timestamp(),
         state: "running".into(),
         breaks: vec![],
         todo_id,
         last_modified: 0,
     };
-    insert_active(conn, &timer);
+    let id = insert_active(conn, &timer);
+    set_timer_todos(conn, id, &todo_ids);
+    if let Some((work, brk)) = pomodoro {
+        set_pomodoro(conn, id, i64::from(work) * 60, i64::from(brk) * 60);
+    }
+
+    if verbosity.quiet() {
+        println!("{id}");
+        return;
+    }
+    println!(
+        "Started \"{name}\" [{}] at {}",
+        color::category(conn, &category),
+        format_time(conn, started.timestamp()),
+    );
+    if let Some((work, brk)) = pomodoro {
+        println!("Pomodoro: {work}m work / {brk}m break. Run `tl pomodoro` for reminders.");
+    }
+}
+
+/// Ask for the new timer's todos, name and category. Linking exactly one
+/// todo uses its text as the name; with several, their texts joined are
+/// suggested.
+fn prompt_details(conn: &Connection) -> (String, String, Vec<u32>) {
+    let mut name = String::new();
+    let open_todos: Vec<_> = list_active_todos(conn).into_iter().filter(|t| !t.done).collect();
+    let picked = pick_todos(conn, open_todos);
+    let todo_ids: Vec<u32> = picked.iter().map(|t| t.id).collect();
+    match picked.as_slice() {
+        [] => {}
+        [one] => name = one.text.clone(),
+        many => {
+            name = prompt_name("Activity name", Some(many.iter().map(|t| t.text.as_str()).collect::<Vec<_>>().join(" + ")));
+        }
+    }
 
-    println!("Started \"{name}\" [{category}] at {}", now.format("%H:%M:%S"));
+    if name.is_empty() {
+        name = prompt_name("Activity name", None);
+    }
+
+    let category = prompt_category(conn);
+    (name, category, todo_ids)
 }
 
-pub fn stop(conn: &Connection) {
+/// Stop the running timer and log it. `ended_at` overrides the stop time for
+/// timers that were left running too long; `force` skips the overlap prompt.
+/// A timer over `max-duration` is capped with `cap`; otherwise that's asked,
+/// except under `--quiet`, `--force` or without a terminal, where it's logged
+/// uncapped with a warning. `complete_todos` answers "Mark todo as done?" up
+/// front (`--done` / `--keep`); without it the question is asked, except
+/// under `--quiet` or without a terminal, where the todos are left open.
+pub fn stop(
+    conn: &Connection,
+    tags: &[String],
+    ended_at: Option<i64>,
+    force: bool,
+    cap: bool,
+    complete_todos: Option<bool>,
+    verbosity: Verbosity,
+) {
     let timer = match get_running(conn) {
         Some(t) => t,
         None => {
@@ -101,51 +220,146 @@ pub fn stop(conn: &Connection) {
         }
     };
 
-    let now_ts = Local::now().timestamp();
+    let mut now_ts = ended_at.unwrap_or_else(|| Local::now().timestamp());
+    if ended_at.is_none()
+        && let Some(max) = max_duration_secs(conn)
+    {
+        let active = timer.active_secs(now_ts);
+        if active > max {
+            let cap_ts = time_at_active(timer.started_at, &timer.breaks, max);
+            let over = format!(
+                "\"{}\" has {} of active time, over your max of {}",
+                timer.name,
+                format_duration(active),
+                format_duration(max),
+            );
+            if cap {
+                now_ts = cap_ts;
+            } else if !verbosity.quiet() && !force && std::io::stdin().is_terminal() {
+                println!("{over}.");
+                let confirm = Confirm::new()
+                    .with_prompt(format!(
+                        "Cap the entry at {} (ending {} {})?",
+                        format_duration(max),
+                        format_date(cap_ts),
+                        format_hm(conn, cap_ts),
+                    ))
+                    .default(true)
+                    .interact_opt()
+                    .chosen();
+                if confirm {
+                    now_ts = cap_ts;
+                }
+            } else {
+                eprintln!("Warning: {over}; logging it uncapped (--cap caps it).");
+            }
+        }
+    }
+    // A clock that went backwards is handled (and flagged) by `to_entry`, and
+    // stopping in the same second as the start logs a zero-length entry;
+    // these checks are for times given with --at / --ago.
+    if ended_at.is_some() && now_ts <= timer.started_at {
+        eprintln!("Stop time must be after the timer started.");
+        std::process::exit(1);
+    }
+    if let Some(last) = timer.breaks.last()
+        && ended_at.is_some()
+        && now_ts < last.start_ts.max(last.end_ts)
+    {
+        eprintln!("Stop time must be after the last break.");
+        std::process::exit(1);
+    }
     let timer_id = timer.id.unwrap();
 
-    let breaks = timer.breaks;
-    let elapsed = now_ts - timer.started_at;
-    let break_secs = total_break_secs(&breaks, now_ts);
-    let active_secs = (elapsed - break_secs).max(0);
+    let todos = timer_todos(conn, &timer);
+    let tags = normalize_tags(tags);
+    let entry = timer.to_entry(now_ts, tags.clone());
+    let active_secs = entry.active_secs;
+    let break_secs = total_break_secs(&entry.breaks, entry.ended_at);
+    if entry.tags.len() > tags.len() {
+        eprintln!(
+            "Warning: the system clock went backwards while this timer ran. The entry ends at {} \
+             instead and is tagged #{CLOCK_SKEW_TAG}; check it with `tl log show`.",
+            format_datetime(conn, entry.ended_at),
+        );
+    }
 
-    let entry = TimeEntry {
-        id: 0,
-        name: timer.name.clone(),
-        category: timer.category.clone(),
-        started_at: timer.started_at,
-        ended_at: now_ts,
-        active_secs,
-        breaks,
-        todo_id: timer.todo_id,
-        last_modified: 0,
-    };
+    let overlapping = find_overlapping(conn, &entry, None);
+    if !force && !confirm_overlaps(conn, &entry, &overlapping) {
+        return;
+    }
 
-    insert_entry(conn, &entry);
-    clear_active(conn, timer_id);
+    let pomodoro = get_pomodoro(conn, timer_id);
+    // The entry is saved before anything else happens, so nothing after this
+    // (notifications, completing todos) can lose the logged time.
+    let entry_id = finish_timer(conn, timer_id, &entry);
 
-    println!(
-        "Stopped \"{}\" [{}] — active: {}, breaks: {}",
-        timer.name,
-        timer.category,
-        format_duration(active_secs),
-        format_duration(break_secs),
+    if verbosity.quiet() {
+        println!("{entry_id}");
+    } else {
+        println!(
+            "Stopped \"{}\" [{}] — {}–{}, elapsed: {} = active: {} + breaks: {}",
+            timer.name,
+            color::category(conn, &timer.category),
+            format_time(conn, entry.started_at),
+            format_time(conn, entry.ended_at),
+            format_duration(entry.ended_at - entry.started_at),
+            format_duration(active_secs),
+            format_duration(break_secs),
+        );
+        println!("Logged as entry #{entry_id}.");
+        let (interruptions, interrupted_secs) = interruption_stats(&entry.breaks);
+        if interruptions > 0 {
+            println!("Interruptions: {interruptions}, {}", format_duration(interrupted_secs));
+        }
+        if !entry.tags.is_empty() {
+            println!("Tags: {}", format_tags(&entry.tags));
+        }
+        if let Some(p) = pomodoro {
+            println!("Completed pomodoros: {}", completed_pomodoros(active_secs, p.work_secs));
+        }
+    }
+    notify::send(
+        conn,
+        "Timer stopped",
+        &format!("\"{}\" — {} logged", timer.name, format_duration(active_secs)),
     );
-
-    if let Some(tid) = timer.todo_id {
-        let confirm = Confirm::new()
-            .with_prompt(format!("Mark todo #{tid} as done?"))
-            .default(false)
-            .interact()
-            .unwrap();
-        if confirm {
-            mark_todo_done(conn, tid);
-            println!("Marked todo #{tid} as done.");
+    notify::check_goal(conn);
+
+    let ask = !verbosity.quiet() && std::io::stdin().is_terminal();
+    for tid in todos {
+        let confirm = complete_todos.unwrap_or_else(|| {
+            ask && Confirm::new()
+                .with_prompt(format!("Mark todo #{tid} as done?"))
+                .default(false)
+                .interact_opt()
+                .answer()
+                .unwrap_or(false)
+        });
+        if !confirm {
+            continue;
+        }
+        if mark_todo_done(conn, tid) {
+            if !verbosity.quiet() {
+                println!("Marked todo #{tid} as done.");
+            }
+        } else {
+            eprintln!("Couldn't mark todo #{tid} as done (was it deleted?); the time is still logged as entry #{entry_id}.");
         }
     }
 }
 
-pub fn pause(conn: &Connection) {
+/// Pause the running timer. Without `--label`, asks for an optional reason
+/// (lunch, meeting, ...); leaving it blank records an unlabelled break, as
+/// does running under `--quiet` or without a terminal.
+pub fn pause(conn: &Connection, label: Option<String>, remind: Option<&str>, verbosity: Verbosity) {
+    let remind_secs = remind.map(|d| match parse_duration(d) {
+        Some(s) if s > 0 => s,
+        _ => {
+            eprintln!("Invalid duration \"{d}\" (try 5m or 1h).");
+            std::process::exit(1);
+        }
+    });
     let mut timer = match get_running(conn) {
         Some(t) => t,
         None => {
@@ -155,18 +369,116 @@ pub fn pause(conn: &Connection) {
     };
 
     let now_ts = Local::now().timestamp();
-    timer.state = "paused".into();
-    timer.breaks.push(proto::Break {
-        start_ts: now_ts,
-        end_ts: 0,
+    let ask = !verbosity.quiet() && std::io::stdin().is_terminal();
+    let label = label.unwrap_or_else(|| {
+        if !ask {
+            return String::new();
+        }
+        Input::new()
+            .with_prompt("Reason for the break (optional)")
+            .allow_empty(true)
+            .interact_text()
+            .answer()
     });
+    timer.pause(now_ts, &label);
     update_active(conn, &timer);
+    if timer.breaks.len() > MAX_BREAKS {
+        eprintln!(
+            "Warning: \"{}\" has {} breaks — is something pausing and resuming it in a loop?",
+            timer.name,
+            timer.breaks.len(),
+        );
+    }
 
-    let now = Local::now();
-    println!("Paused \"{}\" at {}", timer.name, now.format("%H:%M:%S"));
+    if verbosity.quiet() {
+        println!("{}", timer.id.unwrap());
+    } else {
+        println!("Paused \"{}\" at {}", timer.name, format_time(conn, now_ts));
+    }
+
+    if let Some(secs) = remind_secs {
+        wait_for_break(conn, timer.id.unwrap(), now_ts, secs, verbosity);
+    }
+}
+
+/// Record an interruption on the running timer: a break of `duration`
+/// ending now, kept apart from pauses so it can be counted on its own.
+pub fn interrupt(conn: &Connection, duration: &str, reason: Option<String>, verbosity: Verbosity) {
+    let secs = match parse_duration(duration) {
+        Some(s) if s > 0 => s,
+        _ => {
+            eprintln!("Invalid duration \"{duration}\" (try 5m or 1h).");
+            std::process::exit(1);
+        }
+    };
+    let mut timer = match get_running(conn) {
+        Some(t) => t,
+        None => {
+            eprintln!("No running timer.");
+            std::process::exit(1);
+        }
+    };
+
+    let now_ts = Local::now().timestamp();
+    let recorded = timer.interrupt(now_ts, secs, reason.as_deref().unwrap_or(""));
+    if recorded == 0 {
+        eprintln!("Nothing to record: \"{}\" hasn't run since it started, resumed or was last interrupted.", timer.name);
+        std::process::exit(1);
+    }
+    update_active(conn, &timer);
+
+    if verbosity.quiet() {
+        println!("{}", timer.id.unwrap());
+        return;
+    }
+    if recorded < secs {
+        println!(
+            "Only {} of \"{}\" was running, so the interruption is cut to that.",
+            format_duration(recorded),
+            timer.name,
+        );
+    }
+    let (count, total) = interruption_stats(&timer.breaks);
+    println!(
+        "Logged a {} interruption on \"{}\" ({count} so far, {}).",
+        format_duration(recorded),
+        timer.name,
+        format_duration(total),
+    );
 }
 
-pub fn resume(conn: &Connection) {
+/// Block until `secs` have passed since the pause at `paused_at`, then ring.
+/// Returns early if the timer is resumed or stopped meanwhile. The break may
+/// have begun earlier (pausing right after a resume reopens the last one);
+/// it is unaffected either way — this is only a nudge.
+fn wait_for_break(conn: &Connection, timer_id: u32, paused_at: i64, secs: i64, verbosity: Verbosity) {
+    if !verbosity.quiet() {
+        println!(
+            "Reminding you in {} (Ctrl-C stops waiting; the break keeps going).",
+            format_duration(secs)
+        );
+    }
+    loop {
+        let still_on_break = get_active_by_id(conn, timer_id).is_some_and(|t| {
+            // A break opened after `paused_at` means a resume and a new pause.
+            t.state == "paused" && t.breaks.iter().any(|b| b.end_ts == 0 && b.start_ts <= paused_at)
+        });
+        if !still_on_break {
+            return;
+        }
+        if Local::now().timestamp() - paused_at >= secs {
+            notify(&format!("Your {} break is up — back to work? (tl resume)", format_duration(secs)));
+            return;
+        }
+        std::thread::sleep(std::time::Duration::from_secs(1));
+    }
+}
+
+/// Resume a paused timer. With several paused, `last` picks the most recently
+/// paused one without asking; otherwise the picker starts on it. A break
+/// longer than the `long-break` setting gets a chance to be corrected first,
+/// unless `force` is set.
+pub fn resume(conn: &Connection, last: bool, force: bool, verbosity: Verbosity) {
     if get_running(conn).is_some() {
         eprintln!("A timer is already running. Pause or stop it first.");
         std::process::exit(1);
@@ -176,12 +488,25 @@ pub fn resume(conn: &Connection) {
     let paused: Vec<&ActiveTimer> = all.iter().filter(|t| t.state == "paused").collect();
 
     if paused.is_empty() {
-        eprintln!("No paused timers.");
-        std::process::exit(1);
+        // Scripts still get the error; people get a way forward.
+        if verbosity.quiet() || !std::io::stdin().is_terminal() {
+            eprintln!("No paused timers.");
+            std::process::exit(1);
+        }
+        let fresh = Confirm::new()
+            .with_prompt("No paused timers — start a new one?")
+            .default(true)
+            .interact_opt()
+            .chosen();
+        if fresh {
+            start(conn, None, None, None, false, verbosity);
+        }
+        return;
     }
 
-    let timer_to_resume = if paused.len() == 1 {
-        paused[0]
+    let latest = (0..paused.len()).max_by_key(|&i| paused[i].paused_at()).unwrap();
+    let timer_to_resume = if paused.len() == 1 || last {
+        paused[latest]
     } else {
         let now_ts = Local::now().timestamp();
         let items: Vec<String> = paused
@@ -191,26 +516,29 @@ pub fn resume(conn: &Connection) {
                 let break_secs = total_break_secs(&t.breaks, now_ts);
                 let active_secs = (elapsed - break_secs).max(0);
                 format!(
-                    "#{} \"{}\" [{}] — active: {}",
+                    "#{} \"{}\" [{}] — active: {}{}",
                     t.id.unwrap(),
                     t.name,
                     t.category,
                     format_duration(active_secs),
+                    paused_for(t, now_ts),
                 )
             })
             .collect();
 
-        let selection = Select::new()
-            .with_prompt("Which timer to resume?")
+        let selection = FuzzySelect::new()
+            .with_prompt("Which timer to resume? (type to filter)")
+            .max_length(PICKER_ROWS)
             .items(&items)
-            .default(0)
-            .interact()
-            .unwrap();
+            .default(latest)
+            .interact_opt()
+            .chosen();
 
         paused[selection]
     };
 
     let now_ts = Local::now().timestamp();
+    let back_at = if force { now_ts } else { check_long_break(conn, timer_to_resume, now_ts) };
     let mut resumed = ActiveTimer {
         id: timer_to_resume.id,
         name: timer_to_resume.name.clone(),
@@ -221,36 +549,88 @@ pub fn resume(conn: &Connection) {
         todo_id: timer_to_resume.todo_id,
         last_modified: 0,
     };
-    if let Some(last) = resumed.breaks.last_mut() {
-        if last.end_ts == 0 {
-            last.end_ts = now_ts;
-        }
-    }
+    resumed.resume(back_at);
     update_active(conn, &resumed);
 
-    let now = Local::now();
-    println!("Resumed \"{}\" at {}", resumed.name, now.format("%H:%M:%S"));
+    if verbosity.quiet() {
+        println!("{}", resumed.id.unwrap());
+    } else {
+        println!("Resumed \"{}\" at {}", resumed.name, format_time(conn, back_at));
+    }
+}
+
+/// When `timer` has been paused longer than the `long-break` setting, ask
+/// whether to keep the break, cap it at the threshold, or end it at the time
+/// the user actually got back. Returns when the break should end.
+fn check_long_break(conn: &Connection, timer: &ActiveTimer, now_ts: i64) -> i64 {
+    let threshold = long_break_secs(conn);
+    let Some(paused_at) = timer.paused_at().filter(|&p| now_ts - p > threshold) else {
+        return now_ts;
+    };
+    println!(
+        "\"{}\" has been paused for {} (since {}).",
+        timer.name,
+        format_duration(now_ts - paused_at),
+        format_datetime(conn, paused_at),
+    );
+    if !std::io::stdin().is_terminal() {
+        println!("Keeping the whole break; run `tl resume` in a terminal to correct it.");
+        return now_ts;
+    }
+
+    let capped = paused_at + threshold;
+    let items = [
+        "Keep the whole break".to_string(),
+        format!("Cap the break at {} (back at {})", format_duration(threshold), format_datetime(conn, capped)),
+        "Enter when I actually got back".to_string(),
+    ];
+    let choice = Select::new()
+        .with_prompt("That's a long break. Correct it?")
+        .items(&items)
+        .default(0)
+        .interact_opt()
+        .chosen();
+    match choice {
+        1 => capped,
+        2 => loop {
+            let input: String = Input::new()
+                .with_prompt("Back at (e.g. 09:15 or 2024-05-01 09:15)")
+                .interact_text()
+                .answer();
+            match parse_clock_time(&input, Local::now().date_naive()) {
+                Some(ts) if ts > paused_at && ts <= now_ts => break ts,
+                Some(_) => eprintln!(
+                    "Pick a time between {} and now.",
+                    format_datetime(conn, paused_at),
+                ),
+                None => eprintln!("Invalid time \"{input}\"."),
+            }
+        },
+        _ => now_ts,
+    }
 }
 
-pub fn restart(conn: &Connection) {
+pub fn restart(conn: &Connection, verbosity: Verbosity) {
     if let Some(running) = get_running(conn) {
         let now_ts = Local::now().timestamp();
         let elapsed = now_ts - running.started_at;
         let break_secs = total_break_secs(&running.breaks, now_ts);
         let active_secs = (elapsed - break_secs).max(0);
 
-        println!(
-            "Running: \"{}\" [{}] — active: {}",
-            running.name,
-            running.category,
-            format_duration(active_secs),
-        );
+        if !verbosity.quiet() {
+            println!(
+                "Running: \"{}\" [{}] — active: {}",
+                running.name,
+                color::category(conn, &running.category),
+                format_duration(active_secs),
+            );
+        }
 
         let confirm = Confirm::new()
             .with_prompt("Pause current timer and start a new one?")
             .default(false)
-            .interact()
-            .unwrap();
+            .interact_opt()
+            .chosen();
 
         if !confirm {
             return;
@@ -258,14 +638,12 @@ pub fn restart(conn: &Connection) {
 
         // Pause the running timer
         let mut paused = running;
-        paused.state = "paused".into();
-        paused.breaks.push(proto::Break {
-            start_ts: now_ts,
-            end_ts: 0,
-        });
+        paused.pause(now_ts, "");
         update_active(conn, &paused);
 
-        println!("Paused \"{}\".", paused.name);
+        if !verbosity.quiet() {
+            println!("Paused \"{}\".", paused.name);
+        }
     }
 
     let last_entry = match get_last_entry(conn) {
@@ -287,73 +665,86 @@ pub fn restart(conn: &Connection) {
         todo_id: last_entry.todo_id,
         last_modified: 0,
     };
-    insert_active(conn, &timer);
+    let id = insert_active(conn, &timer);
 
+    if verbosity.quiet() {
+        println!("{id}");
+        return;
+    }
     println!(
         "Restarted \"{}\" [{}] at {}",
         timer.name,
-        timer.category,
-        now.format("%H:%M:%S")
+        color::category(conn, &timer.category),
+        format_time(conn, now.timestamp())
     );
 }
 
-pub fn switch(conn: &Connection) {
+/// Pick any active timer to run. The running one is listed too (and
+/// pre-selected); picking it changes nothing.
+pub fn switch(conn: &Connection, verbosity: Verbosity) {
     let all = get_all_active(conn);
     let running = all.iter().find(|t| t.state == "running");
-    let paused: Vec<&ActiveTimer> = all.iter().filter(|t| t.state == "paused").collect();
 
-    if paused.is_empty() {
-        println!("No other timers to switch to.");
+    if all.is_empty() {
+        println!("No active timers to switch to.");
         return;
     }
 
     let now_ts = Local::now().timestamp();
-    let items: Vec<String> = paused
+    let items: Vec<String> = all
         .iter()
         .map(|t| {
-            let elapsed = now_ts - t.started_at;
-            let break_secs = total_break_secs(&t.breaks, now_ts);
-            let active_secs = (elapsed - break_secs).max(0);
             format!(
-                "#{} \"{}\" [{}] — active: {}",
+                "#{} \"{}\" [{}] — active: {}{}",
                 t.id.unwrap(),
                 t.name,
                 t.category,
-                format_duration(active_secs),
+                format_duration(t.active_secs(now_ts)),
+                if t.state == "running" { " (RUNNING)".to_string() } else { paused_for(t, now_ts) },
             )
         })
         .collect();
+    let default = match all.iter().position(|t| t.state == "running") {
+        Some(i) => i,
+        None => (0..all.len()).max_by_key(|&i| all[i].paused_at()).unwrap(),
+    };
 
-    let selection = Select::new()
-        .with_prompt("Switch to which timer?")
+    let selection = FuzzySelect::new()
+        .with_prompt("Switch to which timer? (type to filter)")
+        .max_length(PICKER_ROWS)
         .items(&items)
-        .default(0)
-        .interact()
-        .unwrap();
-
-    let selected = paused[selection];
+        .default(default)
+        .interact_opt()
+        .chosen();
+
+    let selected = &all[selection];
+    if selected.state == "running" {
+        if verbosity.quiet() {
+            println!("{}", selected.id.unwrap());
+        } else {
+            println!("Already on \"{}\" [{}].", selected.name, color::category(conn, &selected.category));
+        }
+        return;
+    }
 
-    // Pause the currently running timer (if any)
+    // Pause the currently running timer (if any) and resume the selected one
+    // in a single transaction.
+    let mut paused_timer = None;
     if let Some(r) = running {
-        let mut paused_timer = ActiveTimer {
+        let mut p = ActiveTimer {
             id: r.id,
             name: r.name.clone(),
             category: r.category.clone(),
             started_at: r.started_at,
-            state: "paused".into(),
+            state: r.state.clone(),
             breaks: r.breaks.clone(),
             todo_id: r.todo_id,
             last_modified: 0,
         };
-        paused_timer.breaks.push(proto::Break {
-            start_ts: now_ts,
-            end_ts: 0,
-        });
-        update_active(conn, &paused_timer);
-        println!("Paused \"{}\".", r.name);
+        p.pause(now_ts, "");
+        paused_timer = Some(p);
     }
 
-    // Resume the selected timer
     let mut resumed = ActiveTimer {
         id: selected.id,
         name: selected.name.clone(),
@@ -364,35 +755,77 @@ pub fn switch(conn: &Connection) {
         todo_id: selected.todo_id,
         last_modified: 0,
     };
-    if let Some(last) = resumed.breaks.last_mut() {
-        if last.end_ts == 0 {
-            last.end_ts = now_ts;
-        }
+    resumed.resume(now_ts);
+    update_actives(conn, &paused_timer.iter().chain([&resumed]).collect::<Vec<_>>());
+
+    if verbosity.quiet() {
+        println!("{}", resumed.id.unwrap());
+        return;
     }
-    update_active(conn, &resumed);
+    if let Some(p) = &paused_timer {
+        println!("Paused \"{}\".", p.name);
+    }
+    println!("Switched to \"{}\" [{}].", resumed.name, color::category(conn, &resumed.category));
+}
 
-    println!("Switched to \"{}\" [{}].", resumed.name, resumed.category);
+/// Jot a timestamped note onto the running timer without stopping it; it
+/// moves to the log entry on stop.
+pub fn note(conn: &Connection, text: &str, verbosity: Verbosity) {
+    let text = text.trim();
+    if text.is_empty() {
+        eprintln!("Nothing to note.");
+        std::process::exit(1);
+    }
+    let Some(timer) = get_running(conn) else {
+        eprintln!("No running timer.");
+        std::process::exit(1);
+    };
+    let timer_id = timer.id.unwrap();
+    let now_ts = Local::now().timestamp();
+    add_timer_note(conn, timer_id, &Note { ts: now_ts, text: text.to_string() });
+    if !verbosity.quiet() {
+        let count = timer_notes(conn, timer_id).len();
+        println!(
+            "Noted on \"{}\" at {} ({count} {}).",
+            timer.name,
+            format_hm(conn, now_ts),
+            if count == 1 { "note" } else { "notes" },
+        );
+    }
 }
 
-pub fn status(conn: &Connection) {
+/// List the active timers, their durations shown at `precision`.
+pub fn status(conn: &Connection, precision: Precision) {
+    idle::check(conn);
+    notify::check_goal(conn);
     let all = get_all_active(conn);
 
     if all.is_empty() {
-        println!("No active timers.");
+        if get_last_entry(conn).is_none() {
+            println!("No timers yet — run `tl start` to begin.");
+        } else {
+            println!("No active timers — run `tl start` to begin one.");
+        }
+        if let Some(line) = goal::progress_line(conn) {
+            println!("{line}");
+        }
         return;
     }
 
+    let palette = color::Palette::load(conn);
     let now_ts = Local::now().timestamp();
+    let max = max_duration_secs(conn);
+    let this_host = hostname();
+    let (mut total_active, mut total_breaks) = (0, 0);
 
     for timer in &all {
         let elapsed = now_ts - timer.started_at;
         let break_secs = total_break_secs(&timer.breaks, now_ts);
         let active_secs = (elapsed - break_secs).max(0);
+        total_active += active_secs;
+        total_breaks += break_secs;
 
-        let started = Local
-            .timestamp_opt(timer.started_at, 0)
-            .single()
-            .unwrap();
+        let started = local_time(timer.started_at);
 
         let state_label = if timer.state == "running" {
             "RUNNING"
@@ -404,68 +837,270 @@ pub fn status(conn: &Connection) {
             "#{} \"{}\" [{}] — {}",
             timer.id.unwrap(),
             timer.name,
-            timer.category,
+            palette.category(&timer.category),
             state_label,
         );
-        println!("  Started:  {}", started.format("%H:%M:%S"));
-        println!("  Active:   {}", format_duration(active_secs));
-        println!("  Breaks:   {}", format_duration(break_secs));
-        if let Some(tid) = timer.todo_id {
-            let todos = list_todos(conn);
-            if let Some(todo) = todos.iter().find(|t| t.id == tid) {
-                println!("  -> todo #{} \"{}\"", tid, todo.text);
+        println!(
+            "  Started:  {} ({})",
+            format_time(conn, started.timestamp()),
+            humanize_ago(timer.started_at, now_ts)
+        );
+        // Only worth a line when the timer is running on another machine.
+        if let Some(host) = timer_host(conn, timer.id.unwrap()).filter(|h| *h != this_host) {
+            println!("  Host:     {host}");
+        }
+        println!("  Active:   {}", format_duration_prec(active_secs, precision));
+        println!("  Breaks:   {}", format_duration_prec(break_secs, precision));
+        let notes = timer_notes(conn, timer.id.unwrap()).len();
+        if notes > 0 {
+            println!("  Notes:    {notes} (shown in `tl log show` once stopped)");
+        }
+        if let Some(paused_at) = timer.paused_at().filter(|&p| now_ts - p > long_break_secs(conn)) {
+            println!(
+                "  Warning:  paused for {} — `tl resume` offers to correct the break.",
+                format_duration_prec(now_ts - paused_at, precision),
+            );
+        }
+        if timer.breaks.len() > MAX_BREAKS {
+            println!("  Warning:  {} breaks — is something pausing and resuming it in a loop?", timer.breaks.len());
+        }
+        if let Some(max) = max
+            && active_secs > max
+        {
+            println!(
+                "  Warning:  over the {} max duration — forgot to stop? `tl stop` offers to cap it.",
+                format_duration(max),
+            );
+        }
+        for b in timer.breaks.iter().filter(|b| !b.label.is_empty()) {
+            let end = if b.end_ts == 0 { now_ts } else { b.end_ts };
+            let start = local_time(b.start_ts);
+            let until = if b.end_ts == 0 {
+                "now".to_string()
+            } else {
+                format_hm(conn, b.end_ts)
+            };
+            println!(
+                "            {}–{until} ({}) — {}",
+                format_hm(conn, start.timestamp()),
+                format_duration_prec(end - b.start_ts, precision),
+                b.label,
+            );
+        }
+        for tid in timer_todos(conn, timer) {
+            if let Some(todo) = get_todo_by_id(conn, tid) {
+                let (_, _, tracked) = get_todo_combined_secs(conn, tid);
+                println!(
+                    "  -> todo #{} \"{}\" ({} tracked)",
+                    tid,
+                    todo.text,
+                    format_duration_prec(tracked, precision)
+                );
+                // Flag scope creep while the task is still being worked on.
+                if let Some(est) = todo.estimate_secs.filter(|&est| timer.state == "running" && tracked > est) {
+                    println!(
+                        "     Warning: todo #{tid} is {}% over estimate ({} / {})",
+                        (tracked - est) * 100 / est.max(1),
+                        format_duration_prec(tracked, precision),
+                        format_duration_prec(est, precision),
+                    );
+                }
             } else {
                 println!("  -> todo #{tid}");
             }
         }
     }
-}
-
-pub fn log(conn: &Connection, today: bool, week: bool) {
-    let since_ts = if today {
-        Some(
-            Local::now()
-                .date_naive()
-                .and_hms_opt(0, 0, 0)
-                .unwrap()
-                .and_local_timezone(Local)
-                .unwrap()
-                .timestamp(),
-        )
-    } else if week {
-        Some(
-            (Local::now() - chrono::Duration::days(7))
-                .timestamp(),
-        )
-    } else {
-        None
-    };
 
-    let entries = query_entries(conn, since_ts);
-
-    if entries.is_empty() {
-        println!("No log entries found.");
-        return;
+    if all.len() > 1 {
+        println!();
+        println!(
+            "Total across {} timers — active: {}, breaks: {}",
+            all.len(),
+            format_duration_prec(total_active, precision),
+            format_duration_prec(total_breaks, precision),
+        );
+    }
+    if let Some(line) = goal::progress_line(conn) {
+        if all.len() < 2 {
+            println!();
+        }
+        println!("{line}");
     }
+}
 
-    println!(
-        "{:<5} {:<20} {:<15} {:<10} {:<12} {:<10} {}",
-        "ID", "Name", "Category", "Date", "Active", "Breaks", "Todo"
-    );
-    println!("{}", "-".repeat(86));
+/// Compact one-screen snapshot of today: totals, per-category breakdown,
+/// active timers, and goal progress.
+pub fn today(conn: &Connection) {
+    let now = Local::now();
+    let now_ts = now.timestamp();
+    let today_start = today_start_ts(conn);
+    let entries = query_entries(conn, Some(today_start));
+    let all = get_all_active(conn);
+    let palette = color::Palette::load(conn);
 
     let mut total_active: i64 = 0;
     let mut total_breaks: i64 = 0;
+    let mut by_cat: Vec<(String, i64)> = Vec::new();
+    let mut add_to_cat = |cat: &str, secs: i64| match by_cat.iter_mut().find(|(c, _)| c == cat) {
+        Some((_, s)) => *s += secs,
+        None => by_cat.push((cat.to_string(), secs)),
+    };
 
     for e in &entries {
-        let break_secs = total_break_secs(&e.breaks, e.ended_at);
         total_active += e.active_secs;
+        total_breaks += total_break_secs(&e.breaks, e.ended_at);
+        add_to_cat(&e.category, e.active_secs);
+    }
+    // A timer started before the day did only counts its part of today.
+    for t in &all {
+        let elapsed = (now_ts - t.started_at.max(today_start)).max(0);
+        let active_secs = active_secs_since(t, today_start, now_ts);
+        let break_secs = elapsed - active_secs;
+        total_active += active_secs;
         total_breaks += break_secs;
+        add_to_cat(&t.category, active_secs);
+    }
+    by_cat.sort_by_key(|c| std::cmp::Reverse(c.1));
 
-        let date = Local
-            .timestamp_opt(e.started_at, 0)
-            .single()
-            .unwrap();
+    println!("Today — {}", today_date(conn).format("%a %Y-%m-%d"));
+    if entries.is_empty() && all.is_empty() {
+        println!("  Nothing tracked yet today.");
+    } else {
+        println!("  Active:   {}", format_duration(total_active));
+        println!("  Breaks:   {}", format_duration(total_breaks));
+        println!("  Entries:  {}", entries.len());
+
+        println!();
+        for (cat, secs) in &by_cat {
+            println!("  {} {}", palette.paint(cat, &format!("{:<20}", truncate(cat, 19))), format_duration(*secs));
+        }
+    }
+
+    if !all.is_empty() {
+        println!();
+        let max = max_duration_secs(conn);
+        for t in &all {
+            let elapsed = now_ts - t.started_at;
+            let active_secs = (elapsed - total_break_secs(&t.breaks, now_ts)).max(0);
+            let icon = if t.state == "running" { "▶" } else { "⏸" };
+            let over = match max {
+                Some(max) if active_secs > max => format!(" (over {} max)", format_duration(max)),
+                _ => String::new(),
+            };
+            println!(
+                "  {icon} #{} \"{}\" [{}] — {}{over}",
+                t.id.unwrap(),
+                t.name,
+                palette.category(&t.category),
+                format_duration(active_secs),
+            );
+        }
+    }
+
+    if let Some(line) = goal::progress_line(conn) {
+        println!();
+        println!("{line}");
+    }
+
+    let budgets = budget::status_lines(conn);
+    if !budgets.is_empty() {
+        println!();
+        println!("Budgets (this week):");
+        for line in budgets {
+            println!("{line}");
+        }
+    }
+}
+
+/// Start of the `--today` / `--week` window, or `None` for all entries.
+pub(crate) fn since_ts(conn: &Connection, today: bool, week: bool) -> Option<i64> {
+    if today {
+        Some(today_start_ts(conn))
+    } else if week {
+        Some(day_start_ts(this_week_start(conn), day_start_hour(conn)))
+    } else {
+        None
+    }
+}
+
+pub fn log(conn: &Connection, today: bool, week: bool, filter: EntryFilter, width: Option<usize>, relative: bool) {
+    let entries = filter_entries(
+        conn,
+        &EntryFilter {
+            since_ts: since_ts(conn, today, week),
+            ..filter
+        },
+    );
+
+    if entries.is_empty() {
+        if get_last_entry(conn).is_none() {
+            println!("No log entries yet — start a timer with `tl start`; `tl stop` logs it here.");
+        } else {
+            println!("No log entries found.");
+        }
+        return;
+    }
+
+    print_entries(conn, &entries, width, relative);
+}
+
+pub fn search(conn: &Connection, query: &str, today: bool, week: bool) {
+    let entries = search_entries(conn, query, since_ts(conn, today, week));
+
+    if entries.is_empty() {
+        println!("No log entries matching \"{query}\".");
+        return;
+    }
+
+    print_entries(conn, &entries, None, false);
+}
+
+/// Width of the `tl log` columns other than name, category and date,
+/// separating spaces and the "Tags" heading included.
+const LOG_FIXED_WIDTH: usize = 43;
+
+/// Date column width: `2024-05-01`, or `--relative`'s `123 days ago`.
+fn log_date_width(relative: bool) -> usize {
+    if relative { 12 } else { 10 }
+}
+
+/// Name and category column widths for a `tl log` table `width` columns
+/// wide: `width` if given, else the terminal's. They split what the other
+/// columns leave 4:3, as the 20/15 used when output isn't a terminal.
+fn log_columns(width: Option<usize>, date_w: usize) -> (usize, usize) {
+    let width = width.or_else(|| terminal_size::terminal_size().map(|(w, _)| w.0 as usize));
+    match width {
+        Some(width) => {
+            let flexible = width.saturating_sub(LOG_FIXED_WIDTH + date_w).max(18);
+            let name = flexible * 4 / 7;
+            (name, flexible - name)
+        }
+        None => (20, 15),
+    }
+}
+
+/// Print entries as the `tl log` table with a TOTAL row, sized to `width`
+/// columns or the terminal (see `log_columns`). `relative` dates each entry
+/// by how long ago it started.
+fn print_entries(conn: &Connection, entries: &[TimeEntry], width: Option<usize>, relative: bool) {
+    let palette = color::Palette::load(conn);
+    let date_w = log_date_width(relative);
+    let (name_w, cat_w) = log_columns(width, date_w);
+    let rule = "-".repeat(LOG_FIXED_WIDTH + date_w + name_w + cat_w);
+    let now = Local::now().timestamp();
+    println!(
+        "{:<5} {:<name_w$} {:<cat_w$} {:<date_w$} {:<12} {:<10} {:<5} Tags",
+        "ID", "Name", "Category", "Date", "Active", "Breaks", "Todo"
+    );
+    println!("{rule}");
+
+    let mut total_active: i64 = 0;
+    let mut total_breaks: i64 = 0;
+
+    for e in entries {
+        let break_secs = total_break_secs(&e.breaks, e.ended_at);
+        total_active += e.active_secs;
+        total_breaks += break_secs;
 
         let todo_col = match e.todo_id {
             Some(tid) => format!("#{tid}"),
@@ -473,20 +1108,21 @@ pub fn log(conn: &Connection, today: bool, week: bool) {
         };
 
         println!(
-            "{:<5} {:<20} {:<15} {:<10} {:<12} {:<10} {}",
+            "{:<5} {:<name_w$} {} {:<date_w$} {:<12} {:<10} {:<5} {}",
             e.id,
-            truncate(&e.name, 19),
-            truncate(&e.category, 14),
-            date.format("%Y-%m-%d"),
+            truncate(&e.name, name_w - 1),
+            palette.paint(&e.category, &format!("{:<cat_w$}", truncate(&e.category, cat_w - 1))),
+            if relative { humanize_ago(e.started_at, now) } else { format_date(e.started_at) },
             format_duration(e.active_secs),
             format_duration(break_secs),
             todo_col,
+            format_tags(&e.tags),
         );
     }
 
-    println!("{}", "-".repeat(86));
+    println!("{rule}");
     println!(
-        "{:<5} {:<20} {:<15} {:<10} {:<12} {}",
+        "{:<5} {:<name_w$} {:<cat_w$} {:<date_w$} {:<12} {}",
         "",
         "TOTAL",
         "",
@@ -506,10 +1142,10 @@ pub fn edit_log(conn: &Connection, id: u32, name: Option<String>, category: Opti
     };
 
     if let Some(n) = name {
-        entry.name = n;
+        entry.name = require_name(&n, "Name");
     }
     if let Some(c) = category {
-        entry.category = c;
+        entry.category = canonical_category(conn, &require_name(&c, "Category"));
     }
     if let Some(m) = add {
         entry.active_secs += (m * 60) as i64;
@@ -524,39 +1160,487 @@ pub fn edit_log(conn: &Connection, id: u32, name: Option<String>, category: Opti
 }
 
 pub fn export_log(conn: &Connection, today: bool, week: bool) {
-    let since_ts = if today {
-        Some(
-            Local::now()
-                .date_naive()
-                .and_hms_opt(0, 0, 0)
-                .unwrap()
-                .and_local_timezone(Local)
-                .unwrap()
-                .timestamp(),
-        )
-    } else if week {
-        Some((Local::now() - chrono::Duration::days(7)).timestamp())
-    } else {
-        None
-    };
+    let entries = query_entries(conn, since_ts(conn, today, week));
 
-    let entries = query_entries(conn, since_ts);
-
-    println!("id,name,category,date,active_secs,break_secs,todo_id");
+    println!("id,name,category,date,active_secs,break_secs,todo_id,tags");
     for e in &entries {
         let break_secs = total_break_secs(&e.breaks, e.ended_at);
-        let date = Local.timestamp_opt(e.started_at, 0).single().unwrap();
+        let date = local_time(e.started_at);
         let todo = match e.todo_id {
             Some(tid) => tid.to_string(),
             None => String::new(),
         };
         let name = e.name.replace("\"", "\"\"");
         let category = e.category.replace("\"", "\"\"");
-        println!("{},\"{}\",\"{}\",{},{},{},{}", e.id, name, category, date.format("%Y-%m-%d"), e.active_secs, break_secs, todo);
+        let tags = e.tags.join(",").replace("\"", "\"\"");
+        println!("{},\"{}\",\"{}\",{},{},{},{},\"{}\"", e.id, name, category, date.format("%Y-%m-%d"), e.active_secs, break_secs, todo, tags);
+    }
+}
+
+/// Split a log entry at `at` into two entries, prompting for each half's
+/// name and category. Active time is divided in proportion to the working
+/// (non-break) time on each side of the split.
+pub fn split(conn: &Connection, id: u32, at: &str) {
+    let entry = match get_entry_by_id(conn, id) {
+        Some(e) => e,
+        None => {
+            eprintln!("Log entry #{id} not found.");
+            std::process::exit(1);
+        }
+    };
+
+    let start = local_time(entry.started_at);
+    let end = local_time(entry.ended_at);
+    let inside = |ts: &i64| *ts > entry.started_at && *ts < entry.ended_at;
+    let split_ts = match parse_clock_time(at, start.date_naive())
+        .filter(inside)
+        .or_else(|| parse_clock_time(at, end.date_naive()).filter(inside))
+    {
+        Some(ts) => ts,
+        None => {
+            eprintln!(
+                "Split time must fall strictly between {} and {}.",
+                format_datetime(conn, start.timestamp()),
+                format_datetime(conn, end.timestamp()),
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let (first_breaks, second_breaks) = split_breaks(&entry.breaks, split_ts);
+    let first_work = (split_ts - entry.started_at - total_break_secs(&first_breaks, split_ts)).max(0);
+    let second_work = (entry.ended_at - split_ts - total_break_secs(&second_breaks, entry.ended_at)).max(0);
+    let first_active = if first_work + second_work > 0 {
+        (entry.active_secs as i128 * first_work as i128 / (first_work + second_work) as i128) as i64
+    } else {
+        entry.active_secs / 2
+    };
+
+    println!(
+        "Splitting #{id} \"{}\" [{}] at {}",
+        entry.name,
+        color::category(conn, &entry.category),
+        format_time(conn, split_ts),
+    );
+
+    let mut parts = Vec::new();
+    for (label, started_at, ended_at, breaks, active_secs) in [
+        ("First", entry.started_at, split_ts, first_breaks, first_active),
+        ("Second", split_ts, entry.ended_at, second_breaks, entry.active_secs - first_active),
+    ] {
+        let name = prompt_name(&format!("{label} part name"), Some(entry.name.clone()));
+        let category = canonical_category(conn, &prompt_name(&format!("{label} part category"), Some(entry.category.clone())));
+        parts.push(TimeEntry {
+            id: 0,
+            name,
+            category,
+            started_at,
+            ended_at,
+            active_secs,
+            breaks,
+            todo_id: entry.todo_id,
+            last_modified: 0,
+            tags: entry.tags.clone(),
+            utc_offset: entry.utc_offset,
+            host: entry.host.clone(),
+        });
+    }
+
+    let ids = replace_entries(conn, &[id], &parts);
+    println!("Replaced log entry #{id} with:");
+    for (new_id, part) in ids.iter().zip(&parts) {
+        println!(
+            "  #{new_id} \"{}\" [{}] — active: {}",
+            part.name,
+            color::category(conn, &part.category),
+            format_duration(part.active_secs),
+        );
+    }
+}
+
+/// Merge two log entries into one spanning both, summing active time and
+/// keeping the first entry's name and category unless the user picks another.
+pub fn merge(conn: &Connection, id1: u32, id2: u32, dry_run: bool) {
+    if id1 == id2 {
+        eprintln!("Cannot merge log entry #{id1} with itself.");
+        std::process::exit(1);
+    }
+    let [first, second] = [id1, id2].map(|id| match get_entry_by_id(conn, id) {
+        Some(e) => e,
+        None => {
+            eprintln!("Log entry #{id} not found.");
+            std::process::exit(1);
+        }
+    });
+
+    let (earlier, later) = if first.started_at <= second.started_at { (&first, &second) } else { (&second, &first) };
+    // Overlapping entries would count the shared time twice, and one's breaks
+    // would eat into the other's work.
+    if later.started_at < earlier.ended_at {
+        eprintln!(
+            "#{id1} and #{id2} overlap ({}–{} and {}–{}); shorten one with `tl log edit` first.",
+            format_datetime(conn, first.started_at),
+            format_datetime(conn, first.ended_at),
+            format_datetime(conn, second.started_at),
+            format_datetime(conn, second.ended_at),
+        );
+        std::process::exit(1);
+    }
+
+    let (first_todos, second_todos) = (entry_todos(conn, &first), entry_todos(conn, &second));
+    if first_todos != second_todos {
+        let describe = |t: &[u32]| match t {
+            [] => "no todo".to_string(),
+            ids => ids.iter().map(|t| format!("todo #{t}")).collect::<Vec<_>>().join(", "),
+        };
+        println!(
+            "Warning: #{id1} is linked to {} but #{id2} is linked to {}.",
+            describe(&first_todos),
+            describe(&second_todos),
+        );
+        // A dry run only reports the differences; it never asks.
+        let confirm = dry_run
+            || Confirm::new()
+                .with_prompt("Merge anyway, linking the result to all of them?")
+                .default(false)
+                .interact_opt()
+                .chosen();
+        if !confirm {
+            return;
+        }
+    }
+
+    let (mut name, mut category) = (first.name.clone(), first.category.clone());
+    let differ = first.name != second.name || first.category != second.category;
+    if differ {
+        println!(
+            "Warning: entries differ — #{id1} \"{}\" [{}] vs #{id2} \"{}\" [{}].",
+            first.name,
+            color::category(conn, &first.category),
+            second.name,
+            color::category(conn, &second.category),
+        );
+        if !dry_run {
+            name = prompt_name("Merged name", Some(name));
+            category = canonical_category(conn, &prompt_name("Merged category", Some(category)));
+        }
+    }
+
+    let mut breaks: Vec<_> = first.breaks.iter().chain(&second.breaks).cloned().collect();
+    // The time between the two goes down as a break, so the span minus
+    // breaks still adds up to the summed active time.
+    if later.started_at > earlier.ended_at {
+        breaks.push(proto::Break {
+            start_ts: earlier.ended_at,
+            end_ts: later.started_at,
+            label: String::new(),
+            interruption: false,
+        });
+    }
+    breaks.sort_by_key(|b| b.start_ts);
+    let mut tags = first.tags.clone();
+    tags.extend(second.tags.iter().cloned());
+
+    let merged = TimeEntry {
+        id: 0,
+        name,
+        category,
+        started_at: first.started_at.min(second.started_at),
+        ended_at: first.ended_at.max(second.ended_at),
+        active_secs: first.active_secs + second.active_secs,
+        breaks,
+        // Both entries' links carry over; the later one's primary todo wins.
+        todo_id: later.todo_id.or(earlier.todo_id),
+        last_modified: 0,
+        tags: normalize_tags(&tags),
+        utc_offset: first.utc_offset.or(second.utc_offset),
+        host: first.host.clone().or_else(|| second.host.clone()),
+    };
+
+    let overlapping: Vec<TimeEntry> = find_overlapping(conn, &merged, Some(id1))
+        .into_iter()
+        .filter(|e| e.id != id2)
+        .collect();
+    if dry_run {
+        if !overlapping.is_empty() {
+            let ids: Vec<String> = overlapping.iter().map(|e| format!("#{}", e.id)).collect();
+            println!("Warning: the merged entry would overlap {}.", ids.join(", "));
+        }
+        println!(
+            "Would merge #{id1} and #{id2} into one entry \"{}\" [{}] — {}–{}, active: {}{}",
+            merged.name,
+            color::category(conn, &merged.category),
+            format_datetime(conn, merged.started_at),
+            format_datetime(conn, merged.ended_at),
+            format_duration(merged.active_secs),
+            if differ { " (name and category asked for when merging)" } else { "" },
+        );
+        return;
+    }
+    if !confirm_overlaps(conn, &merged, &overlapping) {
+        return;
+    }
+
+    let ids = replace_entries(conn, &[id1, id2], std::slice::from_ref(&merged));
+    println!(
+        "Merged #{id1} and #{id2} into #{} \"{}\" [{}] — active: {}",
+        ids[0],
+        merged.name,
+        color::category(conn, &merged.category),
+        format_duration(merged.active_secs),
+    );
+}
+
+/// Interactively edit or delete the breaks of a log entry. Active time is
+/// adjusted by however much total break time changed.
+pub fn edit_breaks(conn: &Connection, id: u32) {
+    let mut entry = match get_entry_by_id(conn, id) {
+        Some(e) => e,
+        None => {
+            eprintln!("Log entry #{id} not found.");
+            std::process::exit(1);
+        }
+    };
+    if entry.breaks.is_empty() {
+        println!("Log entry #{id} has no breaks.");
+        return;
+    }
+
+    let fmt = |ts: i64| format_time(conn, ts);
+    let original_break_secs = total_break_secs(&entry.breaks, entry.ended_at);
+    let mut breaks = entry.breaks.clone();
+
+    println!(
+        "Breaks for #{id} \"{}\" ({} → {})",
+        entry.name,
+        fmt(entry.started_at),
+        fmt(entry.ended_at),
+    );
+    loop {
+        let mut items: Vec<String> = breaks
+            .iter()
+            .enumerate()
+            .map(|(i, b)| {
+                format!("[{}] {} → {}  ({})", i + 1, fmt(b.start_ts), fmt(b.end_ts), format_duration(b.end_ts - b.start_ts))
+            })
+            .collect();
+        items.push("Save".into());
+        items.push("Cancel".into());
+
+        let selection = Select::new()
+            .with_prompt("Pick a break")
+            .items(&items)
+            .default(0)
+            .interact_opt()
+            .chosen();
+        if selection == breaks.len() {
+            break;
+        }
+        if selection > breaks.len() {
+            return;
+        }
+
+        let action = Select::new()
+            .with_prompt(format!("Break {}", selection + 1))
+            .items(["Edit times", "Delete", "Back"])
+            .default(0)
+            .interact_opt()
+            .answer()
+            .unwrap_or(2);
+        let mut edited = breaks.clone();
+        match action {
+            0 => {
+                let b = &mut edited[selection];
+                let mut valid = true;
+                for (label, ts) in [("Start", &mut b.start_ts), ("End", &mut b.end_ts)] {
+                    let date = local_time(*ts).date_naive();
+                    let input: String = Input::new()
+                        .with_prompt(format!("{label} (HH:MM[:SS] or YYYY-MM-DD HH:MM)"))
+                        .default(fmt(*ts))
+                        .interact_text()
+                        .answer();
+                    match parse_clock_time(&input, date) {
+                        Some(t) => *ts = t,
+                        None => {
+                            eprintln!("Invalid time \"{input}\".");
+                            valid = false;
+                            break;
+                        }
+                    }
+                }
+                if !valid {
+                    continue;
+                }
+            }
+            1 => {
+                edited.remove(selection);
+            }
+            _ => continue,
+        }
+        edited.sort_by_key(|b| b.start_ts);
+        match validate_breaks(&edited, entry.started_at, entry.ended_at) {
+            Ok(()) => breaks = edited,
+            Err(e) => eprintln!("Not applied: {e}."),
+        }
+    }
+
+    let new_break_secs = total_break_secs(&breaks, entry.ended_at);
+    entry.active_secs = (entry.active_secs + original_break_secs - new_break_secs).max(0);
+    entry.breaks = breaks;
+    update_entry(conn, &entry);
+    println!(
+        "Updated log entry #{id}. Active: {}, breaks: {}",
+        format_duration(entry.active_secs),
+        format_duration(new_break_secs),
+    );
+}
+
+/// Width of the `tl log show --breaks-gantt` timeline, in columns.
+const GANTT_WIDTH: i64 = 40;
+
+/// Map an entry's active spans and breaks onto a fixed-width bar, `█` for
+/// active and `─` for break. Every break gets at least one column so short
+/// ones don't vanish on a long session.
+fn breaks_gantt(entry: &TimeEntry) -> String {
+    let span = entry.ended_at - entry.started_at;
+    let mut cols = vec!['█'; GANTT_WIDTH as usize];
+    if span > 0 {
+        for b in &entry.breaks {
+            let end = if b.end_ts == 0 { entry.ended_at } else { b.end_ts };
+            let start = b.start_ts.clamp(entry.started_at, entry.ended_at) - entry.started_at;
+            let end = end.clamp(entry.started_at, entry.ended_at) - entry.started_at;
+            let first = (start * GANTT_WIDTH / span).min(GANTT_WIDTH - 1);
+            let last = ((end * GANTT_WIDTH + span - 1) / span).max(first + 1).min(GANTT_WIDTH);
+            for c in &mut cols[first as usize..last as usize] {
+                *c = '─';
+            }
+        }
+    }
+    format!("[{}]", cols.into_iter().collect::<String>())
+}
+
+/// Print everything about one log entry, untruncated, including each break.
+pub fn show(conn: &Connection, id: u32, gantt: bool) {
+    let entry = match get_entry_by_id(conn, id) {
+        Some(e) => e,
+        None => {
+            eprintln!("Log entry #{id} not found.");
+            std::process::exit(1);
+        }
+    };
+    let break_secs = total_break_secs(&entry.breaks, entry.ended_at);
+
+    println!("Entry #{}", entry.id);
+    println!("  Name:      {}", entry.name);
+    println!("  Category:  {}", color::category(conn, &entry.category));
+    if let Some(host) = &entry.host {
+        println!("  Host:      {host}");
+    }
+    println!("  Started:   {}", format_datetime_at(conn, entry.started_at, entry.utc_offset));
+    println!("  Ended:     {}", format_datetime_at(conn, entry.ended_at, entry.utc_offset));
+    println!("  Elapsed:   {}", format_duration(entry.ended_at - entry.started_at));
+    println!("  Active:    {}", format_duration(entry.active_secs));
+    println!("  Breaks:    {}", format_duration(break_secs));
+    let (interruptions, interrupted_secs) = interruption_stats(&entry.breaks);
+    if interruptions > 0 {
+        println!("  Interrupt: {interruptions}, {} (counted in breaks)", format_duration(interrupted_secs));
+    }
+    // `tl log edit` changes active time alone, so say where the difference
+    // went.
+    let adjusted = entry.active_secs + break_secs - (entry.ended_at - entry.started_at);
+    if adjusted > 0 {
+        println!("  Added:     {} (edited in by hand)", format_duration(adjusted));
+    } else if adjusted < 0 {
+        println!("  Untracked: {} (edited out by hand)", format_duration(-adjusted));
+    }
+    for (i, b) in entry.breaks.iter().enumerate() {
+        let end = if b.end_ts == 0 { entry.ended_at } else { b.end_ts };
+        let label = if b.label.is_empty() { String::new() } else { format!(" — {}", b.label) };
+        println!(
+            "    {} {}: {}–{} ({}){label}",
+            if b.interruption { "Interruption" } else { "Break" },
+            i + 1,
+            format_time(conn, b.start_ts),
+            format_time(conn, end),
+            format_duration(end - b.start_ts),
+        );
+    }
+    if gantt {
+        println!(
+            "  Timeline:  {} {}–{}",
+            breaks_gantt(&entry),
+            format_time(conn, entry.started_at),
+            format_time(conn, entry.ended_at),
+        );
+    }
+    if !entry.tags.is_empty() {
+        println!("  Tags:      {}", format_tags(&entry.tags));
+    }
+    for tid in entry_todos(conn, &entry) {
+        match get_todo_by_id(conn, tid) {
+            Some(todo) => println!("  Todo:      #{tid} \"{}\"{}", todo.text, if todo.done { " (done)" } else { "" }),
+            None => println!("  Todo:      #{tid}"),
+        }
+    }
+    let notes = entry_notes(conn, entry.id);
+    if !notes.is_empty() {
+        println!("  Notes:");
+        for n in &notes {
+            println!("    {}  {}", format_hm(conn, n.ts), n.text);
+        }
+    }
+}
+
+/// Point a log entry at different todos, replacing whatever it was linked to.
+pub fn link(conn: &Connection, id: u32, todo_ids: &[u32]) {
+    let mut todos: Vec<u32> = Vec::new();
+    for &tid in todo_ids {
+        if get_todo_by_id(conn, tid).is_none() {
+            eprintln!("Todo #{tid} not found.");
+            std::process::exit(1);
+        }
+        if !todos.contains(&tid) {
+            todos.push(tid);
+        }
+    }
+    if !relink_entry(conn, id, &todos) {
+        eprintln!("Log entry #{id} not found.");
+        std::process::exit(1);
+    }
+    let ids: Vec<String> = todos.iter().map(|t| format!("#{t}")).collect();
+    println!(
+        "Linked log entry #{id} to todo{} {}.",
+        if todos.len() == 1 { "" } else { "s" },
+        ids.join(", "),
+    );
+}
+
+pub fn unlink(conn: &Connection, id: u32) {
+    if relink_entry(conn, id, &[]) {
+        println!("Unlinked log entry #{id} from its todos.");
+    } else {
+        eprintln!("Log entry #{id} not found.");
+        std::process::exit(1);
     }
 }
 
-pub fn rm(conn: &Connection, id: u32) {
+pub fn rm(conn: &Connection, id: u32, dry_run: bool) {
+    if dry_run {
+        match get_entry_by_id(conn, id) {
+            Some(e) => println!(
+                "Would delete log entry #{id} \"{}\" [{}] — {} on {}.",
+                e.name,
+                color::category(conn, &e.category),
+                format_duration(e.active_secs),
+                format_date(e.started_at),
+            ),
+            None => {
+                eprintln!("Log entry #{id} not found.");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
     if delete_entry(conn, id) {
         println!("Deleted log entry #{id}.");
     } else {
@@ -565,7 +1649,170 @@ pub fn rm(conn: &Connection, id: u32) {
     }
 }
 
-fn truncate(s: &str, max: usize) -> String {
+/// `tl archive --before DATE`: move entries started before the start of
+/// `date` out of the main table.
+pub fn archive(conn: &Connection, before: &str, dry_run: bool) {
+    let Ok(date) = chrono::NaiveDate::parse_from_str(before, "%Y-%m-%d") else {
+        eprintln!("Invalid date \"{before}\" (expected YYYY-MM-DD).");
+        std::process::exit(1);
+    };
+    let ids = archive_entries_before(conn, day_start_ts(date, day_start_hour(conn)), dry_run);
+    let verb = if dry_run { "Would archive" } else { "Archived" };
+    match ids.len() {
+        0 => println!("No entries before {date} to archive."),
+        1 => println!("{verb} 1 entry from before {date}: {}", format_id_list(&ids)),
+        n => println!("{verb} {n} entries from before {date}: {}", format_id_list(&ids)),
+    }
+}
+
+/// `, paused 2h ago` for a paused timer in the pickers, so one paused
+/// yesterday and forgotten stands out.
+fn paused_for(timer: &ActiveTimer, now_ts: i64) -> String {
+    timer.paused_at().map_or(String::new(), |ts| format!(", paused {}", humanize_ago(ts, now_ts)))
+}
+
+/// Rows shown at once by the type-to-filter pickers; the rest scroll.
+const PICKER_ROWS: usize = 15;
+
+/// Pick todos to link one at a time with a type-to-filter list, until "None"
+/// (nothing picked yet) or "Done" is chosen, or Esc is pressed. "+ Create new
+/// todo" adds a todo on the spot and links it.
+fn pick_todos(conn: &Connection, open_todos: Vec<TodoItem>) -> Vec<TodoItem> {
+    let mut left = open_todos;
+    let mut picked: Vec<TodoItem> = Vec::new();
+    loop {
+        let mut items: Vec<String> = left.iter().map(|t| format!("#{} {}", t.id, t.text)).collect();
+        items.push("+ Create new todo".into());
+        items.push(if picked.is_empty() { "None".into() } else { "Done".into() });
+        let prompt = if picked.is_empty() {
+            "Link to a todo? (type to filter)".to_string()
+        } else {
+            let ids: Vec<String> = picked.iter().map(|t| format!("#{}", t.id)).collect();
+            format!("Linked {} — link another? (Esc when done)", ids.join(", "))
+        };
+
+        // With no todos left to pick, default to finishing rather than creating.
+        let default = if left.is_empty() { items.len() - 1 } else { 0 };
+        let selection = FuzzySelect::new()
+            .with_prompt(prompt)
+            .items(&items)
+            .default(default)
+            .max_length(PICKER_ROWS)
+            .interact_opt()
+            .answer();
+        match selection {
+            Some(i) if i < left.len() => picked.push(left.remove(i)),
+            Some(i) if i == left.len() => {
+                let text: String = Input::new()
+                    .with_prompt("New todo")
+                    .allow_empty(true)
+                    .interact_text()
+                    .answer();
+                let text = normalize_name(&text);
+                if !text.is_empty() {
+                    let id = add_todo(conn, &text, Local::now().timestamp());
+                    println!("Added todo #{id}: {text}");
+                    picked.extend(get_todo_by_id(conn, id));
+                }
+            }
+            _ => return picked,
+        }
+    }
+}
+
+/// Pick a recently used category or type a new one.
+fn prompt_category(conn: &Connection) -> String {
+    let recent = list_recent_categories(conn, 10);
+    if !recent.is_empty() {
+        let mut items = recent.clone();
+        items.push("Enter new…".into());
+        let selection = Select::new()
+            .with_prompt("Category")
+            .items(&items)
+            .default(0)
+            .interact_opt()
+            .chosen();
+        if selection < recent.len() {
+            return recent[selection].clone();
+        }
+    }
+
+    canonical_category(conn, &prompt_name("Category", None))
+}
+
+/// Ask for a name, category or todo text, refusing blank answers, and tidy
+/// it with `normalize_name`.
+pub fn prompt_name(prompt: &str, default: Option<String>) -> String {
+    let mut input = Input::<String>::new()
+        .with_prompt(prompt)
+        .validate_with(|s: &String| if s.trim().is_empty() { Err("It can't be blank.") } else { Ok(()) });
+    if let Some(default) = default {
+        input = input.default(default);
+    }
+    normalize_name(&input.interact_text().answer())
+}
+
+/// `value` tidied with `normalize_name`; exits if nothing is left of it.
+pub fn require_name(value: &str, what: &str) -> String {
+    let value = normalize_name(value);
+    if value.is_empty() {
+        eprintln!("{what} can't be empty.");
+        std::process::exit(1);
+    }
+    value
+}
+
+/// Warn about logged entries that would double-count the same time and ask
+/// whether to save anyway. Returns `true` when there's nothing to warn about.
+/// An overlap that looks like `entry` logged a second time gets a pointed
+/// warning instead of the general one.
+fn confirm_overlaps(conn: &Connection, entry: &TimeEntry, overlapping: &[TimeEntry]) -> bool {
+    if overlapping.is_empty() {
+        return true;
+    }
+    if let Some(dup) = overlapping.iter().find(|e| is_near_duplicate(entry, e)) {
+        println!(
+            "Warning: a very similar entry already exists (#{} \"{}\" [{}] {} {} – {}).",
+            dup.id,
+            dup.name,
+            dup.category,
+            format_date(dup.started_at),
+            format_hm(conn, dup.started_at),
+            format_hm(conn, dup.ended_at),
+        );
+        return Confirm::new()
+            .with_prompt("Add anyway?")
+            .default(false)
+            .interact_opt()
+            .chosen();
+    }
+    println!("Warning: this overlaps {} logged {}:", overlapping.len(), if overlapping.len() == 1 { "entry" } else { "entries" });
+    for e in overlapping {
+        println!(
+            "  #{} \"{}\" [{}] {} {} – {}",
+            e.id,
+            e.name,
+            e.category,
+            format_date(e.started_at),
+            format_hm(conn, e.started_at),
+            format_hm(conn, e.ended_at),
+        );
+    }
+    Confirm::new()
+        .with_prompt("Save anyway?")
+        .default(false)
+        .interact_opt()
+        .chosen()
+}
+
+fn format_tags(tags: &[String]) -> String {
+    tags.iter()
+        .map(|t| format!("#{t}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub(crate) fn truncate(s: &str, max: usize) -> String {
     if s.len() > max {
         format!("{}…", &s[..max - 1])
     } else {
@@ -573,51 +1820,98 @@ fn truncate(s: &str, max: usize) -> String {
     }
 }
 
-pub fn pomodoro(conn: &Connection, minutes: u32) {
-    println!("--- Pomodoro Setup ({minutes} minutes) ---");
-    start(conn);
+/// Watch the active Pomodoro timer and ring when a work interval or a break is
+/// over. Starts a new Pomodoro timer first if none is active. The timer keeps
+/// running after this exits; stop it with `tl stop` as usual.
+pub fn pomodoro(conn: &Connection, work: u32, brk: u32) {
+    let existing = get_all_active(conn)
+        .into_iter()
+        .find(|t| get_pomodoro(conn, t.id.unwrap()).is_some());
+    let timer_id = match existing {
+        Some(t) => t.id.unwrap(),
+        None => {
+            println!("--- Pomodoro Setup ({work}m work / {brk}m break) ---");
+            start(conn, Some((work, brk)), None, None, false, Verbosity::Normal);
+            match get_running(conn) {
+                Some(t) if get_pomodoro(conn, t.id.unwrap()).is_some() => t.id.unwrap(),
+                _ => return,
+            }
+        }
+    };
 
-    let duration_secs = (minutes * 60) as i64;
     use std::io::{Write, stdout};
     let mut out = stdout();
+    let mut notified_work = -1;
+    let mut notified_break: Option<i64> = None;
 
     loop {
-        let now_ts = Local::now().timestamp();
-        
-        let timer = match get_running(conn) {
-            Some(t) => t,
-            None => {
-                println!("\nPomodoro cancelled or paused externally.");
+        let (timer, p) = match (get_active_by_id(conn, timer_id), get_pomodoro(conn, timer_id)) {
+            (Some(t), Some(p)) => (t, p),
+            _ => {
+                println!("\nPomodoro timer stopped.");
                 return;
             }
         };
 
+        let now_ts = Local::now().timestamp();
+        touch_heartbeat(conn, now_ts);
         let elapsed = now_ts - timer.started_at;
         let break_secs = total_break_secs(&timer.breaks, now_ts);
         let active_secs = (elapsed - break_secs).max(0);
+        let done = completed_pomodoros(active_secs, p.work_secs);
 
-        let remaining = duration_secs - active_secs;
-
-        if remaining <= 0 {
-            break;
+        if notified_work < 0 {
+            notified_work = done;
         }
 
-        let m = remaining / 60;
-        let s = remaining % 60;
-        print!("\r⏳ Pomodoro active: {:02}:{:02} remaining   ", m, s);
+        if timer.state == "running" {
+            notified_break = None;
+            if done > notified_work {
+                notified_work = done;
+                notify(&format!(
+                    "Pomodoro #{done} complete! Take a {}m break (tl pause).",
+                    p.break_secs / 60
+                ));
+            }
+            let remaining = p.work_secs - active_secs % p.work_secs;
+            print!(
+                "\r⏳ Pomodoro #{} — work: {:02}:{:02} remaining   ",
+                done + 1,
+                remaining / 60,
+                remaining % 60
+            );
+        } else {
+            let break_start = timer
+                .breaks
+                .last()
+                .filter(|b| b.end_ts == 0)
+                .map(|b| b.start_ts)
+                .unwrap_or(now_ts);
+            let on_break = now_ts - break_start;
+            if on_break >= p.break_secs && notified_break != Some(break_start) {
+                notified_break = Some(break_start);
+                notify("Break's over! Back to work (tl resume).");
+            }
+            let remaining = (p.break_secs - on_break).max(0);
+            print!(
+                "\r☕ Break: {:02}:{:02} remaining ({done} done)   ",
+                remaining / 60,
+                remaining % 60
+            );
+        }
         let _ = out.flush();
 
         std::thread::sleep(std::time::Duration::from_secs(1));
     }
-    
-    println!("\n✅ Pomodoro finished!");
+}
 
-    // Stop timer
-    stop(conn);
+/// Terminal bell plus a message, and a desktop notification on macOS.
+fn notify(msg: &str) {
+    println!("\x07\n{msg}");
 
     #[cfg(target_os = "macos")]
     let _ = std::process::Command::new("osascript")
         .arg("-e")
-        .arg("display notification \"Pomodoro session complete! Take a break.\" with title \"tl\" sound name \"Glass\"")
+        .arg(format!("display notification \"{msg}\" with title \"tl\" sound name \"Glass\""))
         .status();
 }
@@ -1,57 +1,195 @@
-use chrono::{Local, TimeZone};
+use chrono::Local;
+use dialoguer::Confirm;
 use rusqlite::Connection;
 
+use crate::duration::format_duration;
+use crate::prompt::Choice;
 use crate::state::*;
+use crate::timer::{prompt_name, require_name, since_ts};
 
-pub fn add(conn: &Connection, text: &str) {
+pub fn add(conn: &Connection, text: &str, estimate: Option<&str>, verbosity: Verbosity) {
+    let text = &require_name(text, "Todo text");
+    let estimate_secs = estimate.map(parse_estimate);
     let now_ts = Local::now().timestamp();
     let id = add_todo(conn, text, now_ts);
-    println!("Added todo #{id}: {text}");
+    if let Some(secs) = estimate_secs {
+        set_todo_estimate(conn, id, Some(secs));
+    }
+    match estimate_secs {
+        _ if verbosity.quiet() => println!("{id}"),
+        Some(secs) => println!("Added todo #{id}: {text} (estimate {})", format_duration(secs)),
+        None => println!("Added todo #{id}: {text}"),
+    }
+}
+
+/// Set a todo's time estimate; `0` clears it.
+pub fn estimate(conn: &Connection, id: u32, duration: &str) {
+    let secs = parse_estimate(duration);
+    let estimate_secs = (secs > 0).then_some(secs);
+    if !set_todo_estimate(conn, id, estimate_secs) {
+        eprintln!("Todo #{id} not found.");
+        std::process::exit(1);
+    }
+    match estimate_secs {
+        Some(secs) => println!("Estimate for todo #{id} set to {}.", format_duration(secs)),
+        None => println!("Estimate for todo #{id} cleared."),
+    }
 }
 
-pub fn list(conn: &Connection) {
-    let todos = list_todos(conn);
+fn parse_estimate(duration: &str) -> i64 {
+    match parse_duration(duration) {
+        Some(secs) => secs,
+        None => {
+            eprintln!("Invalid duration \"{duration}\" (try 2h, 1h30m or 45m).");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// List todos with tracked time, all time and today (running timers count
+/// as today, like in `tl today`). Archived todos are hidden unless
+/// `archived` is set; `done` keeps only completed ones, optionally just those
+/// completed `today` or this `week`.
+pub fn list(conn: &Connection, archived: bool, done: bool, today: bool, week: bool) {
+    let mut todos = if archived { list_todos(conn) } else { list_active_todos(conn) };
+    if done {
+        let since = since_ts(conn, today, week).unwrap_or(i64::MIN);
+        todos.retain(|t| t.done && t.completed_at.unwrap_or(i64::MIN) >= since);
+    }
     if todos.is_empty() {
-        println!("No todos.");
+        match (done, list_todos(conn).is_empty()) {
+            (true, _) => println!("No completed todos."),
+            (false, true) => println!("No todos yet — add one with `tl todo add <text>`."),
+            (false, false) => println!("No todos. Archived ones show with `tl todo list --archived`."),
+        }
         return;
     }
 
+    let today_start = today_start_ts(conn);
+    let (mut sum_total, mut sum_today) = (0, 0);
     for item in &todos {
         let check = if item.done { "x" } else { " " };
-        let date = Local
-            .timestamp_opt(item.created_at, 0)
-            .single()
-            .unwrap();
-        let entry_secs = get_todo_total_secs(conn, item.id);
-        let active_secs = get_active_todo_secs(conn, item.id);
-        let total_secs = entry_secs + active_secs;
-        let time_str = if total_secs > 0 {
-            format!("  {}", format_duration(total_secs))
-        } else {
-            String::new()
+        let (_, _, total_secs) = get_todo_combined_secs(conn, item.id);
+        let today_secs =
+            get_todo_secs_since(conn, item.id, today_start) + get_active_todo_secs_since(conn, item.id, today_start);
+        sum_total += total_secs;
+        sum_today += today_secs;
+        let mut time_str = match item.estimate_secs {
+            Some(est) => format!(
+                "  {} / {} est, {}%",
+                format_duration(total_secs),
+                format_duration(est),
+                total_secs * 100 / est.max(1),
+            ),
+            None if total_secs > 0 => format!("  {}", format_duration(total_secs)),
+            None => String::new(),
         };
+        if today_secs > 0 {
+            time_str.push_str(&format!(", {} today", format_duration(today_secs)));
+        }
+        let archived_str = if item.archived { "  (archived)" } else { "" };
+        let completed = item.completed_at.map_or(String::new(), |ts| format!(", done {}", format_date(ts)));
         println!(
-            "  [{check}] #{:<4} {}  ({}){time_str}",
+            "  [{check}] #{:<4} {}  ({}{completed}){time_str}{archived_str}",
             item.id,
             item.text,
-            date.format("%Y-%m-%d"),
+            format_date(item.created_at),
         );
     }
 
     let done = todos.iter().filter(|t| t.done).count();
     let total = todos.len();
-    println!("\n  {done}/{total} completed");
+    println!(
+        "\n  {done}/{total} completed · {} tracked, {} today",
+        format_duration(sum_total),
+        format_duration(sum_today),
+    );
 }
 
-pub fn done(conn: &Connection, id: u32) {
-    if mark_todo_done(conn, id) {
-        println!("Marked todo #{id} as done.");
+/// Archive one todo, or with `done` every completed todo.
+pub fn archive(conn: &Connection, id: Option<u32>, done: bool, dry_run: bool) {
+    if done {
+        let ids = archive_done_todos(conn, dry_run);
+        let n = ids.len();
+        let verb = if dry_run { "Would archive" } else { "Archived" };
+        let list = if n == 0 { String::new() } else { format!(": {}", format_id_list(&ids)) };
+        println!("{verb} {n} completed todo{}{list}.", if n == 1 { "" } else { "s" });
+        return;
+    }
+    let Some(id) = id else {
+        eprintln!("Give a todo ID or --done.");
+        std::process::exit(1);
+    };
+    if dry_run {
+        match get_todo_by_id(conn, id) {
+            Some(todo) => println!("Would archive todo #{id}: {}", todo.text),
+            None => {
+                eprintln!("Todo #{id} not found.");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+    if set_todo_archived(conn, id, true) {
+        println!("Archived todo #{id}.");
+    } else {
+        eprintln!("Todo #{id} not found.");
+        std::process::exit(1);
+    }
+}
+
+pub fn unarchive(conn: &Connection, id: u32) {
+    if set_todo_archived(conn, id, false) {
+        println!("Restored todo #{id} from the archive.");
     } else {
         eprintln!("Todo #{id} not found.");
         std::process::exit(1);
     }
 }
 
+/// Mark the given todos done, plus those linked to log entry `from_entry`,
+/// in one go. Exits with an error if any of them wasn't found.
+pub fn done(conn: &Connection, given: &[u32], from_entry: Option<u32>, dry_run: bool) {
+    let mut ids: Vec<u32> = Vec::new();
+    for &id in given {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    if let Some(entry_id) = from_entry {
+        let Some(entry) = get_entry_by_id(conn, entry_id) else {
+            eprintln!("Log entry #{entry_id} not found.");
+            std::process::exit(1);
+        };
+        let linked = entry_todos(conn, &entry);
+        if linked.is_empty() {
+            eprintln!("Log entry #{entry_id} isn't linked to a todo.");
+            std::process::exit(1);
+        }
+        ids.extend(linked.into_iter().filter(|id| !ids.contains(id)).collect::<Vec<_>>());
+    }
+
+    let results = if dry_run {
+        ids.iter().map(|&id| (id, get_todo_by_id(conn, id).is_some())).collect()
+    } else {
+        mark_todos_done(conn, &ids)
+    };
+    let mut missing = false;
+    for (id, found) in results {
+        if found && dry_run {
+            println!("Would mark todo #{id} as done.");
+        } else if found {
+            println!("Marked todo #{id} as done.");
+        } else {
+            eprintln!("Todo #{id} not found.");
+            missing = true;
+        }
+    }
+    if missing {
+        std::process::exit(1);
+    }
+}
+
 pub fn undo(conn: &Connection, id: u32) {
     if unmark_todo_done(conn, id) {
         println!("Marked todo #{id} as not done.");
@@ -62,6 +200,7 @@ pub fn undo(conn: &Connection, id: u32) {
 }
 
 pub fn edit(conn: &Connection, id: u32, text: &str) {
+    let text = &require_name(text, "Todo text");
     if edit_todo(conn, id, text) {
         println!("Edited todo #{id}: {text}");
     } else {
@@ -70,7 +209,77 @@ pub fn edit(conn: &Connection, id: u32, text: &str) {
     }
 }
 
-pub fn rm(conn: &Connection, id: u32) {
+/// Fold todo `from` into `into`, moving its tracked time and links. Asks for
+/// the merged todo's text, defaulting to the target's.
+pub fn merge(conn: &Connection, from: u32, into: u32, dry_run: bool) {
+    if from == into {
+        eprintln!("Can't merge a todo into itself.");
+        std::process::exit(1);
+    }
+    let find = |id: u32| {
+        get_todo_by_id(conn, id).unwrap_or_else(|| {
+            eprintln!("Todo #{id} not found.");
+            std::process::exit(1);
+        })
+    };
+    let (source, target) = (find(from), find(into));
+    if dry_run {
+        let (entries, timers) = count_todo_links(conn, from);
+        println!(
+            "Would merge #{from} \"{}\" into #{into} \"{}\", moving {entries} time {} and {timers} active {} over and deleting #{from}.",
+            source.text,
+            target.text,
+            if entries == 1 { "entry" } else { "entries" },
+            if timers == 1 { "timer" } else { "timers" },
+        );
+        return;
+    }
+
+    println!("Merging #{from} \"{}\" into #{into} \"{}\".", source.text, target.text);
+    let text = prompt_name("Text for the merged todo", Some(target.text));
+
+    if !merge_todos(conn, from, into, &text) {
+        eprintln!("Todo #{from} or #{into} not found.");
+        std::process::exit(1);
+    }
+    println!(
+        "Merged todo #{from} into #{into}: {text} — {} tracked.",
+        format_duration(get_todo_combined_secs(conn, into).2),
+    );
+}
+
+pub fn rm(conn: &Connection, id: u32, dry_run: bool) {
+    let Some(todo) = get_todo_by_id(conn, id) else {
+        eprintln!("Todo #{id} not found.");
+        std::process::exit(1);
+    };
+
+    let (entries, timers) = count_todo_links(conn, id);
+    if dry_run {
+        println!(
+            "Would remove todo #{id} \"{}\", unlinking {entries} time {} and {timers} active {}.",
+            todo.text,
+            if entries == 1 { "entry" } else { "entries" },
+            if timers == 1 { "timer" } else { "timers" },
+        );
+        return;
+    }
+    if entries + timers > 0 {
+        println!(
+            "Todo #{id} is linked to {entries} time {} and {timers} active {}; they will be kept but unlinked.",
+            if entries == 1 { "entry" } else { "entries" },
+            if timers == 1 { "timer" } else { "timers" },
+        );
+        let confirm = Confirm::new()
+            .with_prompt(format!("Remove todo #{id}?"))
+            .default(false)
+            .interact_opt()
+            .chosen();
+        if !confirm {
+            return;
+        }
+    }
+
     if remove_todo(conn, id) {
         println!("Removed todo #{id}.");
     } else {
@@ -1,7 +1,7 @@
 use std::io;
 use std::time::Duration;
 
-use chrono::{Local, TimeZone};
+use chrono::Local;
 use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
 use crossterm::terminal::{
     disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
@@ -11,6 +11,7 @@ use ratatui::prelude::*;
 use ratatui::widgets::*;
 use rusqlite::Connection;
 
+use crate::duration::format_duration;
 use crate::state::*;
 
 // ---------------------------------------------------------------------------
@@ -40,6 +41,7 @@ enum InputTarget {
     TodoEdit(u32),
     LogEditName(u32),
     LogEditCategory(u32),
+    LogFilter,
 }
 
 #[derive(PartialEq, Clone)]
@@ -71,6 +73,9 @@ struct App {
     // pick list
     pick_items: Vec<(u32, String)>, // (id, label)
     pick_sel: usize,
+    // log tab: `--today` / `--week` cutoff and the text typed after `/`
+    log_since: Option<i64>,
+    log_filter: String,
     // flash message
     flash: Option<(String, std::time::Instant)>,
 }
@@ -88,10 +93,21 @@ impl App {
             new_timer_todo_id: None,
             pick_items: Vec::new(),
             pick_sel: 0,
+            log_since: None,
+            log_filter: String::new(),
             flash: None,
         }
     }
 
+    /// The log filter in effect, following the input box while it's open.
+    fn log_query(&self) -> &str {
+        if self.mode == Mode::Input(InputTarget::LogFilter) {
+            &self.input_buf
+        } else {
+            &self.log_filter
+        }
+    }
+
     fn flash(&mut self, msg: impl Into<String>) {
         self.flash = Some((msg.into(), std::time::Instant::now()));
     }
@@ -118,45 +134,57 @@ impl App {
 // ---------------------------------------------------------------------------
 
 pub fn run(conn: &Connection) {
+    run_app(conn, App::new());
+}
+
+/// `tl log --interactive`: the dashboard opened on the Log tab, limited to
+/// entries since `since_ts`.
+pub fn run_log(conn: &Connection, since_ts: Option<i64>) {
+    let mut app = App::new();
+    app.tab = Tab::Log;
+    app.log_since = since_ts;
+    run_app(conn, app);
+}
+
+fn run_app(conn: &Connection, mut app: App) {
     enable_raw_mode().unwrap();
     let mut stdout = io::stdout();
     execute!(stdout, EnterAlternateScreen).unwrap();
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend).unwrap();
 
-    let mut app = App::new();
-
     loop {
+        touch_heartbeat(conn, Local::now().timestamp());
         terminal.draw(|f| ui(f, conn, &app)).unwrap();
 
-        if event::poll(Duration::from_millis(250)).unwrap() {
-            if let Event::Key(key) = event::read().unwrap() {
-                if key.kind != KeyEventKind::Press {
-                    continue;
-                }
-                if key.code == KeyCode::Char('c')
-                    && key.modifiers.contains(KeyModifiers::CONTROL)
-                {
-                    break;
-                }
-                match &app.mode {
-                    Mode::Normal => {
-                        if handle_normal(conn, &mut app, key.code) {
-                            break;
-                        }
+        if event::poll(Duration::from_millis(250)).unwrap()
+            && let Event::Key(key) = event::read().unwrap()
+        {
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if key.code == KeyCode::Char('c')
+                && key.modifiers.contains(KeyModifiers::CONTROL)
+            {
+                break;
+            }
+            match &app.mode {
+                Mode::Normal => {
+                    if handle_normal(conn, &mut app, key.code) {
+                        break;
                     }
-                    Mode::Input(_) => handle_input(conn, &mut app, key.code),
-                    Mode::Confirm(_) => handle_confirm(conn, &mut app, key.code),
-                    Mode::Pick(_) => handle_pick(conn, &mut app, key.code),
                 }
+                Mode::Input(_) => handle_input(conn, &mut app, key.code),
+                Mode::Confirm(_) => handle_confirm(conn, &mut app, key.code),
+                Mode::Pick(_) => handle_pick(conn, &mut app, key.code),
             }
         }
 
         // expire flash after 3s
-        if let Some((_, t)) = &app.flash {
-            if t.elapsed() > Duration::from_secs(3) {
-                app.flash = None;
-            }
+        if let Some((_, t)) = &app.flash
+            && t.elapsed() > Duration::from_secs(3)
+        {
+            app.flash = None;
         }
     }
 
@@ -204,7 +232,7 @@ fn handle_normal(conn: &Connection, app: &mut App, key: KeyCode) -> bool {
         // s = start new timer
         KeyCode::Char('s') if app.tab == Tab::Timers => {
             // first offer todo linking
-            let open_todos: Vec<_> = list_todos(conn).into_iter().filter(|t| !t.done).collect();
+            let open_todos: Vec<_> = list_active_todos(conn).into_iter().filter(|t| !t.done).collect();
             if !open_todos.is_empty() {
                 app.pick_items = open_todos
                     .iter()
@@ -251,16 +279,16 @@ fn handle_normal(conn: &Connection, app: &mut App, key: KeyCode) -> bool {
         // w = switch — pause running, resume selected paused
         KeyCode::Char('w') if app.tab == Tab::Timers => {
             let all = get_all_active(conn);
-            if let Some(timer) = all.get(app.timer_sel) {
-                if timer.state == "paused" {
-                    let id = timer.id.unwrap();
-                    // pause current running
-                    if let Some(running) = get_running(conn) {
-                        action_pause(conn, running.id.unwrap());
-                    }
-                    action_resume(conn, id);
-                    app.flash("Switched timer");
+            if let Some(timer) = all.get(app.timer_sel)
+                && timer.state == "paused"
+            {
+                let id = timer.id.unwrap();
+                // pause current running
+                if let Some(running) = get_running(conn) {
+                    action_pause(conn, running.id.unwrap());
                 }
+                action_resume(conn, id);
+                app.flash("Switched timer");
             }
         }
         // r = restart (start from last entry)
@@ -296,16 +324,21 @@ fn handle_normal(conn: &Connection, app: &mut App, key: KeyCode) -> bool {
         }
 
         // ---------- Log actions ----------
+        // / = filter by name, category or tag
+        KeyCode::Char('/') if app.tab == Tab::Log => {
+            app.input_buf = app.log_filter.clone();
+            app.mode = Mode::Input(InputTarget::LogFilter);
+        }
         // d = delete log entry
         KeyCode::Char('d') if app.tab == Tab::Log => {
-            let entries = query_entries(conn, None);
+            let entries = log_entries(conn, app);
             if let Some(entry) = entries.get(app.log_sel) {
                 app.mode = Mode::Confirm(ConfirmAction::DeleteLog(entry.id));
             }
         }
         // e = edit log entry name
         KeyCode::Char('e') if app.tab == Tab::Log => {
-            let entries = query_entries(conn, None);
+            let entries = log_entries(conn, app);
             if let Some(entry) = entries.get(app.log_sel) {
                 app.input_buf = entry.name.clone();
                 app.mode = Mode::Input(InputTarget::LogEditName(entry.id));
@@ -313,7 +346,7 @@ fn handle_normal(conn: &Connection, app: &mut App, key: KeyCode) -> bool {
         }
         // c = edit log entry category
         KeyCode::Char('c') if app.tab == Tab::Log => {
-            let entries = query_entries(conn, None);
+            let entries = log_entries(conn, app);
             if let Some(entry) = entries.get(app.log_sel) {
                 app.input_buf = entry.category.clone();
                 app.mode = Mode::Input(InputTarget::LogEditCategory(entry.id));
@@ -328,14 +361,14 @@ fn handle_normal(conn: &Connection, app: &mut App, key: KeyCode) -> bool {
         }
         // Enter / space = toggle done
         KeyCode::Enter | KeyCode::Char(' ') if app.tab == Tab::Todos => {
-            let todos = list_todos(conn);
+            let todos = list_active_todos(conn);
             if let Some(todo) = todos.get(app.todo_sel) {
                 app.mode = Mode::Confirm(ConfirmAction::ToggleTodo(todo.id, todo.done));
             }
         }
         // e = edit todo text
         KeyCode::Char('e') if app.tab == Tab::Todos => {
-            let todos = list_todos(conn);
+            let todos = list_active_todos(conn);
             if let Some(todo) = todos.get(app.todo_sel) {
                 app.input_buf = todo.text.clone();
                 app.mode = Mode::Input(InputTarget::TodoEdit(todo.id));
@@ -343,7 +376,7 @@ fn handle_normal(conn: &Connection, app: &mut App, key: KeyCode) -> bool {
         }
         // d = delete todo
         KeyCode::Char('d') if app.tab == Tab::Todos => {
-            let todos = list_todos(conn);
+            let todos = list_active_todos(conn);
             if let Some(todo) = todos.get(app.todo_sel) {
                 app.mode = Mode::Confirm(ConfirmAction::DeleteTodo(todo.id));
             }
@@ -364,7 +397,7 @@ fn handle_input(conn: &Connection, app: &mut App, key: KeyCode) {
                 Mode::Input(t) => t.clone(),
                 _ => return,
             };
-            let text = app.input_buf.trim().to_string();
+            let text = normalize_name(&app.input_buf);
             match target {
                 InputTarget::TimerName => {
                     if text.is_empty() && app.new_timer_todo_id.is_none() {
@@ -396,11 +429,12 @@ fn handle_input(conn: &Connection, app: &mut App, key: KeyCode) {
                     } else {
                         app.new_timer_name.clone()
                     };
+                    let category = canonical_category(conn, &text);
                     let now = Local::now();
                     let timer = ActiveTimer {
                         id: None,
                         name: name.clone(),
-                        category: text.clone(),
+                        category: category.clone(),
                         started_at: now.timestamp(),
                         state: "running".into(),
                         breaks: vec![],
@@ -408,7 +442,7 @@ fn handle_input(conn: &Connection, app: &mut App, key: KeyCode) {
                         last_modified: 0,
                     };
                     insert_active(conn, &timer);
-                    app.flash(format!("Started \"{name}\" [{text}]"));
+                    app.flash(format!("Started \"{name}\" [{category}]"));
                     app.mode = Mode::Normal;
                 }
                 InputTarget::TodoAdd => {
@@ -431,32 +465,43 @@ fn handle_input(conn: &Connection, app: &mut App, key: KeyCode) {
                     app.mode = Mode::Normal;
                 }
                 InputTarget::LogEditName(id) => {
-                    if !text.is_empty() {
-                        if let Some(mut entry) = get_entry_by_id(conn, id) {
-                            entry.name = text;
-                            update_entry(conn, &entry);
-                            app.flash(format!("Updated entry #{id} name"));
-                        }
+                    if !text.is_empty()
+                        && let Some(mut entry) = get_entry_by_id(conn, id)
+                    {
+                        entry.name = text;
+                        update_entry(conn, &entry);
+                        app.flash(format!("Updated entry #{id} name"));
                     }
                     app.mode = Mode::Normal;
                 }
                 InputTarget::LogEditCategory(id) => {
-                    if !text.is_empty() {
-                        if let Some(mut entry) = get_entry_by_id(conn, id) {
-                            entry.category = text;
-                            update_entry(conn, &entry);
-                            app.flash(format!("Updated entry #{id} category"));
-                        }
+                    if !text.is_empty()
+                        && let Some(mut entry) = get_entry_by_id(conn, id)
+                    {
+                        entry.category = canonical_category(conn, &text);
+                        update_entry(conn, &entry);
+                        app.flash(format!("Updated entry #{id} category"));
                     }
                     app.mode = Mode::Normal;
                 }
+                InputTarget::LogFilter => {
+                    app.log_filter = text;
+                    app.log_sel = 0;
+                    app.mode = Mode::Normal;
+                }
             }
         }
         KeyCode::Backspace => {
             app.input_buf.pop();
+            if app.mode == Mode::Input(InputTarget::LogFilter) {
+                app.log_sel = 0;
+            }
         }
         KeyCode::Char(c) => {
             app.input_buf.push(c);
+            if app.mode == Mode::Input(InputTarget::LogFilter) {
+                app.log_sel = 0;
+            }
         }
         _ => {}
     }
@@ -481,7 +526,7 @@ fn handle_confirm(conn: &Connection, app: &mut App, key: KeyCode) {
                 ConfirmAction::DeleteLog(id) => {
                     delete_entry(conn, id);
                     app.flash(format!("Deleted log entry #{id}"));
-                    let entries = query_entries(conn, None);
+                    let entries = log_entries(conn, app);
                     if app.log_sel >= entries.len() && !entries.is_empty() {
                         app.log_sel = entries.len() - 1;
                     }
@@ -489,7 +534,7 @@ fn handle_confirm(conn: &Connection, app: &mut App, key: KeyCode) {
                 ConfirmAction::DeleteTodo(id) => {
                     remove_todo(conn, id);
                     app.flash(format!("Removed todo #{id}"));
-                    let todos = list_todos(conn);
+                    let todos = list_active_todos(conn);
                     if app.todo_sel >= todos.len() && !todos.is_empty() {
                         app.todo_sel = todos.len() - 1;
                     }
@@ -518,10 +563,8 @@ fn handle_pick(conn: &Connection, app: &mut App, key: KeyCode) {
         KeyCode::Esc => {
             app.mode = Mode::Normal;
         }
-        KeyCode::Down | KeyCode::Char('j') => {
-            if app.pick_sel + 1 < app.pick_items.len() {
-                app.pick_sel += 1;
-            }
+        KeyCode::Down | KeyCode::Char('j') if app.pick_sel + 1 < app.pick_items.len() => {
+            app.pick_sel += 1;
         }
         KeyCode::Up | KeyCode::Char('k') => {
             app.pick_sel = app.pick_sel.saturating_sub(1);
@@ -564,11 +607,7 @@ fn action_pause(conn: &Connection, id: u32) {
         None => return,
     };
     let now_ts = Local::now().timestamp();
-    timer.state = "paused".into();
-    timer.breaks.push(proto::Break {
-        start_ts: now_ts,
-        end_ts: 0,
-    });
+    timer.pause(now_ts, "");
     update_active(conn, &timer);
 }
 
@@ -578,12 +617,7 @@ fn action_resume(conn: &Connection, id: u32) {
         None => return,
     };
     let now_ts = Local::now().timestamp();
-    timer.state = "running".into();
-    if let Some(last) = timer.breaks.last_mut() {
-        if last.end_ts == 0 {
-            last.end_ts = now_ts;
-        }
-    }
+    timer.resume(now_ts);
     update_active(conn, &timer);
 }
 
@@ -592,24 +626,8 @@ fn action_stop(conn: &Connection, id: u32) {
         Some(t) => t,
         None => return,
     };
-    let now_ts = Local::now().timestamp();
-    let elapsed = now_ts - timer.started_at;
-    let break_secs = total_break_secs(&timer.breaks, now_ts);
-    let active_secs = (elapsed - break_secs).max(0);
-
-    let entry = TimeEntry {
-        id: 0,
-        name: timer.name,
-        category: timer.category,
-        started_at: timer.started_at,
-        ended_at: now_ts,
-        active_secs,
-        breaks: timer.breaks,
-        todo_id: timer.todo_id,
-        last_modified: 0,
-    };
-    insert_entry(conn, &entry);
-    clear_active(conn, id);
+    let entry = timer.to_entry(Local::now().timestamp(), vec![]);
+    finish_timer(conn, id, &entry);
 }
 
 // ---------------------------------------------------------------------------
@@ -650,7 +668,7 @@ fn ui(f: &mut Frame, conn: &Connection, app: &App) {
     // Content
     match app.tab {
         Tab::Timers => render_timers(f, conn, chunks[1], app.timer_sel),
-        Tab::Log => render_log(f, conn, chunks[1], app.log_sel),
+        Tab::Log => render_log(f, conn, chunks[1], app),
         Tab::Todos => render_todos(f, conn, chunks[1], app.todo_sel),
     }
 
@@ -681,7 +699,9 @@ fn ui(f: &mut Frame, conn: &Connection, app: &App) {
                 Span::raw(" quit"),
             ]),
             Tab::Log => Line::from(vec![
-                Span::styled(" e", Style::default().fg(Color::Yellow)),
+                Span::styled(" /", Style::default().fg(Color::Yellow)),
+                Span::raw(" filter  "),
+                Span::styled("e", Style::default().fg(Color::Yellow)),
                 Span::raw(" edit name  "),
                 Span::styled("c", Style::default().fg(Color::Yellow)),
                 Span::raw(" edit category  "),
@@ -744,10 +764,7 @@ fn render_timers(f: &mut Frame, conn: &Connection, area: Rect, sel: usize) {
         let elapsed = now_ts - timer.started_at;
         let break_secs = total_break_secs(&timer.breaks, now_ts);
         let active_secs = (elapsed - break_secs).max(0);
-        let started = Local
-            .timestamp_opt(timer.started_at, 0)
-            .single()
-            .unwrap();
+        let started = local_time(timer.started_at);
 
         let (state_str, state_color) = if timer.state == "running" {
             ("▶ RUNNING", Color::Green)
@@ -756,10 +773,7 @@ fn render_timers(f: &mut Frame, conn: &Connection, area: Rect, sel: usize) {
         };
 
         let todo_str = if let Some(tid) = timer.todo_id {
-            let todos = list_todos(conn);
-            todos
-                .iter()
-                .find(|t| t.id == tid)
+            get_todo_by_id(conn, tid)
                 .map(|t| format!("→ #{} {}", tid, t.text))
                 .unwrap_or_else(|| format!("→ #{tid}"))
         } else {
@@ -835,8 +849,24 @@ fn render_timers(f: &mut Frame, conn: &Connection, area: Rect, sel: usize) {
     f.render_stateful_widget(table, area, &mut state);
 }
 
-fn render_log(f: &mut Frame, conn: &Connection, area: Rect, sel: usize) {
-    let entries = query_entries(conn, None);
+/// Entries shown on the Log tab: those since `--today` / `--week`, narrowed
+/// to the ones whose name, category or a tag contains the filter text.
+fn log_entries(conn: &Connection, app: &App) -> Vec<TimeEntry> {
+    let needle = app.log_query().trim().to_lowercase();
+    let mut entries = query_entries(conn, app.log_since);
+    if !needle.is_empty() {
+        entries.retain(|e| {
+            e.name.to_lowercase().contains(&needle)
+                || e.category.to_lowercase().contains(&needle)
+                || e.tags.iter().any(|t| t.to_lowercase().contains(&needle))
+        });
+    }
+    entries
+}
+
+fn render_log(f: &mut Frame, conn: &Connection, area: Rect, app: &App) {
+    let entries = log_entries(conn, app);
+    let sel = app.log_sel;
     let header_style = Style::default()
         .fg(Color::DarkGray)
         .add_modifier(Modifier::BOLD);
@@ -848,7 +878,7 @@ fn render_log(f: &mut Frame, conn: &Connection, area: Rect, sel: usize) {
         let break_secs = total_break_secs(&e.breaks, e.ended_at);
         total_active += e.active_secs;
         total_breaks += break_secs;
-        let date = Local.timestamp_opt(e.started_at, 0).single().unwrap();
+        let date = local_time(e.started_at);
         let todo_col = match e.todo_id {
             Some(tid) => format!("#{tid}"),
             None => String::new(),
@@ -902,7 +932,10 @@ fn render_log(f: &mut Frame, conn: &Connection, area: Rect, sel: usize) {
         .block(
             Block::default()
                 .borders(Borders::ALL)
-                .title(format!(" Log ({} entries) ", entries.len())),
+                .title(match app.log_query().trim() {
+                    "" => format!(" Log ({} entries) ", entries.len()),
+                    query => format!(" Log ({} entries matching \"{query}\") ", entries.len()),
+                }),
         )
         .row_highlight_style(Style::default().bg(Color::DarkGray));
 
@@ -915,7 +948,7 @@ fn render_log(f: &mut Frame, conn: &Connection, area: Rect, sel: usize) {
 }
 
 fn render_todos(f: &mut Frame, conn: &Connection, area: Rect, sel: usize) {
-    let todos = list_todos(conn);
+    let todos = list_active_todos(conn);
     let header_style = Style::default()
         .fg(Color::DarkGray)
         .add_modifier(Modifier::BOLD);
@@ -928,13 +961,8 @@ fn render_todos(f: &mut Frame, conn: &Connection, area: Rect, sel: usize) {
             (" ", Style::default().fg(Color::White))
         };
 
-        let date = Local
-            .timestamp_opt(item.created_at, 0)
-            .single()
-            .unwrap();
-        let entry_secs = get_todo_total_secs(conn, item.id);
-        let active_secs = get_active_todo_secs(conn, item.id);
-        let total_secs = entry_secs + active_secs;
+        let date = local_time(item.created_at);
+        let (_, _, total_secs) = get_todo_combined_secs(conn, item.id);
         let time_str = if total_secs > 0 {
             format_duration(total_secs)
         } else {
@@ -1001,6 +1029,7 @@ fn render_input_popup(f: &mut Frame, target: &InputTarget, buf: &str) {
         InputTarget::TodoEdit(_) => " Edit Todo (Enter to confirm, Esc to cancel) ",
         InputTarget::LogEditName(_) => " Edit Name (Enter to confirm, Esc to cancel) ",
         InputTarget::LogEditCategory(_) => " Edit Category (Enter to confirm, Esc to cancel) ",
+        InputTarget::LogFilter => " Filter (Enter to keep, Esc to cancel) ",
     };
 
     let area = popup_area(f, 50, 3);